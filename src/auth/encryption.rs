@@ -0,0 +1,385 @@
+use std::io::IsTerminal;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AuthConfig;
+use super::keyring_storage::KeyringError;
+
+/// Marker identifying an encrypted token file
+const FORMAT: &str = "workspace-cli-encrypted-v1";
+
+/// PBKDF2-HMAC-SHA256 rounds for new files (OWASP 2023 recommendation)
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Where the token encryption key comes from
+#[derive(Debug, Clone)]
+enum KeySource {
+    Passphrase(String),
+    KeyFile(PathBuf),
+    /// Ask on the terminal the first time a key is needed
+    Prompt,
+}
+
+/// On-disk form of an encrypted token file
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+struct State {
+    source: Option<KeySource>,
+    /// Passphrase or key file contents, once read
+    secret: Option<Vec<u8>>,
+    /// Derived keys by (salt, iterations), so each file costs one derivation per process
+    keys: Vec<(Vec<u8>, u32, [u8; KEY_LEN])>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    source: None,
+    secret: None,
+    keys: Vec::new(),
+});
+
+/// Held while the secret is read, which may mean waiting on a terminal prompt
+static SECRET_READ: Mutex<()> = Mutex::new(());
+
+/// Install the token encryption settings. Call once at startup. Encryption is
+/// on when a passphrase or key file is configured, or `token_encryption` is set.
+pub fn configure(auth: &AuthConfig) {
+    let source = if let Some(ref passphrase) = auth.token_passphrase {
+        Some(KeySource::Passphrase(passphrase.clone()))
+    } else if let Some(ref path) = auth.token_key_file {
+        Some(KeySource::KeyFile(path.clone()))
+    } else if auth.token_encryption {
+        Some(KeySource::Prompt)
+    } else {
+        None
+    };
+
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    state.source = source;
+    state.secret = None;
+    state.keys.clear();
+}
+
+/// Whether token files are written encrypted
+pub fn enabled() -> bool {
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).source.is_some()
+}
+
+/// Whether file contents are an encrypted token file
+pub fn is_encrypted(data: &[u8]) -> bool {
+    serde_json::from_slice::<Envelope>(data).is_ok_and(|e| e.format == FORMAT)
+}
+
+/// Read a token file, decrypting it when encrypted. With encryption enabled a
+/// plaintext file is migrated: it is rewritten encrypted before returning.
+pub fn read_file(path: &Path) -> Result<Vec<u8>, KeyringError> {
+    let data = std::fs::read(path).map_err(|e| KeyringError::RetrieveFailed(format!(
+        "Failed to read token file at {}: {}",
+        path.display(),
+        e
+    )))?;
+
+    if is_encrypted(&data) {
+        return decrypt(&data).map_err(|e| KeyringError::Encryption(format!("{}: {}", path.display(), e)));
+    }
+    if enabled() {
+        write_file(path, &data)?;
+    }
+    Ok(data)
+}
+
+/// Write a token file (encrypted when enabled) readable only by the current user
+pub fn write_file(path: &Path, plaintext: &[u8]) -> Result<(), KeyringError> {
+    let data = if enabled() {
+        encrypt(plaintext).map_err(KeyringError::Encryption)?
+    } else {
+        plaintext.to_vec()
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| KeyringError::StoreFailed(e.to_string()))?;
+    }
+    std::fs::write(path, data)
+        .map_err(|e| KeyringError::StoreFailed(e.to_string()))?;
+
+    // Set file permissions to user-only read/write (Unix only)
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = std::fs::Permissions::from_mode(0o600);
+        std::fs::set_permissions(path, perms)
+            .map_err(|e| KeyringError::StoreFailed(format!("Failed to set file permissions: {}", e)))?;
+    }
+    Ok(())
+}
+
+fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let rng = SystemRandom::new();
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut nonce).map_err(|_| "No system randomness available".to_string())?;
+
+    // Reuse an already derived key (the nonce is what must be unique) so
+    // rewriting files does not pay for key derivation again
+    let reused = STATE.lock().unwrap_or_else(|e| e.into_inner()).keys.iter()
+        .find(|(_, i, _)| *i == PBKDF2_ITERATIONS)
+        .map(|(s, _, _)| s.clone());
+    let salt = match reused {
+        Some(salt) => salt,
+        None => {
+            let mut salt = vec![0u8; SALT_LEN];
+            rng.fill(&mut salt).map_err(|_| "No system randomness available".to_string())?;
+            salt
+        }
+    };
+
+    let key = derive_key(&salt, PBKDF2_ITERATIONS)?;
+    let mut ciphertext = plaintext.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(FORMAT), &mut ciphertext)
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let envelope = Envelope {
+        format: FORMAT.to_string(),
+        kdf: "pbkdf2-sha256".to_string(),
+        iterations: PBKDF2_ITERATIONS,
+        salt: b64.encode(salt),
+        nonce: b64.encode(nonce),
+        ciphertext: b64.encode(ciphertext),
+    };
+    serde_json::to_vec_pretty(&envelope).map_err(|e| e.to_string())
+}
+
+fn decrypt(data: &[u8]) -> Result<Vec<u8>, String> {
+    let envelope: Envelope = serde_json::from_slice(data).map_err(|e| e.to_string())?;
+    if envelope.kdf != "pbkdf2-sha256" {
+        return Err(format!("Unsupported key derivation '{}'", envelope.kdf));
+    }
+    if !enabled() {
+        return Err("tokens are encrypted. Set WORKSPACE_TOKEN_PASSPHRASE or WORKSPACE_TOKEN_KEY_FILE".to_string());
+    }
+
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let corrupt = |_| "Encrypted token file is corrupted".to_string();
+    let salt = b64.decode(&envelope.salt).map_err(corrupt)?;
+    let nonce = b64.decode(&envelope.nonce).map_err(corrupt)?;
+    let mut ciphertext = b64.decode(&envelope.ciphertext).map_err(corrupt)?;
+    let nonce = Nonce::try_assume_unique_for_key(&nonce)
+        .map_err(|_| "Encrypted token file is corrupted".to_string())?;
+
+    let key = derive_key(&salt, envelope.iterations)?;
+    let plaintext = key.open_in_place(nonce, Aad::from(FORMAT), &mut ciphertext)
+        .map_err(|_| "Cannot decrypt tokens: wrong passphrase or key file".to_string())?;
+    Ok(plaintext.to_vec())
+}
+
+/// Derive (or reuse) the key for a salt. `STATE` is only held to look up and
+/// store results: prompting and PBKDF2 run unlocked, so other threads can
+/// still ask [`enabled`] meanwhile.
+fn derive_key(salt: &[u8], iterations: u32) -> Result<LessSafeKey, String> {
+    let rounds = NonZeroU32::new(iterations).ok_or_else(|| "Invalid iteration count".to_string())?;
+    let cached = STATE.lock().unwrap_or_else(|e| e.into_inner()).keys.iter()
+        .find(|(s, i, _)| s == salt && *i == iterations)
+        .map(|(_, _, key)| *key);
+
+    let key = match cached {
+        Some(key) => key,
+        None => {
+            let secret = secret()?;
+            let mut key = [0u8; KEY_LEN];
+            pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, rounds, salt, &secret, &mut key);
+            let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+            if !state.keys.iter().any(|(s, i, _)| s == salt && *i == iterations) {
+                state.keys.push((salt.to_vec(), iterations, key));
+            }
+            key
+        }
+    };
+
+    let unbound = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| "Invalid key".to_string())?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+/// The passphrase or key file contents, read on first use. Reads are
+/// serialized by `SECRET_READ` (not `STATE`) so concurrent callers share one
+/// prompt instead of each asking for the passphrase.
+fn secret() -> Result<Vec<u8>, String> {
+    let _reading = SECRET_READ.lock().unwrap_or_else(|e| e.into_inner());
+    let (secret, source) = {
+        let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        (state.secret.clone(), state.source.clone())
+    };
+    if let Some(secret) = secret {
+        return Ok(secret);
+    }
+
+    let secret = read_secret(source.as_ref())?;
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).secret = Some(secret.clone());
+    Ok(secret)
+}
+
+fn read_secret(source: Option<&KeySource>) -> Result<Vec<u8>, String> {
+    let secret = match source {
+        Some(KeySource::Passphrase(passphrase)) => passphrase.as_bytes().to_vec(),
+        Some(KeySource::KeyFile(path)) => std::fs::read(path)
+            .map_err(|e| format!("Cannot read token key file {}: {}", path.display(), e))?,
+        Some(KeySource::Prompt) => {
+            if !std::io::stdin().is_terminal() {
+                return Err("Token encryption needs a passphrase. Set WORKSPACE_TOKEN_PASSPHRASE or WORKSPACE_TOKEN_KEY_FILE".to_string());
+            }
+            rpassword::prompt_password("Token passphrase: ")
+                .map_err(|e| format!("Cannot read passphrase: {}", e))?
+                .into_bytes()
+        }
+        None => return Err("Token encryption is not configured".to_string()),
+    };
+    if secret.is_empty() {
+        return Err("Token encryption key is empty".to_string());
+    }
+    Ok(secret)
+}
+
+/// One cached token, in the same shape yup-oauth2 writes to its disk cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedToken {
+    scopes: Vec<String>,
+    token: yup_oauth2::storage::TokenInfo,
+}
+
+/// yup-oauth2 token cache that goes through [`read_file`] / [`write_file`], so
+/// it is encrypted at rest and reads plaintext caches from before encryption
+pub struct EncryptedTokenCache {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl EncryptedTokenCache {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Read all cached tokens. A missing file is empty; a file that cannot be
+    /// decrypted or parsed is an error, so callers never write over it.
+    /// Decryption may prompt and derive a key, so it runs on the blocking pool.
+    async fn load(&self) -> Result<Vec<CachedToken>, KeyringError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let path = self.path.clone();
+        let data = tokio::task::spawn_blocking(move || read_file(&path))
+            .await
+            .map_err(|e| KeyringError::RetrieveFailed(e.to_string()))??;
+        serde_json::from_slice(&data).map_err(|e| KeyringError::SerializationFailed(format!(
+            "Token cache {} is not valid: {}",
+            self.path.display(),
+            e
+        )))
+    }
+
+    fn unreadable(&self, error: KeyringError) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Cannot use token cache {}: wrong passphrase or corrupt token cache ({})",
+            self.path.display(),
+            error
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl yup_oauth2::storage::TokenStorage for EncryptedTokenCache {
+    async fn set(&self, scopes: &[&str], token: yup_oauth2::storage::TokenInfo) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        // Never replace a cache we could not read: it may hold other tokens
+        let mut tokens = self.load().await.map_err(|e| self.unreadable(e))?;
+        tokens.retain(|t| !same_scopes(&t.scopes, scopes));
+        tokens.push(CachedToken {
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            token,
+        });
+        let (path, data) = (self.path.clone(), serde_json::to_vec(&tokens)?);
+        tokio::task::spawn_blocking(move || write_file(&path, &data)).await??;
+        Ok(())
+    }
+
+    async fn get(&self, scopes: &[&str]) -> Option<yup_oauth2::storage::TokenInfo> {
+        let _guard = self.lock.lock().await;
+        // The storage trait has no error channel here, so report on stderr
+        // rather than looking like an empty cache
+        let tokens = match self.load().await {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                eprintln!("Warning: {}", self.unreadable(e));
+                return None;
+            }
+        };
+        // A token granted for a superset of the requested scopes also works
+        tokens.iter()
+            .find(|t| same_scopes(&t.scopes, scopes))
+            .or_else(|| tokens.iter().find(|t| scopes.iter().all(|s| t.scopes.iter().any(|g| g == s))))
+            .map(|t| t.token.clone())
+    }
+}
+
+fn same_scopes(stored: &[String], requested: &[&str]) -> bool {
+    stored.len() == requested.len() && requested.iter().all(|s| stored.iter().any(|g| g == s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yup_oauth2::storage::{TokenInfo, TokenStorage};
+
+    fn token(access: &str) -> TokenInfo {
+        TokenInfo {
+            access_token: Some(access.to_string()),
+            refresh_token: Some("1//refresh".to_string()),
+            expires_at: None,
+            id_token: None,
+        }
+    }
+
+    fn use_passphrase(passphrase: &str) {
+        configure(&AuthConfig { token_passphrase: Some(passphrase.to_string()), ..Default::default() });
+    }
+
+    #[tokio::test]
+    async fn wrong_passphrase_leaves_token_cache_untouched() {
+        let dir = std::env::temp_dir().join(format!("workspace-cli-token-cache-{}", std::process::id()));
+        let path = dir.join("token_cache.json");
+        let cache = EncryptedTokenCache::new(&path);
+        let drive = ["https://www.googleapis.com/auth/drive"];
+
+        use_passphrase("correct horse");
+        cache.set(&drive, token("ya29.drive")).await.unwrap();
+        let before = std::fs::read(&path).unwrap();
+
+        use_passphrase("wrong");
+        let error = cache.set(&["https://mail.google.com/"], token("ya29.gmail")).await.unwrap_err();
+        assert!(error.to_string().contains("wrong passphrase or corrupt token cache"), "{}", error);
+        assert!(cache.get(&drive).await.is_none());
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+
+        use_passphrase("correct horse");
+        assert_eq!(cache.get(&drive).await.unwrap().access_token.as_deref(), Some("ya29.drive"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
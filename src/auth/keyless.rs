@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use reqwest::{Client, RequestBuilder};
+use tokio::sync::Mutex;
+use yup_oauth2::ExternalAccountAuthenticator;
+
+use crate::client::{endpoints, network};
+use super::oauth::{self, AuthError, WorkspaceAuthenticator};
+
+/// Default service account on the GCE/GKE metadata server
+pub const METADATA_URL: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default";
+
+/// IAM Service Account Credentials API (signJwt)
+pub const IAM_CREDENTIALS_URL: &str = "https://iamcredentials.googleapis.com/v1";
+
+/// Google's OAuth 2.0 token endpoint, the audience of delegation assertions
+pub const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// Scope the base identity needs to call signJwt
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+enum Source {
+    /// Tokens from the metadata server of the VM or GKE pod
+    Metadata { url: String },
+    /// Workload identity federation (`"type": "external_account"` credential file)
+    ExternalAccount {
+        auth: WorkspaceAuthenticator,
+        /// Service account impersonated by the credential file, if any
+        service_account: Option<String>,
+    },
+}
+
+/// Credentials without a private key. With a subject, domain-wide delegation
+/// works by having IAM sign the JWT assertion (signJwt) instead of a local key.
+pub struct KeylessCredentials {
+    source: Source,
+    subject: Option<String>,
+    /// Access tokens by scope set, with their expiry
+    cache: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl KeylessCredentials {
+    /// Use the metadata server. `GCE_METADATA_HOST` and the `metadata`
+    /// endpoint override point it at a different host.
+    pub fn metadata(subject: Option<&str>) -> Self {
+        let default = match std::env::var("GCE_METADATA_HOST") {
+            Ok(host) if !host.is_empty() => format!(
+                "http://{}/computeMetadata/v1/instance/service-accounts/default",
+                host
+            ),
+            _ => METADATA_URL.to_string(),
+        };
+        Self::new(Source::Metadata { url: endpoints::resolve("metadata", &default) }, subject)
+    }
+
+    /// Use a workload identity federation credential file
+    pub async fn external_account(path: &Path, subject: Option<&str>) -> Result<Self, AuthError> {
+        let secret = yup_oauth2::read_external_account_secret(path)
+            .await
+            .map_err(|e| AuthError::InvalidCredentials(format!("Invalid external account file: {}", e)))?;
+        let service_account = secret.service_account_impersonation_url
+            .as_deref()
+            .and_then(service_account_from_url);
+
+        let auth = ExternalAccountAuthenticator::builder(secret)
+            .build()
+            .await
+            .map_err(|e| AuthError::FlowFailed(e.to_string()))?;
+
+        Ok(Self::new(Source::ExternalAccount { auth, service_account }, subject))
+    }
+
+    fn new(source: Source, subject: Option<&str>) -> Self {
+        Self {
+            source,
+            subject: subject.map(|s| s.to_string()),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Short name of the credential source
+    pub fn kind(&self) -> &'static str {
+        match self.source {
+            Source::Metadata { .. } => "metadata",
+            Source::ExternalAccount { .. } => "external_account",
+        }
+    }
+
+    /// Get an access token for the given scopes (for the subject when delegating)
+    pub async fn token(&self, scopes: &[&str]) -> Result<String, AuthError> {
+        let key = scopes.join(" ");
+        let mut cache = self.cache.lock().await;
+        if let Some((token, expires_at)) = cache.get(&key) {
+            if *expires_at > Instant::now() + Duration::from_secs(60) {
+                return Ok(token.clone());
+            }
+        }
+
+        let (token, expires_in) = match self.subject {
+            Some(ref subject) => self.delegated_token(subject, scopes).await?,
+            None => self.base_token(scopes).await?,
+        };
+        if let Some(seconds) = expires_in {
+            cache.insert(key, (token.clone(), Instant::now() + Duration::from_secs(seconds)));
+        }
+        Ok(token)
+    }
+
+    /// Token for the workload's own identity. The expiry is `None` when the
+    /// underlying authenticator already caches and refreshes it.
+    async fn base_token(&self, scopes: &[&str]) -> Result<(String, Option<u64>), AuthError> {
+        match self.source {
+            Source::Metadata { ref url } => {
+                let request = metadata_client()
+                    .get(format!("{}/token", url))
+                    .query(&[("scopes", scopes.join(","))])
+                    .header("Metadata-Flavor", "Google");
+                let body = send_json(request, "Metadata server token request").await?;
+                access_token(&body).map(|(t, e)| (t, Some(e)))
+            }
+            Source::ExternalAccount { ref auth, .. } => {
+                Ok((oauth::get_token(auth, scopes).await?, None))
+            }
+        }
+    }
+
+    /// Email of the service account whose key IAM signs with
+    async fn service_account_email(&self) -> Result<String, AuthError> {
+        match self.source {
+            Source::Metadata { ref url } => {
+                let response = metadata_client()
+                    .get(format!("{}/email", url))
+                    .header("Metadata-Flavor", "Google")
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| AuthError::TokenFailed(format!("Metadata server email request failed: {}", e)))?;
+                let email = response.text()
+                    .await
+                    .map_err(|e| AuthError::TokenFailed(e.to_string()))?;
+                Ok(email.trim().to_string())
+            }
+            Source::ExternalAccount { ref service_account, .. } => service_account.clone().ok_or_else(|| {
+                AuthError::InvalidCredentials(
+                    "Domain-wide delegation with external_account credentials requires service_account_impersonation_url".to_string()
+                )
+            }),
+        }
+    }
+
+    /// Mint a token for `subject`: IAM signs the delegation assertion for
+    /// the service account, which is then exchanged at the token endpoint
+    async fn delegated_token(&self, subject: &str, scopes: &[&str]) -> Result<(String, Option<u64>), AuthError> {
+        let (base_token, _) = self.base_token(&[CLOUD_PLATFORM_SCOPE]).await?;
+        let email = self.service_account_email().await?;
+        let token_url = endpoints::resolve("oauth_token", TOKEN_URL);
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let claims = serde_json::json!({
+            "iss": email,
+            "sub": subject,
+            "scope": scopes.join(" "),
+            "aud": token_url,
+            "iat": now,
+            "exp": now + 3600,
+        });
+
+        let client = network::http_client(network::default_timeout());
+        let sign_url = format!(
+            "{}/projects/-/serviceAccounts/{}:signJwt",
+            endpoints::resolve("iam_credentials", IAM_CREDENTIALS_URL),
+            email
+        );
+        let signed = send_json(
+            client.post(sign_url)
+                .bearer_auth(&base_token)
+                .json(&serde_json::json!({ "payload": claims.to_string() })),
+            "signJwt",
+        ).await?;
+        let assertion = signed.get("signedJwt")
+            .and_then(|j| j.as_str())
+            .ok_or_else(|| AuthError::TokenFailed("signJwt response has no signedJwt".to_string()))?;
+
+        let body = send_json(
+            client.post(&token_url).form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion),
+            ]),
+            "Delegated token exchange",
+        ).await?;
+        access_token(&body).map(|(t, e)| (t, Some(e)))
+    }
+}
+
+/// The metadata server is link-local, so it must never go through a proxy
+fn metadata_client() -> Client {
+    Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+async fn send_json(request: RequestBuilder, what: &str) -> Result<serde_json::Value, AuthError> {
+    let response = request.send()
+        .await
+        .map_err(|e| AuthError::TokenFailed(format!("{} failed: {}", what, e)))?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(AuthError::TokenFailed(format!("{} failed ({}): {}", what, status, body)));
+    }
+    serde_json::from_str(&body)
+        .map_err(|e| AuthError::TokenFailed(format!("{} returned invalid JSON: {}", what, e)))
+}
+
+fn access_token(body: &serde_json::Value) -> Result<(String, u64), AuthError> {
+    let token = body.get("access_token")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| AuthError::TokenFailed("No access token in response".to_string()))?;
+    let expires_in = body.get("expires_in").and_then(|e| e.as_u64()).unwrap_or(3600);
+    Ok((token.to_string(), expires_in))
+}
+
+/// `.../serviceAccounts/<email>:generateAccessToken` -> `<email>`
+fn service_account_from_url(url: &str) -> Option<String> {
+    let (_, rest) = url.rsplit_once("/serviceAccounts/")?;
+    let email = rest.split(':').next()?;
+    urlencoding::decode(email).ok().map(|e| e.into_owned())
+}
@@ -9,6 +9,22 @@ pub struct StoredToken {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub expires_at: Option<i64>, // Unix timestamp
+    /// Scopes the login requested; empty when it requested all of `SCOPES`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    /// Scopes Google reported as granted at login
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub granted_scopes: Vec<String>,
+}
+
+/// Whether credentials from this keyring backend outlive the process. Builds
+/// without a platform keyring get keyring's mock store, which keeps passwords
+/// in the `Entry` itself.
+fn persists(builder: &keyring::CredentialBuilder) -> bool {
+    !matches!(
+        builder.persistence(),
+        keyring::credential::CredentialPersistence::EntryOnly | keyring::credential::CredentialPersistence::ProcessOnly
+    )
 }
 
 /// Keyring-based token storage
@@ -17,8 +33,13 @@ pub struct KeyringStorage {
 }
 
 impl KeyringStorage {
-    /// Create a new keyring storage for the given user/account
+    /// Create a new keyring storage for the given user/account. Fails when the
+    /// only keyring compiled in would lose the token when the process exits,
+    /// so callers fall back to file storage.
     pub fn new(account: &str) -> Result<Self, KeyringError> {
+        if !persists(keyring::default::default_credential_builder().as_ref()) {
+            return Err(KeyringError::InitFailed("no persistent keyring available".to_string()));
+        }
         let entry = Entry::new(SERVICE_NAME, account)
             .map_err(|e| KeyringError::InitFailed(e.to_string()))?;
         Ok(Self { entry })
@@ -49,9 +70,11 @@ impl KeyringStorage {
 
     /// Delete token from keyring
     pub fn delete(&self) -> Result<(), KeyringError> {
-        self.entry
-            .delete_credential()
-            .map_err(|e| KeyringError::DeleteFailed(e.to_string()))
+        match self.entry.delete_credential() {
+            // Nothing stored (e.g. the token lives in the file fallback)
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(KeyringError::DeleteFailed(e.to_string())),
+        }
     }
 
     /// Check if a token exists
@@ -78,41 +101,18 @@ impl FileStorage {
 
     /// Store token to file
     pub fn store(&self, token: &StoredToken) -> Result<(), KeyringError> {
-        // Create parent directory if needed
-        if let Some(parent) = self.path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| KeyringError::StoreFailed(e.to_string()))?;
-        }
-
         let json = serde_json::to_string_pretty(token)
             .map_err(|e| KeyringError::SerializationFailed(e.to_string()))?;
 
-        // Write to file with restricted permissions (0600 = rw-------)
-        std::fs::write(&self.path, json)
-            .map_err(|e| KeyringError::StoreFailed(e.to_string()))?;
-
-        // Set file permissions to user-only read/write (Unix only)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let perms = std::fs::Permissions::from_mode(0o600);
-            std::fs::set_permissions(&self.path, perms)
-                .map_err(|e| KeyringError::StoreFailed(format!("Failed to set file permissions: {}", e)))?;
-        }
-
-        Ok(())
+        // Encrypted when token encryption is enabled; always mode 0600
+        super::encryption::write_file(&self.path, json.as_bytes())
     }
 
     /// Retrieve token from file
     pub fn retrieve(&self) -> Result<StoredToken, KeyringError> {
-        let json = std::fs::read_to_string(&self.path)
-            .map_err(|e| KeyringError::RetrieveFailed(format!(
-                "Failed to read token file at {}: {}",
-                self.path.display(),
-                e
-            )))?;
+        let json = super::encryption::read_file(&self.path)?;
 
-        serde_json::from_str(&json)
+        serde_json::from_slice(&json)
             .map_err(|e| KeyringError::SerializationFailed(format!(
                 "Failed to deserialize token from {} (file may be corrupted): {}",
                 self.path.display(),
@@ -137,6 +137,11 @@ impl FileStorage {
     pub fn exists(&self) -> bool {
         self.path.exists()
     }
+
+    /// Check if the token file is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        std::fs::read(&self.path).is_ok_and(|data| super::encryption::is_encrypted(&data))
+    }
 }
 
 /// Combined storage that tries keyring first, falls back to file
@@ -246,6 +251,8 @@ impl TokenStorage {
     pub fn storage_type(&self) -> &'static str {
         if self.keyring.as_ref().map(|kr| kr.exists()).unwrap_or(false) {
             "keyring"
+        } else if self.file.is_encrypted() {
+            "encrypted_file"
         } else if self.file.exists() {
             "file"
         } else {
@@ -270,4 +277,17 @@ pub enum KeyringError {
 
     #[error("Serialization error: {0}")]
     SerializationFailed(String),
+
+    #[error("Token encryption error: {0}")]
+    Encryption(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_keyrings_are_not_used() {
+        assert!(!persists(keyring::mock::default_credential_builder().as_ref()));
+    }
 }
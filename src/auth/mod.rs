@@ -1,7 +1,9 @@
 pub mod oauth;
+pub mod encryption;
+pub mod keyless;
 pub mod keyring_storage;
 pub mod token;
 
 pub use oauth::{AuthError, WorkspaceAuthenticator, SCOPES};
 pub use keyring_storage::{KeyringError, StoredToken, TokenStorage};
-pub use token::{TokenManager, TokenManagerError, AuthStatus};
+pub use token::{TokenManager, TokenManagerError, AuthStatus, ProfileStatus, list_profiles, revoke_profile, token_cache_path};
@@ -1,13 +1,19 @@
+use std::future::Future;
 use std::path::Path;
+use std::pin::Pin;
 use yup_oauth2::{
-    authenticator::Authenticator,
+    authenticator::{Authenticator, AuthenticatorBuilder},
+    authenticator_delegate::{DeviceAuthResponse, DeviceFlowDelegate},
     ApplicationSecret,
+    DeviceFlowAuthenticator,
     InstalledFlowAuthenticator,
     InstalledFlowReturnMethod,
     ServiceAccountAuthenticator,
     hyper_rustls::HttpsConnector,
 };
 
+use super::encryption;
+
 /// All scopes needed for Google Workspace APIs
 /// All scopes for interactive OAuth flow (user consents to all at once)
 pub const SCOPES: &[&str] = &[
@@ -30,6 +36,14 @@ pub const SCOPES: &[&str] = &[
     "https://www.googleapis.com/auth/admin.directory.user.readonly",
 ];
 
+/// Services accepted by `auth login --services`
+pub const SCOPED_SERVICES: &[&str] = &[
+    "gmail", "drive", "calendar", "docs", "sheets", "slides", "tasks", "chat", "contacts", "groups", "admin",
+];
+
+/// Google's token introspection endpoint
+pub const TOKENINFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+
 /// Get scopes for a specific service (used with --as to request only needed scopes)
 pub fn scopes_for_service(service: &str) -> &'static [&'static str] {
     match service {
@@ -76,6 +90,25 @@ pub fn scopes_for_service(service: &str) -> &'static [&'static str] {
     }
 }
 
+/// The part of `SCOPES` that covers a service, i.e. what a default interactive
+/// login grants for it (Gmail gets `gmail.modify` there, not `mail.google.com`)
+pub fn login_scopes_for_service(service: &str) -> &'static [&'static str] {
+    match service {
+        "gmail" => &[
+            "https://www.googleapis.com/auth/gmail.modify",
+        ],
+        "contacts" => &[
+            "https://www.googleapis.com/auth/contacts",
+            "https://www.googleapis.com/auth/directory.readonly",
+        ],
+        "admin" => &[
+            "https://www.googleapis.com/auth/admin.directory.user.readonly",
+        ],
+        "drive" | "calendar" | "docs" | "sheets" | "slides" | "tasks" | "chat" | "groups" => scopes_for_service(service),
+        _ => SCOPES,
+    }
+}
+
 pub type WorkspaceAuthenticator = Authenticator<HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>;
 
 /// Create an authenticator using OAuth2 installed application flow (interactive)
@@ -85,8 +118,8 @@ pub async fn create_installed_flow_auth(
 ) -> Result<WorkspaceAuthenticator, AuthError> {
     let secret = read_application_secret(credentials_path).await?;
 
-    let auth = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect)
-        .persist_tokens_to_disk(token_cache_path)
+    let builder = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect);
+    let auth = with_token_cache(builder, token_cache_path)
         .force_account_selection(true)
         .build()
         .await
@@ -95,16 +128,93 @@ pub async fn create_installed_flow_auth(
     Ok(auth)
 }
 
+/// Persist tokens to the cache file, encrypted when token encryption is enabled
+fn with_token_cache<C, F>(builder: AuthenticatorBuilder<C, F>, token_cache_path: &Path) -> AuthenticatorBuilder<C, F> {
+    if encryption::enabled() {
+        builder.with_storage(Box::new(encryption::EncryptedTokenCache::new(token_cache_path)))
+    } else {
+        builder.persist_tokens_to_disk(token_cache_path)
+    }
+}
+
+/// Whether Google's device authorization grant will issue `scope`. Gmail and
+/// full Drive access are refused there with invalid_scope.
+pub fn device_flow_allows(scope: &str) -> bool {
+    scope != "https://mail.google.com/"
+        && scope != "https://www.googleapis.com/auth/drive"
+        && !scope.starts_with("https://www.googleapis.com/auth/gmail.")
+}
+
+/// Google's device authorization endpoint (OAuth 2.0 device grant)
+pub const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+
+/// Create an authenticator using the OAuth2 device authorization flow (headless,
+/// no local browser). The verification URL and user code are printed to stderr
+/// while the token endpoint is polled.
+pub async fn create_device_flow_auth(
+    credentials_path: &Path,
+    token_cache_path: &Path,
+) -> Result<WorkspaceAuthenticator, AuthError> {
+    let secret = read_application_secret(credentials_path).await?;
+    let device_code_url = crate::client::endpoints::resolve("oauth_device", DEVICE_CODE_URL);
+
+    let builder = DeviceFlowAuthenticator::builder(secret);
+    let auth = with_token_cache(builder, token_cache_path)
+        .device_code_url(device_code_url)
+        .flow_delegate(Box::new(DeviceCodePrompt))
+        .build()
+        .await
+        .map_err(|e| AuthError::FlowFailed(e.to_string()))?;
+
+    Ok(auth)
+}
+
+/// Prints the device-flow verification URL and user code as JSON on stderr
+struct DeviceCodePrompt;
+
+impl DeviceFlowDelegate for DeviceCodePrompt {
+    fn present_user_code<'a>(
+        &'a self,
+        response: &'a DeviceAuthResponse,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        let prompt = serde_json::json!({
+            "status": "pending",
+            "message": format!("Visit {} and enter code {}", response.verification_uri, response.user_code),
+            "verification_url": response.verification_uri,
+            "user_code": response.user_code,
+            "expires_in": (response.expires_at.unix_timestamp() - now).max(0),
+        });
+        eprintln!("{}", prompt);
+        Box::pin(async {})
+    }
+}
+
 /// Create an authenticator using service account (headless)
 /// When `subject` is provided, uses domain-wide delegation to impersonate that user
 pub async fn create_service_account_auth(
     service_account_path: &Path,
     subject: Option<&str>,
 ) -> Result<WorkspaceAuthenticator, AuthError> {
-    let sa_key = yup_oauth2::read_service_account_key(service_account_path)
+    let sa_key = read_service_account_key(service_account_path).await?;
+    service_account_auth(sa_key, subject).await
+}
+
+/// Read a service account key file
+pub async fn read_service_account_key(path: &Path) -> Result<yup_oauth2::ServiceAccountKey, AuthError> {
+    yup_oauth2::read_service_account_key(path)
         .await
-        .map_err(|e| AuthError::InvalidCredentials(e.to_string()))?;
+        .map_err(|e| AuthError::InvalidCredentials(e.to_string()))
+}
 
+/// Create a service account authenticator from an already loaded key
+pub async fn service_account_auth(
+    sa_key: yup_oauth2::ServiceAccountKey,
+    subject: Option<&str>,
+) -> Result<WorkspaceAuthenticator, AuthError> {
     let mut builder = ServiceAccountAuthenticator::builder(sa_key);
     if let Some(email) = subject {
         builder = builder.subject(email);
@@ -203,6 +313,53 @@ pub async fn get_token(
         .ok_or_else(|| AuthError::TokenFailed("No access token in response".to_string()))
 }
 
+/// Google's OAuth 2.0 token revocation endpoint
+pub const REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+
+/// Revoke a refresh or access token at Google. Revoking a refresh token ends
+/// the whole grant. Tokens Google no longer knows count as revoked.
+pub async fn revoke_token(token: &str) -> Result<(), AuthError> {
+    let url = crate::client::endpoints::resolve("oauth_revoke", REVOKE_URL);
+    let response = crate::client::network::http_client(std::time::Duration::from_secs(30))
+        .post(url)
+        .form(&[("token", token)])
+        .send()
+        .await
+        .map_err(|e| AuthError::TokenFailed(format!("Token revocation failed: {}", e)))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    if status.as_u16() == 400 && body.contains("invalid_token") {
+        return Ok(());
+    }
+    Err(AuthError::TokenFailed(format!("Token revocation failed ({}): {}", status, body)))
+}
+
+/// Ask Google which scopes an access token actually carries
+pub async fn granted_scopes(access_token: &str) -> Result<Vec<String>, AuthError> {
+    let url = crate::client::endpoints::resolve("oauth_tokeninfo", TOKENINFO_URL);
+    let info: serde_json::Value = crate::client::network::http_client(std::time::Duration::from_secs(30))
+        .get(url)
+        .query(&[("access_token", access_token)])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| AuthError::TokenFailed(format!("Token info lookup failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| AuthError::TokenFailed(format!("Invalid token info: {}", e)))?;
+
+    Ok(info.get("scope")
+        .and_then(|s| s.as_str())
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
     #[error("Invalid credentials: {0}")]
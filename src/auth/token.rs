@@ -1,11 +1,15 @@
 use std::path::PathBuf;
-use crate::config::Config;
+use crate::config::{Config, DEFAULT_PROFILE};
+use super::encryption;
+use super::keyless::KeylessCredentials;
 use super::oauth::{self, AuthError, WorkspaceAuthenticator, SCOPES};
 use super::keyring_storage::{KeyringError, TokenStorage, StoredToken};
 
 /// Manages authentication and token lifecycle
 pub struct TokenManager {
     authenticator: Option<WorkspaceAuthenticator>,
+    /// Metadata-server or external_account credentials (used instead of `authenticator`)
+    keyless: Option<KeylessCredentials>,
     storage: TokenStorage,
     config: Config,
     credentials_path: Option<PathBuf>,
@@ -13,6 +17,14 @@ pub struct TokenManager {
     subject: Option<String>,
     /// Service name for per-service scope selection (used with --as)
     service: Option<String>,
+    /// Key loaded by `login_service_account`, reused by `for_subject`
+    service_account_key: Option<yup_oauth2::ServiceAccountKey>,
+    /// Whether `ensure_authenticated` requires the service's scopes to be granted
+    scope_check: bool,
+    /// Scopes to request at the next interactive login (`None` = all of `SCOPES`)
+    login_scopes: Option<Vec<String>>,
+    /// Scopes of the current user login (`None` = all of `SCOPES`)
+    user_scopes: Option<Vec<String>>,
 }
 
 impl TokenManager {
@@ -20,14 +32,29 @@ impl TokenManager {
     pub fn new(config: Config) -> Self {
         Self {
             authenticator: None,
-            storage: TokenStorage::new("default"),
+            keyless: None,
+            storage: TokenStorage::new(config.profile_name()),
             credentials_path: None,
             subject: config.auth.impersonate_subject.clone(),
             service: None,
+            service_account_key: None,
+            scope_check: true,
+            login_scopes: None,
+            user_scopes: None,
             config,
         }
     }
 
+    /// Profile whose tokens this manager uses
+    pub fn profile(&self) -> &str {
+        self.config.profile_name()
+    }
+
+    /// Email being impersonated via domain-wide delegation, if any
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
     /// Set the email to impersonate via domain-wide delegation
     pub fn set_subject(&mut self, subject: Option<String>) {
         self.subject = subject;
@@ -38,19 +65,81 @@ impl TokenManager {
         self.service = Some(service.to_string());
     }
 
+    /// Enable or disable the per-service scope check (off for commands that span services)
+    pub fn set_scope_check(&mut self, enabled: bool) {
+        self.scope_check = enabled;
+    }
+
+    /// Limit the next interactive login to the scopes of these services
+    pub fn set_login_services(&mut self, services: &[String]) -> Result<(), TokenManagerError> {
+        let mut scopes: Vec<String> = Vec::new();
+        for service in services {
+            if !oauth::SCOPED_SERVICES.contains(&service.as_str()) {
+                return Err(TokenManagerError::MissingCredentials(format!(
+                    "Unknown service '{}'. Use one of: {}",
+                    service,
+                    oauth::SCOPED_SERVICES.join(", ")
+                )));
+            }
+            for scope in oauth::scopes_for_service(service) {
+                if !scopes.iter().any(|s| s == scope) {
+                    scopes.push(scope.to_string());
+                }
+            }
+        }
+        self.login_scopes = Some(scopes);
+        Ok(())
+    }
+
+    /// Scopes the current credentials ask for on behalf of a service: the
+    /// delegation scopes with `--as` or after `auth login --services`, otherwise
+    /// the share of `SCOPES` a default login requested
+    pub fn requested_scopes(&self, service: &str) -> Vec<String> {
+        let scopes = if self.subject.is_some() || self.user_scopes.is_some() {
+            oauth::scopes_for_service(service)
+        } else {
+            oauth::login_scopes_for_service(service)
+        };
+        scopes.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Scopes to request for the user's token
+    fn user_scope_refs(&self) -> Vec<&str> {
+        match self.user_scopes {
+            Some(ref scopes) => scopes.iter().map(|s| s.as_str()).collect(),
+            None => SCOPES.to_vec(),
+        }
+    }
+
     /// Try to restore authenticator from cached tokens
     /// Call this before making API requests
     /// When subject is set, automatically uses service account flow for domain-wide delegation
     pub async fn ensure_authenticated(&mut self) -> Result<(), TokenManagerError> {
+        // Pre-issued token (WORKSPACE_ACCESS_TOKEN) needs no authenticator
+        if self.config.auth.access_token.is_some() {
+            return Ok(());
+        }
+
         // Already have an authenticator
-        if self.authenticator.is_some() {
+        if self.authenticator.is_some() || self.keyless.is_some() {
             // Validate that the authenticator can still get tokens
             // This checks for expiry and refreshes if needed
-            if let Ok(_) = self.get_access_token().await {
+            if self.get_access_token().await.is_ok() {
                 return Ok(());
             }
             // If token fetch fails, clear the authenticator and retry
             self.authenticator = None;
+            self.keyless = None;
+        }
+
+        match self.config.auth.credential_source.as_deref() {
+            None | Some("user") => {}
+            Some("service_account") => return self.login_service_account(None).await,
+            Some("metadata") => return self.login_metadata().await,
+            Some(other) => return Err(TokenManagerError::MissingCredentials(format!(
+                "Unknown credential_source '{}'. Use user, service_account or metadata",
+                other
+            ))),
         }
 
         // If impersonation is requested, use service account flow
@@ -65,6 +154,16 @@ impl TokenManager {
             return Err(TokenManagerError::NotAuthenticated);
         }
 
+        // Surface a missing or wrong encryption key here rather than letting the
+        // flow treat an unreadable cache as empty; this also migrates plaintext caches
+        if encryption::enabled() || std::fs::read(&token_cache).is_ok_and(|d| encryption::is_encrypted(&d)) {
+            let path = token_cache.clone();
+            tokio::task::spawn_blocking(move || encryption::read_file(&path))
+                .await
+                .map_err(|e| TokenManagerError::Storage(KeyringError::RetrieveFailed(e.to_string())))?
+                .map_err(TokenManagerError::Storage)?;
+        }
+
         // Try to find credentials path
         let creds_path = self.credentials_path.clone()
             .or_else(|| self.config.auth.credentials_path.clone())
@@ -74,13 +173,22 @@ impl TokenManager {
             "No credentials file found. Run 'workspace-cli auth login --credentials <path>' first.".to_string()
         ))?;
 
+        // Logins limited with --services record their scopes; older logins cover all of SCOPES
+        let stored = self.storage.retrieve().ok();
+        self.user_scopes = stored.as_ref().map(|t| t.scopes.clone()).filter(|s| !s.is_empty());
+
+        let missing = self.missing_service_scopes(stored.as_ref());
+        if !missing.is_empty() {
+            return self.authorize_missing_scopes(creds_path, missing).await;
+        }
+
         // Restore authenticator from cached tokens
         let auth = oauth::create_installed_flow_auth(&creds_path, &token_cache)
             .await
             .map_err(TokenManagerError::Auth)?;
 
         // Verify we can get a token before considering authentication successful
-        oauth::get_token(&auth, SCOPES)
+        oauth::get_token(&auth, &self.user_scope_refs())
             .await
             .map_err(TokenManagerError::Auth)?;
 
@@ -89,6 +197,52 @@ impl TokenManager {
         Ok(())
     }
 
+    /// Scopes the current service needs that a `--services` login did not grant
+    fn missing_service_scopes(&self, stored: Option<&StoredToken>) -> Vec<String> {
+        let (Some(service), Some(token)) = (self.service.as_deref(), stored) else { return Vec::new() };
+        if !self.scope_check || token.scopes.is_empty() {
+            return Vec::new();
+        }
+        let granted = if token.granted_scopes.is_empty() { &token.scopes } else { &token.granted_scopes };
+        oauth::scopes_for_service(service)
+            .iter()
+            .filter(|scope| !granted.iter().any(|g| g == *scope))
+            .map(|scope| scope.to_string())
+            .collect()
+    }
+
+    /// Incremental authorization: ask on the terminal, then log in again for the
+    /// current scopes plus the missing ones
+    async fn authorize_missing_scopes(&mut self, creds_path: PathBuf, missing: Vec<String>) -> Result<(), TokenManagerError> {
+        use std::io::{BufRead, IsTerminal};
+
+        let service = self.service.clone().unwrap_or_default();
+        let error = TokenManagerError::MissingScopes {
+            service: service.clone(),
+            scopes: missing.join(" "),
+        };
+        if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+            return Err(error);
+        }
+
+        eprint!(
+            "'{}' needs access you have not granted yet:\n  {}\nAuthorize now in the browser? [Y/n] ",
+            service,
+            missing.join("\n  ")
+        );
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer).is_err()
+            || matches!(answer.trim().to_lowercase().as_str(), "n" | "no")
+        {
+            return Err(error);
+        }
+
+        let mut scopes = self.user_scopes.clone().unwrap_or_default();
+        scopes.extend(missing);
+        self.login_scopes = Some(scopes);
+        self.login_user(Some(creds_path), false).await
+    }
+
     /// Find credentials file in common locations
     fn find_credentials_file(&self) -> Option<PathBuf> {
         let candidates = [
@@ -106,6 +260,16 @@ impl TokenManager {
 
     /// Initialize with interactive OAuth2 flow
     pub async fn login_interactive(&mut self, credentials_path: Option<PathBuf>) -> Result<(), TokenManagerError> {
+        self.login_user(credentials_path, false).await
+    }
+
+    /// Initialize with the OAuth2 device authorization flow (no local browser needed)
+    pub async fn login_device(&mut self, credentials_path: Option<PathBuf>) -> Result<(), TokenManagerError> {
+        self.login_user(credentials_path, true).await
+    }
+
+    /// Run a user login flow and store the resulting token
+    async fn login_user(&mut self, credentials_path: Option<PathBuf>, device: bool) -> Result<(), TokenManagerError> {
         let creds_path = credentials_path
             .or_else(|| self.config.auth.credentials_path.clone())
             .ok_or_else(|| TokenManagerError::MissingCredentials(
@@ -119,6 +283,17 @@ impl TokenManager {
             ));
         }
 
+        let requested: Vec<&str> = match self.login_scopes {
+            Some(ref scopes) => scopes.iter().map(|s| s.as_str()).collect(),
+            None => SCOPES.to_vec(),
+        };
+        if device {
+            let refused: Vec<&str> = requested.iter().copied().filter(|s| !oauth::device_flow_allows(s)).collect();
+            if !refused.is_empty() {
+                return Err(TokenManagerError::DeviceScopes(refused.join(", ")));
+            }
+        }
+
         let token_cache = self.token_cache_path();
 
         // Ensure the config directory exists
@@ -128,15 +303,23 @@ impl TokenManager {
             })?;
         }
 
-        let auth = oauth::create_installed_flow_auth(&creds_path, &token_cache)
-            .await
-            .map_err(TokenManagerError::Auth)?;
+        let auth = if device {
+            oauth::create_device_flow_auth(&creds_path, &token_cache).await
+        } else {
+            oauth::create_installed_flow_auth(&creds_path, &token_cache).await
+        }
+        .map_err(TokenManagerError::Auth)?;
 
         // Test that we can get a token
-        let token = oauth::get_token(&auth, SCOPES)
+        let token = oauth::get_token(&auth, &requested)
             .await
             .map_err(TokenManagerError::Auth)?;
 
+        // Users can untick scopes on the consent screen, so record what was really granted
+        let granted_scopes = oauth::granted_scopes(&token)
+            .await
+            .unwrap_or_else(|_| requested.iter().map(|s| s.to_string()).collect());
+
         // Store token info with current timestamp + estimated expiry (3600 seconds is typical)
         let expires_at = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -147,8 +330,11 @@ impl TokenManager {
             access_token: token.clone(),
             refresh_token: None, // yup-oauth2 handles refresh internally
             expires_at,
+            scopes: self.login_scopes.clone().unwrap_or_default(),
+            granted_scopes,
         }).map_err(TokenManagerError::Storage)?;
 
+        self.user_scopes = self.login_scopes.take();
         self.authenticator = Some(auth);
         self.credentials_path = Some(creds_path);
         Ok(())
@@ -170,37 +356,110 @@ impl TokenManager {
             ));
         }
 
-        let auth = oauth::create_service_account_auth(&sa_path, self.subject.as_deref())
+        // Workload identity federation files have no private key
+        let key_type = std::fs::read_to_string(&sa_path).ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(|t| t.to_string()));
+        if key_type.as_deref() == Some("external_account") {
+            let keyless = KeylessCredentials::external_account(&sa_path, self.subject.as_deref())
+                .await
+                .map_err(TokenManagerError::Auth)?;
+            return self.use_keyless(keyless).await;
+        }
+
+        let sa_key = oauth::read_service_account_key(&sa_path)
+            .await
+            .map_err(TokenManagerError::Auth)?;
+        let auth = oauth::service_account_auth(sa_key.clone(), self.subject.as_deref())
             .await
             .map_err(TokenManagerError::Auth)?;
 
         self.authenticator = Some(auth);
+        self.service_account_key = Some(sa_key);
+        Ok(())
+    }
+
+    /// A token manager for the same profile and service that impersonates
+    /// `subject`. The service account key loaded here is reused, so fanning
+    /// out over many users (`--as-each`) reads and parses it only once.
+    pub async fn for_subject(&self, subject: &str) -> Result<TokenManager, TokenManagerError> {
+        let mut config = self.config.clone();
+        // A pre-issued token belongs to one identity, so it never stands in for the subject
+        config.auth.access_token = None;
+        let mut tm = TokenManager::new(config);
+        tm.subject = Some(subject.to_string());
+        tm.service = self.service.clone();
+
+        if let Some(ref sa_key) = self.service_account_key {
+            let auth = oauth::service_account_auth(sa_key.clone(), Some(subject))
+                .await
+                .map_err(TokenManagerError::Auth)?;
+            tm.authenticator = Some(auth);
+            tm.service_account_key = Some(sa_key.clone());
+        } else if self.keyless.as_ref().is_some_and(|k| k.kind() == "metadata") {
+            tm.keyless = Some(KeylessCredentials::metadata(Some(subject)));
+        } else {
+            tm.login_service_account(self.config.auth.service_account_path.clone()).await?;
+        }
+        Ok(tm)
+    }
+
+    /// Initialize with the GCE/GKE metadata server
+    pub async fn login_metadata(&mut self) -> Result<(), TokenManagerError> {
+        let keyless = KeylessCredentials::metadata(self.subject.as_deref());
+        self.use_keyless(keyless).await
+    }
+
+    /// Switch to keyless credentials once they have produced a token
+    async fn use_keyless(&mut self, keyless: KeylessCredentials) -> Result<(), TokenManagerError> {
+        self.authenticator = None;
+        self.keyless = Some(keyless);
+        if let Err(e) = self.get_access_token().await {
+            self.keyless = None;
+            return Err(e);
+        }
         Ok(())
     }
 
     /// Get an access token for API calls
     /// When impersonating (--as), uses per-service scopes to avoid requesting unauthorized scopes
     pub async fn get_access_token(&self) -> Result<String, TokenManagerError> {
-        let auth = self.authenticator.as_ref()
-            .ok_or(TokenManagerError::NotAuthenticated)?;
+        if let Some(ref token) = self.config.auth.access_token {
+            return Ok(token.clone());
+        }
 
         let scopes = if self.subject.is_some() {
             if let Some(ref svc) = self.service {
-                oauth::scopes_for_service(svc)
+                oauth::scopes_for_service(svc).to_vec()
             } else {
-                SCOPES
+                SCOPES.to_vec()
             }
         } else {
-            SCOPES
+            self.user_scope_refs()
         };
 
-        oauth::get_token(auth, scopes)
+        if let Some(ref keyless) = self.keyless {
+            return keyless.token(&scopes).await.map_err(TokenManagerError::Auth);
+        }
+
+        let auth = self.authenticator.as_ref()
+            .ok_or(TokenManagerError::NotAuthenticated)?;
+
+        oauth::get_token(auth, &scopes)
             .await
             .map_err(TokenManagerError::Auth)
     }
 
     /// Get token for specific scopes
     pub async fn get_token_for_scopes(&self, scopes: &[&str]) -> Result<String, TokenManagerError> {
+        if let Some(ref token) = self.config.auth.access_token {
+            return Ok(token.clone());
+        }
+
+        if let Some(ref keyless) = self.keyless {
+            return keyless.token(scopes).await.map_err(TokenManagerError::Auth);
+        }
+
         let auth = self.authenticator.as_ref()
             .ok_or(TokenManagerError::NotAuthenticated)?;
 
@@ -211,52 +470,166 @@ impl TokenManager {
 
     /// Check if we have stored credentials
     pub fn is_authenticated(&self) -> bool {
-        self.authenticator.is_some() || self.token_cache_path().exists()
+        self.authenticator.is_some()
+            || self.keyless.is_some()
+            || self.config.auth.access_token.is_some()
+            || self.token_cache_path().exists()
     }
 
-    /// Clear all stored tokens (logout)
-    pub fn logout(&mut self) -> Result<(), TokenManagerError> {
+    /// Clear all stored tokens (logout). Unless `local_only`, the grant is first
+    /// revoked at Google; if that fails nothing is deleted so it can be retried.
+    /// Returns the number of tokens revoked.
+    pub async fn logout(&mut self, local_only: bool) -> Result<usize, TokenManagerError> {
         // Clear the authenticator to free resources
         self.authenticator = None;
+        self.keyless = None;
         self.credentials_path = None;
 
-        self.storage.delete().map_err(TokenManagerError::Storage)?;
-
-        // Also try to remove the token cache file
-        let cache_path = self.token_cache_path();
-        if cache_path.exists() {
-            std::fs::remove_file(cache_path)
-                .map_err(|e| TokenManagerError::Storage(
-                    KeyringError::DeleteFailed(format!("Failed to remove token cache: {}", e))
-                ))?;
+        if local_only {
+            delete_tokens(&self.storage, self.profile())?;
+            return Ok(0);
         }
-
-        Ok(())
+        revoke_profile(self.profile()).await
     }
 
     /// Get authentication status info
     pub fn status(&self) -> AuthStatus {
         AuthStatus {
+            profile: self.profile().to_string(),
             authenticated: self.is_authenticated(),
             storage_type: self.storage.storage_type().to_string(),
             token_cache_path: self.token_cache_path(),
+            granted_scopes: self.storage.retrieve().ok()
+                .map(|t| t.granted_scopes)
+                .filter(|s| !s.is_empty()),
+            credential_source: self.keyless.as_ref()
+                .map(|k| k.kind().to_string())
+                .or_else(|| self.config.auth.credential_source.clone()),
         }
     }
 
     /// Get the token cache file path
     fn token_cache_path(&self) -> PathBuf {
-        Config::config_dir()
-            .map(|d| d.join("token_cache.json"))
-            .unwrap_or_else(|| PathBuf::from("token_cache.json"))
+        token_cache_path(self.profile())
     }
 }
 
+/// Revoke a profile's stored grants at Google, then delete its local tokens.
+/// Returns the number of tokens revoked.
+pub async fn revoke_profile(profile: &str) -> Result<usize, TokenManagerError> {
+    let storage = TokenStorage::new(profile);
+    let cache_path = token_cache_path(profile);
+
+    // The refresh token lives in the token cache; revoking it ends the grant
+    let mut refresh_tokens = Vec::new();
+    let mut access_tokens = Vec::new();
+    if cache_path.exists() {
+        let data = encryption::read_file(&cache_path).map_err(TokenManagerError::Storage)?;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&data).unwrap_or_default();
+        for token in entries.iter().map(|e| &e["token"]) {
+            if let Some(t) = token["refresh_token"].as_str() {
+                refresh_tokens.push(t.to_string());
+            }
+            if let Some(t) = token["access_token"].as_str() {
+                access_tokens.push(t.to_string());
+            }
+        }
+    }
+    if let Ok(stored) = storage.retrieve() {
+        refresh_tokens.extend(stored.refresh_token);
+        access_tokens.push(stored.access_token);
+    }
+
+    let mut tokens = if refresh_tokens.is_empty() { access_tokens } else { refresh_tokens };
+    tokens.sort();
+    tokens.dedup();
+    for token in &tokens {
+        oauth::revoke_token(token).await.map_err(TokenManagerError::Auth)?;
+    }
+
+    delete_tokens(&storage, profile)?;
+    Ok(tokens.len())
+}
+
+/// Delete a profile's stored token and token cache
+fn delete_tokens(storage: &TokenStorage, profile: &str) -> Result<(), TokenManagerError> {
+    storage.delete().map_err(TokenManagerError::Storage)?;
+
+    let cache_path = token_cache_path(profile);
+    if cache_path.exists() {
+        std::fs::remove_file(cache_path)
+            .map_err(|e| TokenManagerError::Storage(
+                KeyringError::DeleteFailed(format!("Failed to remove token cache: {}", e))
+            ))?;
+    }
+    Ok(())
+}
+
+/// Token cache file for a profile. The default profile keeps the original
+/// `token_cache.json` name so existing logins carry over.
+pub fn token_cache_path(profile: &str) -> PathBuf {
+    let file = if profile == DEFAULT_PROFILE {
+        "token_cache.json".to_string()
+    } else {
+        format!("token_cache_{}.json", profile)
+    };
+    Config::config_dir()
+        .map(|d| d.join(&file))
+        .unwrap_or_else(|| PathBuf::from(file))
+}
+
+/// Profiles known on this machine: `default`, those in `config.toml`, and any with a token cache
+pub fn list_profiles(config: &Config) -> Vec<ProfileStatus> {
+    let mut names: std::collections::BTreeSet<String> = config.profiles.keys().cloned().collect();
+    names.insert(DEFAULT_PROFILE.to_string());
+    if let Some(entries) = Config::config_dir().and_then(|d| std::fs::read_dir(d).ok()) {
+        for entry in entries.flatten() {
+            let file = entry.file_name().to_string_lossy().to_string();
+            if let Some(name) = file.strip_prefix("token_cache_").and_then(|f| f.strip_suffix(".json")) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+
+    names.into_iter()
+        .map(|name| {
+            let profile = config.clone().with_profile(Some(&name));
+            ProfileStatus {
+                active: name == config.profile_name(),
+                authenticated: token_cache_path(&name).exists(),
+                credentials_path: profile.auth.credentials_path,
+                impersonate_subject: profile.auth.impersonate_subject,
+                name,
+            }
+        })
+        .collect()
+}
+
+/// One entry of `auth list`
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct ProfileStatus {
+    pub name: String,
+    pub active: bool,
+    pub authenticated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impersonate_subject: Option<String>,
+}
+
 /// Authentication status information
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
 pub struct AuthStatus {
+    pub profile: String,
     pub authenticated: bool,
     pub storage_type: String,
     pub token_cache_path: PathBuf,
+    /// Scopes granted at the last interactive login
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granted_scopes: Option<Vec<String>>,
+    /// Keyless or service account source, when not using a user login
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_source: Option<String>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -272,4 +645,10 @@ pub enum TokenManagerError {
 
     #[error("Token storage error: {0}")]
     Storage(#[from] KeyringError),
+
+    #[error("The current login does not grant {service} access ({scopes}). Run the command in a terminal to authorize it, or run 'workspace-cli auth login --services <services>,{service}'.")]
+    MissingScopes { service: String, scopes: String },
+
+    #[error("Google's device flow does not grant {0}. Drop those services from --services, or run 'workspace-cli auth login' without --device.")]
+    DeviceScopes(String),
 }
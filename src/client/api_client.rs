@@ -1,10 +1,15 @@
-use reqwest::{Client, Method, Response};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use serde::{de::DeserializeOwned, Serialize};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::auth::TokenManager;
 use crate::error::{WorkspaceError, ApiError};
+use super::cache::{self, CacheEntry, ResponseCache};
+use super::cassette;
+use super::network;
+use super::partial;
 use super::rate_limiter::{ApiRateLimiter, ConcurrencyPermit};
+use super::trace::{self, CallStats};
 use super::retry::{RetryConfig, Retryable, with_retry, RetryError, is_retryable_status, parse_retry_after};
 
 /// Base URLs for Google Workspace APIs
@@ -21,6 +26,48 @@ pub mod endpoints {
     pub const GROUPS: &str = "https://cloudidentity.googleapis.com/v1";
     pub const ADMIN: &str = "https://admin.googleapis.com/admin/directory/v1";
     pub const ADMIN_REPORTS: &str = "https://admin.googleapis.com/admin/reports/v1";
+    pub const DRIVE_UPLOAD: &str = "https://www.googleapis.com/upload/drive/v3";
+
+    use std::collections::BTreeMap;
+    use std::sync::RwLock;
+
+    /// Endpoint overrides installed from `[api]` config / environment
+    struct Overrides {
+        base_url: Option<String>,
+        services: BTreeMap<String, String>,
+    }
+
+    static OVERRIDES: RwLock<Overrides> = RwLock::new(Overrides {
+        base_url: None,
+        services: BTreeMap::new(),
+    });
+
+    /// Install endpoint overrides from config. Call once at startup, before
+    /// any client is constructed.
+    pub fn configure(config: &crate::config::ApiConfig) {
+        let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+        overrides.base_url = config.base_url.clone();
+        overrides.services = config.endpoints.clone();
+    }
+
+    /// Resolve the base URL for an endpoint key (`gmail`, `drive_upload`,
+    /// `gmail_batch`, ...). A per-key override wins; otherwise `base_url`
+    /// replaces the scheme and host of `default`, keeping its path.
+    pub fn resolve(key: &str, default: &str) -> String {
+        let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(url) = overrides.services.get(key) {
+            return url.trim_end_matches('/').to_string();
+        }
+        match overrides.base_url {
+            Some(ref base) => {
+                let path = url::Url::parse(default)
+                    .map(|u| u.path().trim_end_matches('/').to_string())
+                    .unwrap_or_default();
+                format!("{}{}", base.trim_end_matches('/'), path)
+            }
+            None => default.to_string(),
+        }
+    }
 }
 
 /// Google Workspace API client
@@ -35,6 +82,12 @@ pub struct ApiClient {
     /// `includeItemsFromAllDrives=true`. Required to see items in Shared Drives
     /// via Drive v3 — otherwise the API returns 404 even to members.
     shared_drives: bool,
+    /// ETag cache for GET requests (see [`cache::configure`])
+    cache: Option<std::sync::Arc<ResponseCache>>,
+    /// Service name used in `--trace` records and quota estimates
+    service: &'static str,
+    /// Per-request timeout from `[api.services.<name>]`, overriding the client's
+    timeout: Option<Duration>,
 }
 
 impl Clone for ApiClient {
@@ -47,6 +100,9 @@ impl Clone for ApiClient {
             base_url: self.base_url.clone(),
             dry_run: self.dry_run,
             shared_drives: self.shared_drives,
+            cache: self.cache.clone(),
+            service: self.service,
+            timeout: self.timeout,
         }
     }
 }
@@ -54,22 +110,17 @@ impl Clone for ApiClient {
 impl ApiClient {
     /// Create a new API client
     pub fn new(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
-        let http = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .pool_max_idle_per_host(10)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .tcp_keepalive(Duration::from_secs(60))
-            .build()
-            .expect("Failed to create HTTP client");
-
         Self {
-            http,
+            http: network::http_client(network::default_timeout()),
             token_manager,
             rate_limiter: None,
             retry_config: RetryConfig::default(),
             base_url: String::new(),
             dry_run: false,
             shared_drives: false,
+            cache: cache::shared(),
+            service: "api",
+            timeout: None,
         }
     }
 
@@ -81,6 +132,15 @@ impl ApiClient {
         self
     }
 
+    /// Set the service name reported by `--trace` and apply its
+    /// `[api.services.<name>]` timeout and retry settings
+    pub fn with_service(mut self, service: &'static str) -> Self {
+        self.service = service;
+        self.timeout = network::service_timeout(service);
+        self.retry_config = network::retry_config(service, self.retry_config);
+        self
+    }
+
     /// Set the base URL for this client
     pub fn with_base_url(mut self, url: &str) -> Self {
         self.base_url = url.to_string();
@@ -99,6 +159,11 @@ impl ApiClient {
         self
     }
 
+    /// Retry configuration used for every request
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
+
     /// Enable dry-run mode: print the request and exit without executing it
     pub fn with_dry_run(mut self, dry_run: bool) -> Self {
         self.dry_run = dry_run;
@@ -108,96 +173,114 @@ impl ApiClient {
     /// Create a Gmail client
     pub fn gmail(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::GMAIL)
-            .with_rate_limiter(ApiRateLimiter::gmail())
+            .with_base_url(&endpoints::resolve("gmail", endpoints::GMAIL))
+            .with_rate_limiter(ApiRateLimiter::gmail().with_service_limits("gmail").with_shared_bucket("gmail"))
             .with_retry_config(RetryConfig::conservative())
+            .with_service("gmail")
     }
 
     /// Create a Drive client
     pub fn drive(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::DRIVE)
-            .with_rate_limiter(ApiRateLimiter::drive())
+            .with_base_url(&endpoints::resolve("drive", endpoints::DRIVE))
+            .with_rate_limiter(ApiRateLimiter::drive().with_service_limits("drive").with_shared_bucket("drive"))
             .with_retry_config(RetryConfig::conservative())
             .with_shared_drives()
+            .with_service("drive")
     }
 
     /// Create a Calendar client
     pub fn calendar(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::CALENDAR)
-            .with_rate_limiter(ApiRateLimiter::calendar())
+            .with_base_url(&endpoints::resolve("calendar", endpoints::CALENDAR))
+            .with_rate_limiter(ApiRateLimiter::calendar().with_service_limits("calendar").with_shared_bucket("calendar"))
             .with_retry_config(RetryConfig::default())
+            .with_service("calendar")
     }
 
     /// Create a Docs client
     pub fn docs(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::DOCS)
-            .with_rate_limiter(ApiRateLimiter::docs())
+            .with_base_url(&endpoints::resolve("docs", endpoints::DOCS))
+            .with_rate_limiter(ApiRateLimiter::docs().with_service_limits("docs").with_shared_bucket("docs"))
             .with_retry_config(RetryConfig::aggressive())
+            .with_service("docs")
     }
 
     /// Create a Sheets client
     pub fn sheets(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::SHEETS)
-            .with_rate_limiter(ApiRateLimiter::docs())
+            .with_base_url(&endpoints::resolve("sheets", endpoints::SHEETS))
+            .with_rate_limiter(ApiRateLimiter::docs().with_service_limits("sheets").with_shared_bucket("sheets"))
             .with_retry_config(RetryConfig::aggressive())
+            .with_service("sheets")
     }
 
     /// Create a Slides client
     pub fn slides(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::SLIDES)
-            .with_rate_limiter(ApiRateLimiter::docs())
+            .with_base_url(&endpoints::resolve("slides", endpoints::SLIDES))
+            .with_rate_limiter(ApiRateLimiter::docs().with_service_limits("slides").with_shared_bucket("slides"))
             .with_retry_config(RetryConfig::aggressive())
+            .with_service("slides")
     }
 
     /// Create a Tasks client
     pub fn tasks(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::TASKS)
-            .with_rate_limiter(ApiRateLimiter::tasks())
+            .with_base_url(&endpoints::resolve("tasks", endpoints::TASKS))
+            .with_rate_limiter(ApiRateLimiter::tasks().with_service_limits("tasks").with_shared_bucket("tasks"))
             .with_retry_config(RetryConfig::default())
+            .with_service("tasks")
     }
 
     /// Create a Google Chat client
     pub fn chat(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::CHAT)
-            .with_rate_limiter(ApiRateLimiter::tasks())
+            .with_base_url(&endpoints::resolve("chat", endpoints::CHAT))
+            .with_rate_limiter(ApiRateLimiter::tasks().with_service_limits("chat").with_shared_bucket("chat"))
             .with_retry_config(RetryConfig::default())
+            .with_service("chat")
     }
 
     /// Create a Google Contacts (People API) client
     pub fn contacts(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::CONTACTS)
-            .with_rate_limiter(ApiRateLimiter::tasks())
+            .with_base_url(&endpoints::resolve("contacts", endpoints::CONTACTS))
+            .with_rate_limiter(ApiRateLimiter::tasks().with_service_limits("contacts").with_shared_bucket("contacts"))
             .with_retry_config(RetryConfig::default())
+            .with_service("contacts")
     }
 
     /// Create a Google Groups (Cloud Identity) client
     pub fn groups(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::GROUPS)
-            .with_rate_limiter(ApiRateLimiter::tasks())
+            .with_base_url(&endpoints::resolve("groups", endpoints::GROUPS))
+            .with_rate_limiter(ApiRateLimiter::tasks().with_service_limits("groups").with_shared_bucket("groups"))
             .with_retry_config(RetryConfig::default())
+            .with_service("groups")
     }
 
     pub fn admin(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::ADMIN)
-            .with_rate_limiter(ApiRateLimiter::tasks())
+            .with_base_url(&endpoints::resolve("admin", endpoints::ADMIN))
+            .with_rate_limiter(ApiRateLimiter::tasks().with_service_limits("admin").with_shared_bucket("admin"))
             .with_retry_config(RetryConfig::default())
+            .with_service("admin")
     }
 
     pub fn admin_reports(token_manager: std::sync::Arc<tokio::sync::RwLock<TokenManager>>) -> Self {
         Self::new(token_manager)
-            .with_base_url(endpoints::ADMIN_REPORTS)
-            .with_rate_limiter(ApiRateLimiter::tasks())
+            .with_base_url(&endpoints::resolve("admin_reports", endpoints::ADMIN_REPORTS))
+            .with_rate_limiter(ApiRateLimiter::tasks().with_service_limits("admin_reports").with_shared_bucket("admin_reports"))
             .with_retry_config(RetryConfig::default())
+            .with_service("admin_reports")
+    }
+
+    /// Resolve a path (or absolute URL) into the final request URL, including
+    /// shared-drive parameters when enabled
+    pub fn url(&self, path: &str) -> String {
+        self.build_url(path)
     }
 
     /// Build full URL from path. For Drive clients (with `shared_drives`
@@ -218,6 +301,15 @@ impl ApiClient {
         }
     }
 
+    /// Start a request, applying the per-service timeout
+    fn http_request(&self, method: Method, url: &str) -> RequestBuilder {
+        let builder = self.http.request(method, url);
+        match self.timeout {
+            Some(timeout) => builder.timeout(timeout),
+            None => builder,
+        }
+    }
+
     /// Get access token
     pub async fn get_token(&self) -> Result<String, WorkspaceError> {
         let tm = self.token_manager.read().await;
@@ -228,7 +320,10 @@ impl ApiClient {
 
     /// Execute a GET request
     pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, WorkspaceError> {
-        self.request_no_body(Method::GET, path, 1).await
+        match self.with_partial_fields(path, path) {
+            Some(url) => self.request_no_body(Method::GET, &url, 1).await,
+            None => self.request_no_body(Method::GET, path, 1).await,
+        }
     }
 
     /// Execute a GET request with query parameters
@@ -247,10 +342,22 @@ impl ApiClient {
             let separator = if base_url.contains('?') { "&" } else { "?" };
             format!("{}{}{}", base_url, separator, query_string)
         };
+        let full_url = self.with_partial_fields(path, &full_url).unwrap_or(full_url);
 
         self.request_no_body(Method::GET, &full_url, 1).await
     }
 
+    /// Put the `--fields` partial-response mask on a GET of a known resource,
+    /// replacing any default `fields` the command chose
+    fn with_partial_fields(&self, path: &str, url: &str) -> Option<String> {
+        let mask = partial::fields_param(self.service, path)?;
+        let (base, query) = url.split_once('?').unwrap_or((url, ""));
+        let mut pairs: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+        pairs.retain(|(key, _)| key != "fields");
+        pairs.push(("fields".to_string(), mask));
+        Some(format!("{}?{}", base, serde_urlencoded::to_string(&pairs).ok()?))
+    }
+
     /// Execute a POST request
     pub async fn post<T, B>(&self, path: &str, body: &B) -> Result<T, WorkspaceError>
     where
@@ -284,6 +391,87 @@ impl ApiClient {
         Ok(())
     }
 
+    /// Execute a request with any method and an optional JSON body, returning
+    /// the raw JSON response (`null` for empty bodies)
+    pub async fn request_json(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, WorkspaceError> {
+        match body {
+            Some(b) => self.request(method, path, Some(b), 1).await,
+            None => self.request_no_body(method, path, 1).await,
+        }
+    }
+
+    /// Execute a media request (upload/download) and return the raw response
+    /// for streaming. `url` is used verbatim (see [`ApiClient::url`]).
+    ///
+    /// `build` is invoked once per attempt so streaming bodies can be
+    /// recreated on retry. `preview` is printed in place of the body in
+    /// dry-run mode. 308 (Resume Incomplete) is returned as a success so
+    /// resumable uploads can inspect the `Range` header.
+    pub async fn send_media<F, Fut>(
+        &self,
+        method: Method,
+        url: &str,
+        preview: serde_json::Value,
+        build: F,
+    ) -> Result<Response, WorkspaceError>
+    where
+        F: Fn(RequestBuilder) -> Fut,
+        Fut: std::future::Future<Output = Result<RequestBuilder, WorkspaceError>>,
+    {
+        // Dry-run: print request details and stop without sending any bytes
+        if self.dry_run {
+            let output = serde_json::json!({
+                "dry_run": true,
+                "method": method.as_str(),
+                "url": url,
+                "body": preview,
+                "auth": "Bearer [REDACTED]"
+            });
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+            return Err(WorkspaceError::DryRun);
+        }
+        // Acquire rate limit (holds the write concurrency permit for the transfer)
+        let wait_started = Instant::now();
+        let _permit: Option<ConcurrencyPermit> = if let Some(ref limiter) = self.rate_limiter {
+            limiter.acquire(1).await.ok().flatten()
+        } else {
+            None
+        };
+        let rate_limit_wait = wait_started.elapsed();
+        let stats = CallStats::default();
+        let started = Instant::now();
+
+        let make_request = || async {
+            let token = self.get_token().await?;
+            let builder = self.http_request(method.clone(), url).bearer_auth(&token);
+            stats.attempt();
+            let response = cassette::send(build(builder).await?).await?;
+
+            let status = response.status();
+            stats.status(status.as_u16());
+            stats.bytes(response.content_length().unwrap_or(0));
+            if status.is_success() || status == StatusCode::PERMANENT_REDIRECT {
+                Ok(response)
+            } else {
+                Err(self.error_from_response(response).await)
+            }
+        };
+
+        let result = with_retry(self.retry_config.clone(), make_request).await;
+        self.trace(&method, url, &stats, started, rate_limit_wait);
+
+        match result {
+            Ok(value) => Ok(value),
+            Err(RetryError::NonRetryable(e)) => Err(e),
+            Err(RetryError::MaxRetriesExceeded { last_error, .. }) => Err(last_error),
+        }
+    }
+
     /// Execute a request without body (GET, DELETE)
     async fn request_no_body<T: DeserializeOwned>(
         &self,
@@ -291,7 +479,7 @@ impl ApiClient {
         path: &str,
         cost: u32,
     ) -> Result<T, WorkspaceError> {
-        // Dry-run: print request details and exit without executing
+        // Dry-run: print request details and stop without executing
         if self.dry_run {
             let url = self.build_url(path);
             let output = serde_json::json!({
@@ -302,14 +490,18 @@ impl ApiClient {
                 "auth": "Bearer [REDACTED]"
             });
             println!("{}", serde_json::to_string_pretty(&output).unwrap());
-            std::process::exit(0);
+            return Err(WorkspaceError::DryRun);
         }
         // Acquire rate limit
+        let wait_started = Instant::now();
         let _permit: Option<ConcurrencyPermit> = if let Some(ref limiter) = self.rate_limiter {
             limiter.acquire(cost).await.ok().flatten()
         } else {
             None
         };
+        let rate_limit_wait = wait_started.elapsed();
+        let stats = CallStats::default();
+        let started = Instant::now();
 
         let url = self.build_url(path);
 
@@ -318,15 +510,35 @@ impl ApiClient {
             // Get fresh token for each attempt (in case it expires during retries)
             let token = self.get_token().await?;
 
-            let builder = self.http.request(method.clone(), &url)
+            let mut builder = self.http_request(method.clone(), &url)
                 .bearer_auth(&token);
 
-            let response = builder.send().await?;
-            self.handle_response(response).await
+            // Revalidate a cached GET instead of refetching the payload
+            // Entries may need decrypting, which can prompt for the passphrase
+            let cached = match self.cache {
+                Some(ref cache) if method == Method::GET => {
+                    let (cache, url) = (cache.clone(), url.clone());
+                    tokio::task::spawn_blocking(move || cache.get(&url)).await.ok().flatten()
+                }
+                _ => None,
+            };
+            if let Some(ref entry) = cached {
+                builder = builder.header(reqwest::header::IF_NONE_MATCH, &entry.etag);
+            }
+
+            stats.attempt();
+            let response = cassette::send(builder).await?;
+            stats.status(response.status().as_u16());
+            if method == Method::GET {
+                self.handle_cacheable_response(&url, response, cached, &stats).await
+            } else {
+                self.handle_response(response, &stats).await
+            }
         };
 
         // Execute with retry
         let result = with_retry(self.retry_config.clone(), make_request).await;
+        self.trace(&method, &url, &stats, started, rate_limit_wait);
 
         match result {
             Ok(value) => Ok(value),
@@ -347,7 +559,7 @@ impl ApiClient {
         T: DeserializeOwned,
         B: Serialize + ?Sized,
     {
-        // Dry-run: print request details and exit without executing
+        // Dry-run: print request details and stop without executing
         if self.dry_run {
             let url = self.build_url(path);
             let body_json = if let Some(b) = body {
@@ -363,14 +575,18 @@ impl ApiClient {
                 "auth": "Bearer [REDACTED]"
             });
             println!("{}", serde_json::to_string_pretty(&output).unwrap());
-            std::process::exit(0);
+            return Err(WorkspaceError::DryRun);
         }
         // Acquire rate limit
+        let wait_started = Instant::now();
         let _permit: Option<ConcurrencyPermit> = if let Some(ref limiter) = self.rate_limiter {
             limiter.acquire(cost).await.ok().flatten()
         } else {
             None
         };
+        let rate_limit_wait = wait_started.elapsed();
+        let stats = CallStats::default();
+        let started = Instant::now();
 
         let url = self.build_url(path);
 
@@ -379,19 +595,22 @@ impl ApiClient {
             // Get fresh token for each attempt (in case it expires during retries)
             let token = self.get_token().await?;
 
-            let mut builder = self.http.request(method.clone(), &url)
+            let mut builder = self.http_request(method.clone(), &url)
                 .bearer_auth(&token);
 
             if let Some(b) = body {
                 builder = builder.json(b);
             }
 
-            let response = builder.send().await?;
-            self.handle_response(response).await
+            stats.attempt();
+            let response = cassette::send(builder).await?;
+            stats.status(response.status().as_u16());
+            self.handle_response(response, &stats).await
         };
 
         // Execute with retry
         let result = with_retry(self.retry_config.clone(), make_request).await;
+        self.trace(&method, &url, &stats, started, rate_limit_wait);
 
         match result {
             Ok(value) => Ok(value),
@@ -401,33 +620,96 @@ impl ApiClient {
     }
 
     /// Handle API response
-    async fn handle_response<T: DeserializeOwned>(&self, response: Response) -> Result<T, WorkspaceError> {
+    async fn handle_response<T: DeserializeOwned>(&self, response: Response, stats: &CallStats) -> Result<T, WorkspaceError> {
         let status = response.status();
 
         if status.is_success() {
-            response.json().await.map_err(WorkspaceError::from)
+            let body = response.text().await?;
+            stats.bytes(body.len() as u64);
+            parse_body(&body)
         } else {
-            let retry_after = response.headers()
-                .get("retry-after")
-                .and_then(|v| v.to_str().ok())
-                .and_then(parse_retry_after)
-                .map(|d| d.as_secs());
-
-            let error_body: serde_json::Value = response.json().await.unwrap_or_default();
-            let message = error_body
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or("Unknown error")
-                .to_string();
-
-            Err(WorkspaceError::Api(ApiError {
-                code: status.as_u16(),
-                message,
-                domain: "api".to_string(),
-                retry_after,
-            }))
+            Err(self.error_from_response(response).await)
+        }
+    }
+
+    /// Handle a GET response: serve the cached body on 304, store bodies that carry an ETag
+    async fn handle_cacheable_response<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        response: Response,
+        cached: Option<CacheEntry>,
+        stats: &CallStats,
+    ) -> Result<T, WorkspaceError> {
+        let status = response.status();
+
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(entry) = cached {
+                return parse_body(&entry.body);
+            }
+        }
+
+        let cache = match self.cache {
+            Some(ref cache) if status.is_success() => cache,
+            _ => return self.handle_response(response, stats).await,
+        };
+        let etag = response.headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+
+        let body = response.text().await?;
+        stats.bytes(body.len() as u64);
+        let parsed = parse_body(&body);
+        if let Some(etag) = etag {
+            let (cache, url) = (cache.clone(), url.to_string());
+            let _ = tokio::task::spawn_blocking(move || cache.put(&url, &etag, &body)).await;
         }
+        parsed
+    }
+
+    /// Emit a `--trace` record for a finished call; every attempt is charged quota
+    fn trace(&self, method: &Method, url: &str, stats: &CallStats, started: Instant, rate_limit_wait: Duration) {
+        let units = trace::quota_units(self.service, method, url) * stats.attempts().max(1);
+        trace::record(self.service, method, url, stats, started.elapsed(), rate_limit_wait, units);
+    }
+
+    /// Convert a non-success response into an API error
+    async fn error_from_response(&self, response: Response) -> WorkspaceError {
+        let status = response.status();
+        let retry_after = response.headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after)
+            .map(|d| d.as_secs());
+
+        // Let the rate limiter (and, when shared, other processes) back off too
+        if let (Some(seconds), Some(ref limiter)) = (retry_after, &self.rate_limiter) {
+            limiter.penalize(Duration::from_secs(seconds)).await;
+        }
+
+        let error_body: serde_json::Value = response.json().await.unwrap_or_default();
+        let message = error_body
+            .get("error")
+            .and_then(|e| e.get("message"))
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        WorkspaceError::Api(ApiError {
+            code: status.as_u16(),
+            message,
+            domain: "api".to_string(),
+            retry_after,
+        })
+    }
+}
+
+/// Deserialize a response body; empty bodies (e.g. 204 from DELETE) parse as `null`
+fn parse_body<T: DeserializeOwned>(text: &str) -> Result<T, WorkspaceError> {
+    if text.trim().is_empty() {
+        serde_json::from_value(serde_json::Value::Null).map_err(WorkspaceError::from)
+    } else {
+        serde_json::from_str(text).map_err(WorkspaceError::from)
     }
 }
 
@@ -1,8 +1,17 @@
+use futures::StreamExt;
 use reqwest::{Client, Method};
 use serde::de::DeserializeOwned;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+use crate::error::WorkspaceError;
+use super::api_client::endpoints;
+use super::cassette;
+use super::network;
+use super::trace::{self, CallStats};
+use super::retry::{RetryConfig, RetryState, Retryable, with_retry, is_retryable_status, parse_retry_after};
+
 /// Batch request endpoints
 pub mod batch_endpoints {
     pub const GMAIL: &str = "https://gmail.googleapis.com/batch/gmail/v1";
@@ -83,46 +92,77 @@ pub struct BatchClient {
     http: Client,
     endpoint: String,
     max_requests: usize,
+    /// Number of chunks of `max_requests` sent in parallel
+    concurrency: usize,
+    retry_config: RetryConfig,
+    /// Service name used in `--trace` records and quota estimates
+    service: &'static str,
+    /// Per-request timeout from `[api.services.<name>]`, overriding the client's
+    timeout: Option<Duration>,
 }
 
 impl BatchClient {
     pub fn new(endpoint: impl Into<String>) -> Self {
-        let http = Client::builder()
-            .timeout(Duration::from_secs(120))
-            .pool_max_idle_per_host(10)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .tcp_keepalive(Duration::from_secs(60))
-            .build()
-            .expect("Failed to create HTTP client");
+        // A batch carries up to 100 calls, so it gets at least two minutes
+        let timeout = network::default_timeout().max(Duration::from_secs(120));
 
         Self {
-            http,
+            http: network::http_client(timeout),
             endpoint: endpoint.into(),
             max_requests: 100, // Google's limit
+            concurrency: 4,
+            retry_config: RetryConfig::conservative(),
+            service: "batch",
+            timeout: None,
         }
     }
 
+    /// Set how many chunks of requests are sent in parallel
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set retry configuration for failed chunks and 429/5xx parts
+    pub fn with_retry_config(mut self, config: RetryConfig) -> Self {
+        self.retry_config = config;
+        self
+    }
+
+    /// Set the service name reported by `--trace` and apply its
+    /// `[api.services.<name>]` timeout and retry settings
+    pub fn with_service(mut self, service: &'static str) -> Self {
+        self.service = service;
+        self.timeout = network::service_timeout(service);
+        self.retry_config = network::retry_config(service, self.retry_config);
+        self
+    }
+
     /// Create a Gmail batch client
     pub fn gmail() -> Self {
-        Self::new(batch_endpoints::GMAIL)
+        Self::new(endpoints::resolve("gmail_batch", batch_endpoints::GMAIL)).with_service("gmail")
     }
 
     /// Create a Drive batch client
     pub fn drive() -> Self {
-        Self::new(batch_endpoints::DRIVE)
+        Self::new(endpoints::resolve("drive_batch", batch_endpoints::DRIVE)).with_service("drive")
     }
 
     /// Create a Calendar batch client
     pub fn calendar() -> Self {
-        Self::new(batch_endpoints::CALENDAR)
+        Self::new(endpoints::resolve("calendar_batch", batch_endpoints::CALENDAR)).with_service("calendar")
     }
 
     /// Create a Chat batch client
     pub fn chat() -> Self {
-        Self::new(batch_endpoints::CHAT)
+        Self::new(endpoints::resolve("chat_batch", batch_endpoints::CHAT)).with_service("chat")
     }
 
-    /// Execute a batch of requests
+    /// Execute a batch of requests of any size.
+    ///
+    /// Requests are split into chunks of `max_requests` that run with bounded
+    /// concurrency. Parts that fail with 429/5xx are retried with backoff, and
+    /// responses are returned in input order.
     pub async fn execute(
         &self,
         requests: Vec<BatchRequest>,
@@ -132,6 +172,114 @@ impl BatchClient {
             return Ok(Vec::new());
         }
 
+        let chunks: Vec<Vec<BatchRequest>> = requests
+            .chunks(self.max_requests)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+
+        // `buffered` keeps chunk results in input order
+        let results: Vec<Result<Vec<BatchResponse>, BatchError>> = futures::stream::iter(chunks)
+            .map(|chunk| self.execute_chunk(chunk, access_token))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut responses = Vec::with_capacity(requests.len());
+        for result in results {
+            responses.extend(result?);
+        }
+        Ok(responses)
+    }
+
+    /// Execute one chunk, retrying 429/5xx parts (and missing parts) until they
+    /// succeed or the retry budget runs out
+    async fn execute_chunk(
+        &self,
+        requests: Vec<BatchRequest>,
+        access_token: &str,
+    ) -> Result<Vec<BatchResponse>, BatchError> {
+        let mut results: Vec<Option<BatchResponse>> = vec![None; requests.len()];
+        let mut pending: Vec<usize> = (0..requests.len()).collect();
+        let mut state = RetryState::new(self.retry_config.clone());
+
+        loop {
+            // Content-IDs are positions in the chunk, so duplicate caller ids still map back correctly
+            let parts: Vec<BatchRequest> = pending.iter()
+                .map(|&i| BatchRequest { id: i.to_string(), ..requests[i].clone() })
+                .collect();
+
+            let stats = CallStats::default();
+            let started = Instant::now();
+            let responses = with_retry(self.retry_config.clone(), || self.send(&parts, access_token, &stats)).await;
+            let units: u32 = parts.iter()
+                .map(|p| trace::quota_units(self.service, &p.method, &p.path))
+                .sum();
+            trace::record(
+                self.service,
+                &Method::POST,
+                &self.endpoint,
+                &stats,
+                started.elapsed(),
+                Duration::ZERO,
+                units * stats.attempts().max(1),
+            );
+            let responses = responses.map_err(|e| e.into_inner())?;
+
+            let mut by_index: HashMap<usize, BatchResponse> = responses.into_iter()
+                .filter_map(|resp| Some((resp.id.parse().ok()?, resp)))
+                .collect();
+
+            let mut retry = Vec::new();
+            let mut retry_after: Option<Duration> = None;
+            for i in pending {
+                match by_index.remove(&i) {
+                    Some(resp) if is_retryable_status(resp.status) && state.should_retry() => {
+                        let wait = resp.headers.iter()
+                            .find(|(k, _)| k.eq_ignore_ascii_case("retry-after"))
+                            .and_then(|(_, v)| parse_retry_after(v));
+                        retry_after = retry_after.max(wait);
+                        retry.push(i);
+                    }
+                    Some(mut resp) => {
+                        resp.id = requests[i].id.clone();
+                        results[i] = Some(resp);
+                    }
+                    None if state.should_retry() => retry.push(i),
+                    None => results[i] = Some(BatchResponse {
+                        id: requests[i].id.clone(),
+                        status: 500,
+                        headers: Vec::new(),
+                        body: serde_json::json!({ "error": { "message": "No response for request in batch" } }),
+                    }),
+                }
+            }
+
+            if retry.is_empty() {
+                break;
+            }
+
+            let Some(backoff) = state.next_backoff() else { break };
+            let duration = retry_after.unwrap_or(backoff);
+            tracing::debug!(
+                parts = retry.len(),
+                attempt = state.attempt(),
+                backoff_ms = duration.as_millis() as u64,
+                "Retrying batch parts after backoff"
+            );
+            tokio::time::sleep(duration).await;
+            pending = retry;
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Send a single multipart request (at most `max_requests` parts)
+    async fn send(
+        &self,
+        requests: &[BatchRequest],
+        access_token: &str,
+        stats: &CallStats,
+    ) -> Result<Vec<BatchResponse>, BatchError> {
         if requests.len() > self.max_requests {
             return Err(BatchError::TooManyRequests {
                 count: requests.len(),
@@ -140,21 +288,30 @@ impl BatchClient {
         }
 
         let boundary = format!("batch_{}", Uuid::new_v4().to_string().replace("-", ""));
-        let body = self.build_multipart_body(&requests, &boundary);
-
-        let response = self.http
+        let body = self.build_multipart_body(requests, &boundary);
+        let mut builder = self.http
             .post(&self.endpoint)
             .header("Content-Type", format!("multipart/mixed; boundary={}", boundary))
             .header("Authorization", format!("Bearer {}", access_token))
-            .body(body)
-            .send()
-            .await
-            .map_err(BatchError::Network)?;
+            .body(body);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        stats.attempt();
+        let response = cassette::send(builder).await.map_err(|e| match e {
+            WorkspaceError::Network(e) => BatchError::Network(e),
+            other => BatchError::InvalidResponse(other.to_string()),
+        })?;
+        stats.status(response.status().as_u16());
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = response.headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
             let text = response.text().await.unwrap_or_default();
-            return Err(BatchError::HttpError { status, message: text });
+            return Err(BatchError::HttpError { status, message: text, retry_after });
         }
 
         // Get the response boundary from Content-Type header
@@ -167,6 +324,7 @@ impl BatchClient {
             .ok_or_else(|| BatchError::InvalidResponse("Missing boundary in response".into()))?;
 
         let response_body = response.text().await.map_err(BatchError::Network)?;
+        stats.bytes(response_body.len() as u64);
         self.parse_multipart_response(&response_body, &response_boundary)
     }
 
@@ -314,8 +472,25 @@ pub enum BatchError {
     Network(#[from] reqwest::Error),
 
     #[error("HTTP error {status}: {message}")]
-    HttpError { status: u16, message: String },
+    HttpError { status: u16, message: String, retry_after: Option<Duration> },
 
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
 }
+
+impl Retryable for BatchError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            BatchError::Network(_) => true,
+            BatchError::HttpError { status, .. } => is_retryable_status(*status),
+            _ => false,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            BatchError::HttpError { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
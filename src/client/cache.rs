@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::encryption;
+
+/// A cached GET response, revalidated with `If-None-Match`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Profile and impersonated subject the response was fetched as
+    #[serde(default)]
+    pub account: String,
+    pub url: String,
+    pub etag: String,
+    pub body: String,
+}
+
+/// On-disk ETag cache for GET responses, one file per account and URL.
+/// Entries are user-only and encrypted like token files when token encryption
+/// is on. Least-recently-used entries are evicted once the directory exceeds
+/// `max_bytes`.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    account: String,
+}
+
+static SHARED: RwLock<Option<Arc<ResponseCache>>> = RwLock::new(None);
+
+/// Install the process-wide cache used by every `ApiClient`. Call once at
+/// startup, before any client is constructed; `None` disables caching.
+pub fn configure(cache: Option<ResponseCache>) {
+    *SHARED.write().unwrap_or_else(|e| e.into_inner()) = cache.map(Arc::new);
+}
+
+/// The process-wide cache, if enabled
+pub fn shared() -> Option<Arc<ResponseCache>> {
+    SHARED.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+impl ResponseCache {
+    /// `account` separates entries of different profiles and `--as` subjects,
+    /// which may see different responses for the same URL
+    pub fn new(dir: PathBuf, max_bytes: u64, account: &str) -> Self {
+        Self { dir, max_bytes, account: account.to_string() }
+    }
+
+    /// Default location under the user cache dir; `None` when unavailable
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|p| p.join("workspace-cli").join("http"))
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let key = format!("{}\n{}", self.account, url);
+        self.dir.join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+    }
+
+    /// Look up the entry for a URL, marking it as recently used
+    pub fn get(&self, url: &str) -> Option<CacheEntry> {
+        let path = self.entry_path(url);
+        let entry: CacheEntry = serde_json::from_slice(&encryption::read_file(&path).ok()?).ok()?;
+        if entry.account != self.account || entry.url != url {
+            return None;
+        }
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(entry)
+    }
+
+    /// Store a response body with its ETag, then evict down to the size bound.
+    /// Failures are ignored: the cache is an optimization only.
+    pub fn put(&self, url: &str, etag: &str, body: &str) {
+        let entry = CacheEntry {
+            account: self.account.clone(),
+            url: url.to_string(),
+            etag: etag.to_string(),
+            body: body.to_string(),
+        };
+        let Ok(content) = serde_json::to_string(&entry) else { return };
+        if content.len() as u64 > self.max_bytes || std::fs::create_dir_all(&self.dir).is_err() {
+            return;
+        }
+        if encryption::write_file(&self.entry_path(url), content.as_bytes()).is_ok() {
+            self.evict();
+        }
+    }
+
+    /// Drop the least-recently-used entries until the cache fits in `max_bytes`
+    fn evict(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+        let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((e.path(), meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            })
+            .collect();
+
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return;
+        }
+
+        files.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= size;
+            }
+        }
+    }
+}
+
+/// FNV-1a: stable across builds, so cache file names survive upgrades
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
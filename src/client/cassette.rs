@@ -0,0 +1,350 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use base64::Engine;
+use reqwest::{Request, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::error::WorkspaceError;
+
+/// Placeholder written in place of scrubbed secrets
+const REDACTED: &str = "[REDACTED]";
+
+/// Request headers that are recorded (everything else, including auth, is dropped)
+const RECORDED_REQUEST_HEADERS: &[&str] = &[
+    "content-type",
+    "content-range",
+    "x-upload-content-type",
+    "x-upload-content-length",
+];
+
+/// Query parameters that carry credentials. A resumable upload's
+/// `upload_id` is a bearer credential for its session.
+const SECRET_QUERY_PARAMS: &[&str] = &["access_token", "key", "oauth_token", "upload_id"];
+
+/// Response headers that repeat a resumable upload's session id
+const SECRET_RESPONSE_HEADERS: &[&str] = &["x-guploader-uploadid"];
+
+/// HTTP record/replay mode for the process. Recording writes each
+/// request/response pair to its own JSON file ("cassette") with auth
+/// scrubbed; replaying serves responses from those files without touching
+/// the network or needing credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Off,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// A recorded request/response pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Request body, when it was buffered and valid UTF-8 (streamed media is not recorded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Body when it is JSON (kept structured so cassettes are easy to edit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<serde_json::Value>,
+    /// Body when it is text but not JSON (e.g. multipart batch responses)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Body when it is binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    /// The body was not recorded: media (downloads, exports) is streamed
+    /// through instead of being buffered, and replays with an empty body
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub omitted: bool,
+}
+
+struct State {
+    mode: Mode,
+    /// Next sequence number for recorded files
+    next_seq: Option<usize>,
+    /// Loaded interactions and whether each has been served yet
+    replay: Option<Vec<(Interaction, bool)>>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    mode: Mode::Off,
+    next_seq: None,
+    replay: None,
+});
+
+/// Install the cassette mode. Call once at startup, before any request is sent.
+pub fn configure(mode: Mode) {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    state.mode = mode;
+    state.next_seq = None;
+    state.replay = None;
+}
+
+/// Send a request, recording or replaying it according to the current mode
+pub async fn send(builder: RequestBuilder) -> Result<Response, WorkspaceError> {
+    let (client, request) = builder.build_split();
+    let request = request?;
+
+    let mode = STATE.lock().unwrap_or_else(|e| e.into_inner()).mode.clone();
+    match mode {
+        Mode::Off => Ok(client.execute(request).await?),
+        Mode::Replay(dir) => replay(&dir, &record_request(&request)),
+        Mode::Record(dir) => {
+            let recorded_request = record_request(&request);
+            let response = client.execute(request).await?;
+
+            let status = response.status().as_u16();
+            let headers: BTreeMap<String, String> = response.headers()
+                .iter()
+                .filter(|(name, _)| name.as_str() != "set-cookie")
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .map(|(name, value)| scrub_response_header(name, value))
+                .collect();
+
+            if !is_textual(headers.get("content-type").map(String::as_str)) {
+                let interaction = Interaction {
+                    request: recorded_request,
+                    response: RecordedResponse {
+                        status,
+                        headers,
+                        omitted: true,
+                        ..encode_body(&[])
+                    },
+                };
+                save(&dir, &interaction)?;
+                return Ok(response);
+            }
+            let bytes = response.bytes().await?.to_vec();
+
+            let interaction = Interaction {
+                request: recorded_request,
+                response: RecordedResponse {
+                    status,
+                    headers,
+                    ..encode_body(&bytes)
+                },
+            };
+            save(&dir, &interaction)?;
+            build_response(&interaction.response)
+        }
+    }
+}
+
+/// Scrubbed, comparable form of an outgoing request
+fn record_request(request: &Request) -> RecordedRequest {
+    let headers = request.headers()
+        .iter()
+        .filter(|(name, _)| RECORDED_REQUEST_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut body = request.body()
+        .and_then(|b| b.as_bytes())
+        .and_then(|b| std::str::from_utf8(b).ok())
+        .map(|s| s.to_string());
+
+    // Multipart boundaries are random per run; normalize so replays match
+    if let (Some(ref mut text), Some(boundary)) = (&mut body, headers.get("content-type").and_then(|ct| boundary_of(ct))) {
+        *text = text.replace(&boundary, "BOUNDARY");
+    }
+    let headers = headers.into_iter()
+        .map(|(name, value)| match boundary_of(&value) {
+            Some(boundary) if name == "content-type" => (name, value.replace(&boundary, "BOUNDARY")),
+            _ => (name, value),
+        })
+        .collect();
+
+    RecordedRequest {
+        method: request.method().to_string(),
+        url: scrub_url(request.url().as_str()),
+        headers,
+        body,
+    }
+}
+
+fn boundary_of(content_type: &str) -> Option<String> {
+    content_type
+        .split(';')
+        .find_map(|p| p.trim().strip_prefix("boundary="))
+        .map(|b| b.trim_matches('"').to_string())
+}
+
+/// Replace credential query parameters in a URL
+pub(crate) fn scrub_url(url: &str) -> String {
+    let Ok(mut url) = url::Url::parse(url) else { return url.to_string() };
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url.query_pairs()
+            .map(|(k, v)| {
+                let v = if SECRET_QUERY_PARAMS.contains(&k.as_ref()) { REDACTED.to_string() } else { v.to_string() };
+                (k.to_string(), v)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Scrub a recorded response header. `Location` holds the session URI of a
+/// resumable upload, so it goes through [`scrub_url`] like request URLs.
+fn scrub_response_header(name: String, value: String) -> (String, String) {
+    match name.as_str() {
+        "location" => (name, scrub_url(&value)),
+        n if SECRET_RESPONSE_HEADERS.contains(&n) => (name, REDACTED.to_string()),
+        _ => (name, value),
+    }
+}
+
+/// Whether a response body is JSON or text and so worth keeping in the
+/// cassette. Bodies without a Content-Type are usually empty.
+fn is_textual(content_type: Option<&str>) -> bool {
+    let Some(content_type) = content_type else { return true };
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.starts_with("multipart/")
+        || essence.ends_with("/json")
+        || essence.ends_with("+json")
+}
+
+fn encode_body(bytes: &[u8]) -> RecordedResponse {
+    let mut response = RecordedResponse {
+        status: 0,
+        headers: BTreeMap::new(),
+        json: None,
+        text: None,
+        base64: None,
+        omitted: false,
+    };
+    if bytes.is_empty() {
+        return response;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => match serde_json::from_str(text) {
+            Ok(json) => response.json = Some(json),
+            Err(_) => response.text = Some(text.to_string()),
+        },
+        Err(_) => response.base64 = Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+    }
+    response
+}
+
+fn build_response(recorded: &RecordedResponse) -> Result<Response, WorkspaceError> {
+    let body = if let Some(ref json) = recorded.json {
+        serde_json::to_vec(json)?
+    } else if let Some(ref text) = recorded.text {
+        text.clone().into_bytes()
+    } else if let Some(ref data) = recorded.base64 {
+        base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| WorkspaceError::Config(format!("Invalid cassette body: {}", e)))?
+    } else {
+        Vec::new()
+    };
+
+    let mut builder = http::Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        // The body is re-encoded, so framing headers from the original response no longer apply
+        if matches!(name.as_str(), "content-length" | "transfer-encoding" | "content-encoding") {
+            continue;
+        }
+        builder = builder.header(name, value);
+    }
+    let response = builder
+        .body(body)
+        .map_err(|e| WorkspaceError::Config(format!("Invalid cassette response: {}", e)))?;
+    Ok(Response::from(response))
+}
+
+fn save(dir: &Path, interaction: &Interaction) -> Result<(), WorkspaceError> {
+    std::fs::create_dir_all(dir)?;
+
+    let seq = {
+        let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = *state.next_seq.get_or_insert_with(|| load_files(dir).len());
+        state.next_seq = Some(seq + 1);
+        seq
+    };
+
+    let slug: String = url::Url::parse(&interaction.request.url).ok()
+        .and_then(|u| u.path_segments()?.rfind(|s| !s.is_empty()).map(|s| s.to_string()))
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(40)
+        .collect();
+    let file = dir.join(format!(
+        "{:04}-{}-{}.json",
+        seq,
+        interaction.request.method.to_lowercase(),
+        slug
+    ));
+
+    std::fs::write(file, serde_json::to_string_pretty(interaction)?)?;
+    Ok(())
+}
+
+/// Cassette files in a directory, in recording order
+fn load_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Serve the first unused interaction matching the request
+fn replay(dir: &Path, request: &RecordedRequest) -> Result<Response, WorkspaceError> {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if state.replay.is_none() {
+        let mut interactions = Vec::new();
+        for file in load_files(dir) {
+            let content = std::fs::read_to_string(&file)?;
+            let interaction: Interaction = serde_json::from_str(&content).map_err(|e| {
+                WorkspaceError::Config(format!("Invalid cassette {}: {}", file.display(), e))
+            })?;
+            interactions.push((interaction, false));
+        }
+        state.replay = Some(interactions);
+    }
+
+    let interactions = state.replay.as_mut().expect("cassettes loaded above");
+    let found = interactions.iter_mut().find(|(recorded, used)| {
+        !*used
+            && recorded.request.method == request.method
+            && recorded.request.url == request.url
+            && (recorded.request.body.is_none() || recorded.request.body == request.body)
+    });
+
+    match found {
+        Some((interaction, used)) => {
+            *used = true;
+            build_response(&interaction.response)
+        }
+        None => Err(WorkspaceError::Config(format!(
+            "No recorded response for {} {} in {}",
+            request.method,
+            request.url,
+            dir.display()
+        ))),
+    }
+}
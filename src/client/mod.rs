@@ -1,7 +1,12 @@
 pub mod api_client;
 pub mod batch;
+pub mod cache;
+pub mod cassette;
+pub mod network;
+pub mod partial;
 pub mod rate_limiter;
 pub mod retry;
+pub mod trace;
 
 pub use api_client::{ApiClient, endpoints};
 pub use batch::{BatchClient, BatchRequest, BatchResponse, BatchError, batch_endpoints};
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use reqwest::{Certificate, Client, Proxy};
+
+use crate::config::{ApiConfig, ServiceConfig};
+use crate::error::WorkspaceError;
+use super::rate_limiter::RateLimitConfig;
+use super::retry::RetryConfig;
+
+/// Network settings installed from `[api]` config / environment
+struct Settings {
+    timeout: Duration,
+    max_retries: Option<u32>,
+    services: BTreeMap<String, ServiceConfig>,
+    proxy: Option<Proxy>,
+    certificates: Vec<Certificate>,
+    user_agent: Option<String>,
+}
+
+static SETTINGS: RwLock<Option<Settings>> = RwLock::new(None);
+
+/// Install network settings from config. Call once at startup, before any
+/// client is constructed. Fails on an invalid proxy URL, CA bundle or refill rate.
+pub fn configure(config: &ApiConfig) -> Result<(), WorkspaceError> {
+    let proxy = config.proxy.as_deref()
+        .map(Proxy::all)
+        .transpose()
+        .map_err(|e| WorkspaceError::Config(format!("Invalid proxy: {}", e)))?;
+
+    let certificates = match config.ca_bundle {
+        Some(ref path) => {
+            let pem = std::fs::read(path).map_err(|e| {
+                WorkspaceError::Config(format!("Cannot read CA bundle {}: {}", path.display(), e))
+            })?;
+            Certificate::from_pem_bundle(&pem).map_err(|e| {
+                WorkspaceError::Config(format!("Invalid CA bundle {}: {}", path.display(), e))
+            })?
+        }
+        None => Vec::new(),
+    };
+
+    for (service, overrides) in &config.services {
+        if overrides.rate_limit_refill.is_some_and(|r| r.is_nan() || r <= 0.0) {
+            return Err(WorkspaceError::Config(format!(
+                "[api.services.{}] rate_limit_refill must be greater than 0",
+                service
+            )));
+        }
+    }
+
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = Some(Settings {
+        timeout: Duration::from_secs(config.timeout_seconds),
+        max_retries: config.max_retries,
+        services: config.services.clone(),
+        proxy,
+        certificates,
+        user_agent: config.user_agent.clone(),
+    });
+    Ok(())
+}
+
+/// Build an HTTP client with the configured proxy, CA bundle and user agent
+pub fn http_client(timeout: Duration) -> Client {
+    let settings = SETTINGS.read().unwrap_or_else(|e| e.into_inner());
+    let user_agent = settings.as_ref()
+        .and_then(|s| s.user_agent.clone())
+        .unwrap_or_else(|| format!("workspace-cli/{}", env!("CARGO_PKG_VERSION")));
+
+    let mut builder = Client::builder()
+        .timeout(timeout)
+        .user_agent(user_agent)
+        .pool_max_idle_per_host(10)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60));
+    if let Some(ref settings) = *settings {
+        if let Some(ref proxy) = settings.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        for certificate in &settings.certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+    }
+    builder.build().expect("Failed to create HTTP client")
+}
+
+/// The `[api] timeout_seconds` setting (30s when unconfigured)
+pub fn default_timeout() -> Duration {
+    SETTINGS.read().unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|s| s.timeout)
+        .unwrap_or(Duration::from_secs(30))
+}
+
+/// Per-service timeout from `[api.services.<name>]`, overriding the client default
+pub fn service_timeout(service: &str) -> Option<Duration> {
+    SETTINGS.read().unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|s| s.services.get(service))
+        .and_then(|c| c.timeout_seconds)
+        .map(Duration::from_secs)
+}
+
+/// Apply configured retry overrides for a service on top of its built-in policy
+pub fn retry_config(service: &str, mut config: RetryConfig) -> RetryConfig {
+    let settings = SETTINGS.read().unwrap_or_else(|e| e.into_inner());
+    let Some(ref s) = *settings else { return config };
+    let overrides = s.services.get(service);
+
+    if let Some(max) = overrides.and_then(|c| c.max_retries).or(s.max_retries) {
+        config.max_retries = max;
+    }
+    if let Some(ms) = overrides.and_then(|c| c.initial_backoff_ms) {
+        config.initial_backoff = Duration::from_millis(ms);
+    }
+    if let Some(ms) = overrides.and_then(|c| c.max_backoff_ms) {
+        config.max_backoff = Duration::from_millis(ms);
+    }
+    config
+}
+
+/// Apply configured rate-limit overrides for a service on top of its built-in limits
+pub fn rate_limit(service: &str, mut config: RateLimitConfig) -> RateLimitConfig {
+    let settings = SETTINGS.read().unwrap_or_else(|e| e.into_inner());
+    let Some(overrides) = settings.as_ref().and_then(|s| s.services.get(service)) else { return config };
+
+    if let Some(capacity) = overrides.rate_limit_capacity {
+        config.capacity = capacity.max(1);
+    }
+    if let Some(refill) = overrides.rate_limit_refill {
+        config.refill_rate = refill;
+    }
+    config
+}
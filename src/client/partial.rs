@@ -0,0 +1,122 @@
+use std::sync::RwLock;
+
+use crate::utils::field_mask::{build_fields_param, validate_field_mask};
+
+/// A read endpoint whose response the CLI prints as-is, so `--fields` can be
+/// sent to the server as a `fields=` partial-response mask
+struct Resource {
+    service: &'static str,
+    /// Request path, `*` matching one segment
+    path: &'static str,
+    /// Array key of a list response (`None` for a single resource)
+    wrapper: Option<&'static str>,
+    /// Top-level keys of list responses that are always requested (paging)
+    keep: &'static [&'static str],
+    /// Fields the response types cannot deserialize without. Nested ones
+    /// (`attendees/email`) are only added when their parent is selected.
+    required: &'static [&'static str],
+}
+
+const RESOURCES: &[Resource] = &[
+    Resource { service: "gmail", path: "/users/me/labels", wrapper: Some("labels"), keep: &[], required: &["id", "name"] },
+    Resource { service: "drive", path: "/files", wrapper: Some("files"), keep: &["nextPageToken", "incompleteSearch"], required: &["id", "name", "mimeType"] },
+    Resource { service: "drive", path: "/files/*", wrapper: None, keep: &[], required: &["id", "name", "mimeType"] },
+    Resource { service: "drive", path: "/files/*/permissions", wrapper: Some("permissions"), keep: &["nextPageToken"], required: &["type", "role"] },
+    Resource { service: "drive", path: "/drives", wrapper: Some("drives"), keep: &["nextPageToken"], required: &["id", "name"] },
+    Resource { service: "calendar", path: "/calendars/*/events", wrapper: Some("items"), keep: &["nextPageToken", "nextSyncToken"], required: &["attendees/email"] },
+    Resource { service: "calendar", path: "/users/me/calendarList", wrapper: Some("items"), keep: &["nextPageToken"], required: &["id"] },
+    Resource { service: "tasks", path: "/users/@me/lists", wrapper: Some("items"), keep: &["nextPageToken"], required: &["id", "title"] },
+    Resource { service: "tasks", path: "/lists/*/tasks", wrapper: Some("items"), keep: &["nextPageToken"], required: &["title", "links/type"] },
+    Resource { service: "chat", path: "/spaces", wrapper: Some("spaces"), keep: &["nextPageToken"], required: &[] },
+    Resource { service: "chat", path: "/spaces/*", wrapper: None, keep: &[], required: &[] },
+    Resource { service: "chat", path: "/spaces/*/messages", wrapper: Some("messages"), keep: &["nextPageToken"], required: &[] },
+    Resource { service: "chat", path: "/spaces/*/messages/*", wrapper: None, keep: &[], required: &[] },
+    Resource { service: "contacts", path: "/people/me/connections", wrapper: Some("connections"), keep: &["nextPageToken", "totalPeople", "totalItems"], required: &[] },
+    Resource { service: "contacts", path: "/people:listDirectoryPeople", wrapper: Some("people"), keep: &["nextPageToken", "totalSize"], required: &[] },
+    Resource { service: "contacts", path: "/people/*", wrapper: None, keep: &[], required: &[] },
+    Resource { service: "admin", path: "/users", wrapper: Some("users"), keep: &["nextPageToken"], required: &[] },
+    Resource { service: "admin", path: "/users/*", wrapper: None, keep: &[], required: &[] },
+    Resource { service: "groups", path: "/groups", wrapper: Some("groups"), keep: &["nextPageToken"], required: &[] },
+    Resource { service: "groups", path: "/groups/*/memberships", wrapper: Some("memberships"), keep: &["nextPageToken"], required: &[] },
+    // Only the raw JSON modes of `docs get`, `sheets get --full` and `slides get --full`
+    // select these; Markdown, text and plain values need the whole resource
+    Resource { service: "docs", path: "/documents/*", wrapper: None, keep: &[], required: &["documentId", "title", "body/content"] },
+    Resource { service: "sheets", path: "/spreadsheets/*/values/*", wrapper: None, keep: &[], required: &["range"] },
+    Resource { service: "slides", path: "/presentations/*", wrapper: None, keep: &[], required: &["presentationId", "title", "slides/objectId", "slides/pageElements/objectId", "masters/objectId", "layouts/objectId"] },
+];
+
+/// Array keys of the list responses above, for client-side `--fields` filtering
+pub fn list_wrappers() -> impl Iterator<Item = &'static str> {
+    RESOURCES.iter().filter_map(|r| r.wrapper)
+}
+
+static FIELDS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// Install the `--fields` selection for server-side partial responses. Call
+/// once at startup, and only for read commands that print API resources
+/// (a read-modify-write must never fetch a trimmed resource). A selection
+/// that is not a valid field mask is only applied client-side.
+pub fn configure(fields: Option<Vec<String>>) {
+    *FIELDS.write().unwrap_or_else(|e| e.into_inner()) =
+        fields.filter(|f| !f.is_empty() && validate_field_mask(f).is_ok());
+}
+
+/// The `fields=` mask for a GET, if `--fields` is set and the endpoint is a
+/// known resource. List responses get the array wrapper and paging keys:
+/// `--fields id,name` on `/files` becomes `nextPageToken,incompleteSearch,files(id,name,mimeType)`.
+pub fn fields_param(service: &str, path: &str) -> Option<String> {
+    let fields = FIELDS.read().unwrap_or_else(|e| e.into_inner()).clone()?;
+    let path = path.split('?').next().unwrap_or(path);
+    let resource = RESOURCES.iter().find(|r| r.service == service && matches(r.path, path))?;
+
+    let mut selected: Vec<String> = Vec::new();
+    let mut add = |field: String| {
+        if !selected.contains(&field) {
+            selected.push(field);
+        }
+    };
+    for field in &fields {
+        let field = field.replace('.', "/");
+        // Paging keys are requested anyway; a wrapper prefix is redundant
+        if resource.keep.contains(&field.as_str()) {
+            continue;
+        }
+        let field = match resource.wrapper {
+            Some(wrapper) => field.strip_prefix(&format!("{}/", wrapper)).map(str::to_string).unwrap_or(field),
+            None => field,
+        };
+        add(field);
+    }
+    for required in resource.required {
+        let needed = match required.split_once('/') {
+            Some((parent, _)) => fields.iter().any(|f| f.replace('.', "/").starts_with(&format!("{}/", parent))),
+            None => true,
+        };
+        if needed {
+            add(required.to_string());
+        }
+    }
+
+    // A whole parent makes its sub-selections invalid ("attendees,attendees/email")
+    let whole: Vec<String> = selected.iter().filter(|f| !f.contains('/')).cloned().collect();
+    selected.retain(|f| match f.split_once('/') {
+        Some((parent, _)) => !whole.iter().any(|w| w == parent),
+        None => true,
+    });
+
+    Some(match resource.wrapper {
+        Some(wrapper) => {
+            let mut parts: Vec<String> = resource.keep.iter().map(|k| k.to_string()).collect();
+            parts.push(format!("{}({})", wrapper, build_fields_param(&selected)));
+            build_fields_param(&parts)
+        }
+        None => build_fields_param(&selected),
+    })
+}
+
+fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    pattern.len() == path.len()
+        && pattern.iter().zip(&path).all(|(p, s)| *p == "*" || p == s)
+}
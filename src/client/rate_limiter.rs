@@ -1,5 +1,8 @@
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, Semaphore};
 
 /// Rate limiter configuration for a specific API
@@ -138,6 +141,145 @@ impl TokenBucket {
         let tokens = self.tokens.lock().await;
         *tokens as u32
     }
+
+    /// Drain the bucket into debt covering `retry_after`, so no request goes
+    /// out before the server is ready again
+    pub async fn penalize(&self, retry_after: Duration) {
+        self.refill().await;
+        let mut tokens = self.tokens.lock().await;
+        *tokens = tokens.min(-retry_after.as_secs_f64() * self.config.refill_rate);
+    }
+}
+
+/// Where shared buckets live and which account they belong to
+struct SharedSettings {
+    dir: PathBuf,
+    account: String,
+}
+
+static SHARED: RwLock<Option<SharedSettings>> = RwLock::new(None);
+
+/// Enable cross-process buckets for every limiter created afterwards. Call
+/// once at startup; `None` keeps buckets in process memory.
+pub fn configure_shared(dir: Option<PathBuf>, account: &str) {
+    *SHARED.write().unwrap_or_else(|e| e.into_inner()) = dir.map(|dir| SharedSettings {
+        dir,
+        account: account.to_string(),
+    });
+}
+
+/// Default directory for shared bucket state
+pub fn default_shared_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("workspace-cli").join("ratelimit"))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedState {
+    tokens: f64,
+    /// Seconds since the Unix epoch
+    last_refill: f64,
+}
+
+/// Token bucket persisted in a file and guarded by an exclusive file lock, so
+/// concurrent CLI processes draw from one quota budget (keyed by service and account)
+#[derive(Clone)]
+pub struct SharedTokenBucket {
+    config: RateLimitConfig,
+    path: PathBuf,
+}
+
+impl SharedTokenBucket {
+    pub fn new(config: RateLimitConfig, path: PathBuf) -> Self {
+        Self { config, path }
+    }
+
+    /// Acquire tokens, waiting if necessary
+    pub async fn acquire(&self, cost: u32) -> Result<(), RateLimitError> {
+        let cost = cost as f64;
+
+        if cost > self.config.capacity as f64 {
+            return Err(RateLimitError::CostExceedsCapacity {
+                cost: cost as u32,
+                capacity: self.config.capacity,
+            });
+        }
+
+        let refill_rate = self.config.refill_rate;
+        loop {
+            let wait_secs = self.update(move |tokens| {
+                if *tokens >= cost {
+                    *tokens -= cost;
+                    None
+                } else {
+                    Some((cost - *tokens) / refill_rate)
+                }
+            }).await?;
+
+            match wait_secs {
+                None => return Ok(()),
+                // Never sleep while holding the lock
+                Some(secs) => tokio::time::sleep(Duration::from_secs_f64(secs.min(1.0))).await,
+            }
+        }
+    }
+
+    /// Drain the shared bucket into debt covering `retry_after`
+    pub async fn penalize(&self, retry_after: Duration) -> Result<(), RateLimitError> {
+        let debt = -retry_after.as_secs_f64() * self.config.refill_rate;
+        self.update(move |tokens| *tokens = tokens.min(debt)).await
+    }
+
+    /// Run [`Self::update_locked`] on the blocking pool: waiting for the file
+    /// lock must not stall the async runtime
+    async fn update<T, F>(&self, f: F) -> Result<T, RateLimitError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut f64) -> T + Send + 'static,
+    {
+        let bucket = self.clone();
+        tokio::task::spawn_blocking(move || bucket.update_locked(f))
+            .await
+            .map_err(|e| RateLimitError::SharedState(std::io::Error::other(e)))?
+    }
+
+    /// Refill and apply `f` to the token count under an exclusive file lock
+    fn update_locked<T>(&self, f: impl FnOnce(&mut f64) -> T) -> Result<T, RateLimitError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        file.lock()?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let mut state: SharedState = serde_json::from_str(&content).unwrap_or(SharedState {
+            tokens: self.config.initial_tokens.unwrap_or(self.config.capacity) as f64,
+            last_refill: now,
+        });
+
+        let elapsed = (now - state.last_refill).max(0.0);
+        state.tokens = (state.tokens + elapsed * self.config.refill_rate).min(self.config.capacity as f64);
+        state.last_refill = now;
+
+        let result = f(&mut state.tokens);
+
+        let content = serde_json::to_string(&state)
+            .map_err(|e| RateLimitError::SharedState(std::io::Error::other(e)))?;
+        file.set_len(0)?;
+        file.rewind()?;
+        file.write_all(content.as_bytes())?;
+        Ok(result)
+    }
 }
 
 /// Semaphore-based concurrency limiter (for Drive writes)
@@ -191,6 +333,7 @@ pub struct ConcurrencyPermit {
 /// Composite rate limiter for a specific API
 pub struct ApiRateLimiter {
     token_bucket: TokenBucket,
+    shared_bucket: Option<SharedTokenBucket>,
     concurrency: Option<ConcurrencyLimiter>,
 }
 
@@ -198,6 +341,7 @@ impl ApiRateLimiter {
     pub fn new(config: RateLimitConfig) -> Self {
         Self {
             token_bucket: TokenBucket::new(config),
+            shared_bucket: None,
             concurrency: None,
         }
     }
@@ -207,9 +351,54 @@ impl ApiRateLimiter {
         self
     }
 
+    /// Apply `[api.services.<name>]` capacity and refill overrides. Call before
+    /// [`Self::with_shared_bucket`] so the shared bucket uses the same limits.
+    pub fn with_service_limits(mut self, service: &str) -> Self {
+        let config = super::network::rate_limit(service, self.token_bucket.config.clone());
+        self.token_bucket = TokenBucket::new(config);
+        self
+    }
+
+    /// Share this limiter's budget with other processes under `service`, if
+    /// shared buckets are enabled (see [`configure_shared`])
+    pub fn with_shared_bucket(mut self, service: &str) -> Self {
+        let shared = SHARED.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(ref settings) = *shared {
+            let key: String = format!("{}-{}", service, settings.account)
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@') { c } else { '_' })
+                .collect();
+            self.shared_bucket = Some(SharedTokenBucket::new(
+                self.token_bucket.config.clone(),
+                settings.dir.join(format!("{}.json", key)),
+            ));
+        }
+        self
+    }
+
+    /// Tighten the bucket after a `Retry-After` response
+    pub async fn penalize(&self, retry_after: Duration) {
+        self.token_bucket.penalize(retry_after).await;
+        if let Some(ref shared) = self.shared_bucket {
+            if let Err(e) = shared.penalize(retry_after).await {
+                tracing::debug!(error = %e, "Failed to update shared rate limit state");
+            }
+        }
+    }
+
     /// Acquire rate limit, returning optional concurrency permit
     pub async fn acquire(&self, cost: u32) -> Result<Option<ConcurrencyPermit>, RateLimitError> {
-        self.token_bucket.acquire(cost).await?;
+        match self.shared_bucket {
+            Some(ref shared) => match shared.acquire(cost).await {
+                // Fall back to the in-process bucket if the state file is unusable
+                Err(RateLimitError::SharedState(e)) => {
+                    tracing::debug!(error = %e, "Shared rate limit unavailable, using in-process bucket");
+                    self.token_bucket.acquire(cost).await?;
+                }
+                result => result?,
+            },
+            None => self.token_bucket.acquire(cost).await?,
+        }
 
         if let Some(ref concurrency) = self.concurrency {
             Ok(Some(concurrency.acquire().await))
@@ -259,4 +448,7 @@ pub mod gmail_costs {
 pub enum RateLimitError {
     #[error("Operation cost ({cost}) exceeds bucket capacity ({capacity})")]
     CostExceedsCapacity { cost: u32, capacity: u32 },
+
+    #[error("Shared rate limit state error: {0}")]
+    SharedState(#[from] std::io::Error),
 }
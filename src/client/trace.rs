@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use reqwest::Method;
+use serde::Serialize;
+
+use super::rate_limiter::gmail_costs;
+
+/// Where `--trace` records go
+#[derive(Debug, Clone)]
+pub enum TraceSink {
+    Stderr,
+    File(PathBuf),
+}
+
+/// Per-call counters filled in while a request (and its retries) runs
+#[derive(Debug, Default)]
+pub struct CallStats {
+    attempts: AtomicU32,
+    status: AtomicU16,
+    bytes: AtomicU64,
+}
+
+impl CallStats {
+    /// Count one HTTP attempt
+    pub fn attempt(&self) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Remember the status of the latest response
+    pub fn status(&self, status: u16) {
+        self.status.store(status, Ordering::Relaxed);
+    }
+
+    /// Number of HTTP attempts so far
+    pub fn attempts(&self) -> u32 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Add response body bytes
+    pub fn bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// One structured trace line per logical API call
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceRecord<'a> {
+    trace: &'static str,
+    service: &'a str,
+    method: &'a str,
+    url: String,
+    status: u16,
+    latency_ms: u64,
+    attempts: u32,
+    rate_limit_wait_ms: u64,
+    response_bytes: u64,
+    quota_units: u32,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceSummary {
+    calls: u64,
+    http_requests: u64,
+    errors: u64,
+    quota_units: u64,
+    response_bytes: u64,
+    latency_ms: u64,
+}
+
+struct State {
+    sink: Option<TraceSink>,
+    services: BTreeMap<String, ServiceSummary>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    sink: None,
+    services: BTreeMap::new(),
+});
+
+/// Enable tracing for the process. Call once at startup.
+pub fn configure(sink: Option<TraceSink>) {
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).sink = sink;
+}
+
+/// Write a trace record for a completed call and add it to the service summary
+pub fn record(
+    service: &str,
+    method: &Method,
+    url: &str,
+    stats: &CallStats,
+    latency: Duration,
+    rate_limit_wait: Duration,
+    quota_units: u32,
+) {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(sink) = state.sink.clone() else { return };
+
+    let record = TraceRecord {
+        trace: "request",
+        service,
+        method: method.as_str(),
+        url: super::cassette::scrub_url(url),
+        status: stats.status.load(Ordering::Relaxed),
+        latency_ms: latency.as_millis() as u64,
+        attempts: stats.attempts(),
+        rate_limit_wait_ms: rate_limit_wait.as_millis() as u64,
+        response_bytes: stats.bytes.load(Ordering::Relaxed),
+        quota_units,
+    };
+
+    let summary = state.services.entry(service.to_string()).or_default();
+    summary.calls += 1;
+    summary.http_requests += u64::from(record.attempts);
+    summary.quota_units += u64::from(quota_units);
+    summary.response_bytes += record.response_bytes;
+    summary.latency_ms += record.latency_ms;
+    if !(200..400).contains(&record.status) {
+        summary.errors += 1;
+    }
+
+    if let Ok(line) = serde_json::to_string(&record) {
+        write_line(&sink, &line);
+    }
+}
+
+/// Print the per-service summary. Safe to call more than once; only the first call prints.
+pub fn finish() {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(sink) = state.sink.take() else { return };
+
+    let services = std::mem::take(&mut state.services);
+    let total_quota: u64 = services.values().map(|s| s.quota_units).sum();
+    let summary = serde_json::json!({
+        "trace": "summary",
+        "services": services,
+        "totalQuotaUnits": total_quota,
+    });
+    write_line(&sink, &summary.to_string());
+}
+
+fn write_line(sink: &TraceSink, line: &str) {
+    match sink {
+        TraceSink::Stderr => eprintln!("{}", line),
+        TraceSink::File(path) => {
+            let file = std::fs::OpenOptions::new().create(true).append(true).open(path);
+            if let Ok(mut file) = file {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+}
+
+/// Estimated quota units for one request. Gmail charges per method
+/// (see [`gmail_costs`]); other services count one unit per request.
+pub fn quota_units(service: &str, method: &Method, path: &str) -> u32 {
+    if service != "gmail" {
+        return 1;
+    }
+    let path = path.split('?').next().unwrap_or(path);
+    if path.ends_with("/send") {
+        gmail_costs::SEND
+    } else if path.ends_with("/batchModify") || path.ends_with("/batchDelete") {
+        gmail_costs::BATCH_MODIFY
+    } else {
+        match *method {
+            Method::DELETE => gmail_costs::DELETE,
+            Method::GET if path.ends_with("/messages") || path.ends_with("/threads") => gmail_costs::LIST,
+            Method::GET => gmail_costs::GET,
+            _ => gmail_costs::MODIFY,
+        }
+    }
+}
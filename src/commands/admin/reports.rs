@@ -1,9 +1,10 @@
 use crate::client::ApiClient;
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 /// Response from Admin Reports API activities.list
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivitiesResponse {
     #[serde(default)]
@@ -11,7 +12,7 @@ pub struct ActivitiesResponse {
     pub next_page_token: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityItem {
     pub id: Option<ActivityId>,
@@ -21,21 +22,21 @@ pub struct ActivityItem {
     pub events: Vec<ActivityEvent>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityId {
     pub time: Option<String>,
     pub unique_qualifier: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityActor {
     pub email: Option<String>,
     pub profile_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityEvent {
     #[serde(rename = "type")]
@@ -45,7 +46,7 @@ pub struct ActivityEvent {
     pub parameters: Vec<EventParameter>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EventParameter {
     pub name: Option<String>,
@@ -56,7 +57,7 @@ pub struct EventParameter {
 }
 
 /// Flattened view event for output
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct FlatViewEvent {
     pub time: String,
     pub actor_email: String,
@@ -46,7 +46,7 @@ pub async fn get_user(client: &ApiClient, user_key: &str) -> Result<User> {
     {
         return Err(crate::error::WorkspaceError::Config(
             format!("Invalid user key '{}': must be an email address or user ID", user_key)
-        ).into());
+        ));
     }
     client.get(&format!("/users/{}", user_key)).await
 }
@@ -0,0 +1,203 @@
+use reqwest::Method;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::auth::TokenManager;
+use crate::client::{endpoints, ApiClient};
+use crate::error::{Result, WorkspaceError};
+
+/// Services accepted by `--service`, with their default base URLs
+pub const SERVICES: &[(&str, &str)] = &[
+    ("gmail", endpoints::GMAIL),
+    ("drive", endpoints::DRIVE),
+    ("calendar", endpoints::CALENDAR),
+    ("docs", endpoints::DOCS),
+    ("sheets", endpoints::SHEETS),
+    ("slides", endpoints::SLIDES),
+    ("tasks", endpoints::TASKS),
+    ("chat", endpoints::CHAT),
+    ("contacts", endpoints::CONTACTS),
+    ("groups", endpoints::GROUPS),
+    ("admin", endpoints::ADMIN),
+    ("admin_reports", endpoints::ADMIN_REPORTS),
+];
+
+/// Array fields that hold the items of a list response, in lookup order
+const ITEM_FIELDS: &[&str] = &[
+    "items", "files", "messages", "threads", "events", "labels", "drafts",
+    "revisions", "permissions", "comments", "replies", "spaces", "memberships",
+    "connections", "people", "groups", "users", "members", "activities",
+    "calendars", "acl", "forwardingAddresses", "sendAs", "filters", "delegates",
+];
+
+/// Parameters for a raw API call
+#[derive(Debug, Clone)]
+pub struct ApiParams {
+    pub method: Method,
+    /// Path relative to the service base URL, or an absolute URL
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<serde_json::Value>,
+}
+
+impl ApiParams {
+    /// Build parameters from raw CLI arguments
+    pub fn parse(method: &str, path: &str, query: &[String], body: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            method: parse_method(method)?,
+            path: path.to_string(),
+            query: parse_query(query)?,
+            body: body.map(parse_body).transpose()?,
+        })
+    }
+}
+
+/// Parse an HTTP method name
+pub fn parse_method(method: &str) -> Result<Method> {
+    match method.to_uppercase().as_str() {
+        "GET" => Ok(Method::GET),
+        "POST" => Ok(Method::POST),
+        "PUT" => Ok(Method::PUT),
+        "PATCH" => Ok(Method::PATCH),
+        "DELETE" => Ok(Method::DELETE),
+        other => Err(WorkspaceError::Config(format!("Unsupported method: {}", other))),
+    }
+}
+
+/// Parse `--query k=v` pairs
+pub fn parse_query(pairs: &[String]) -> Result<Vec<(String, String)>> {
+    pairs.iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .ok_or_else(|| WorkspaceError::Config(format!("Invalid query '{}': expected key=value", pair)))
+        })
+        .collect()
+}
+
+/// Parse `--body`: inline JSON, `@file`, or `@-` for stdin
+pub fn parse_body(arg: &str) -> Result<serde_json::Value> {
+    let content = match arg.strip_prefix('@') {
+        Some("-") => {
+            use std::io::Read;
+            let mut buffer = String::new();
+            std::io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        }
+        Some(path) => std::fs::read_to_string(path)?,
+        None => arg.to_string(),
+    };
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Pick the service for a call: `--service` wins, otherwise infer it from an absolute URL.
+/// With `--service`, an absolute URL must point at that service's host (its
+/// default or configured endpoint), since the request carries the user's token.
+pub fn resolve_service(service: Option<&str>, path: &str) -> Result<&'static str> {
+    if let Some(name) = service {
+        let (service, default) = SERVICES.iter()
+            .find(|(s, _)| *s == name)
+            .ok_or_else(|| WorkspaceError::Config(format!(
+                "Unknown service: {}. Use one of: {}",
+                name,
+                SERVICES.iter().map(|(s, _)| *s).collect::<Vec<_>>().join(", ")
+            )))?;
+        let endpoint = endpoints::resolve(service, default);
+        if path.starts_with("http") && !same_origin(path, default) && !same_origin(path, &endpoint) {
+            return Err(WorkspaceError::Config(format!(
+                "'{}' is not on the {} API host ({}); refusing to send credentials there",
+                path, service, endpoint
+            )));
+        }
+        return Ok(service);
+    }
+
+    if path.starts_with("http") {
+        // Longest matching base wins (admin_reports before admin)
+        let service = SERVICES.iter()
+            .filter(|(name, default)| path.starts_with(default) || path.starts_with(&endpoints::resolve(name, default)))
+            .max_by_key(|(_, default)| default.len())
+            .map(|(s, _)| *s);
+        if let Some(service) = service {
+            return Ok(service);
+        }
+    }
+
+    Err(WorkspaceError::Config(format!(
+        "Cannot infer service for '{}'. Pass --service or an absolute Google API URL.",
+        path
+    )))
+}
+
+/// Whether two URLs share scheme, host and port
+fn same_origin(a: &str, b: &str) -> bool {
+    match (url::Url::parse(a), url::Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Create the client for a service, with that service's rate limits and retry policy
+pub fn service_client(service: &str, token_manager: Arc<RwLock<TokenManager>>) -> ApiClient {
+    match service {
+        "gmail" => ApiClient::gmail(token_manager),
+        "drive" => ApiClient::drive(token_manager),
+        "calendar" => ApiClient::calendar(token_manager),
+        "docs" => ApiClient::docs(token_manager),
+        "sheets" => ApiClient::sheets(token_manager),
+        "slides" => ApiClient::slides(token_manager),
+        "tasks" => ApiClient::tasks(token_manager),
+        "chat" => ApiClient::chat(token_manager),
+        "contacts" => ApiClient::contacts(token_manager),
+        "groups" => ApiClient::groups(token_manager),
+        "admin_reports" => ApiClient::admin_reports(token_manager),
+        _ => ApiClient::admin(token_manager),
+    }
+}
+
+/// Execute a single API call, optionally for a specific page
+pub async fn call(
+    client: &ApiClient,
+    params: &ApiParams,
+    page_token: Option<&str>,
+) -> Result<serde_json::Value> {
+    let mut query = params.query.clone();
+    if let Some(token) = page_token {
+        query.retain(|(k, _)| k != "pageToken");
+        query.push(("pageToken".to_string(), token.to_string()));
+    }
+
+    let path = if query.is_empty() {
+        params.path.clone()
+    } else {
+        let query_string = serde_urlencoded::to_string(&query)
+            .map_err(|e| WorkspaceError::Config(e.to_string()))?;
+        let separator = if params.path.contains('?') { "&" } else { "?" };
+        format!("{}{}{}", params.path, separator, query_string)
+    };
+
+    client.request_json(params.method.clone(), &path, params.body.as_ref()).await
+}
+
+/// Split a list response into its items and next page token.
+/// Responses without a recognizable item array are returned as a single item.
+pub fn page_items(response: serde_json::Value) -> (Vec<serde_json::Value>, Option<String>) {
+    let next_page_token = response.get("nextPageToken")
+        .and_then(|t| t.as_str())
+        .map(|t| t.to_string());
+
+    let mut object = match response {
+        serde_json::Value::Object(map) => map,
+        other => return (vec![other], next_page_token),
+    };
+
+    let key = ITEM_FIELDS.iter()
+        .find(|k| object.get(**k).is_some_and(|v| v.is_array()))
+        .map(|k| k.to_string())
+        .or_else(|| object.iter().find(|(_, v)| v.is_array()).map(|(k, _)| k.clone()));
+
+    match key.and_then(|k| object.remove(&k)) {
+        Some(serde_json::Value::Array(items)) => (items, next_page_token),
+        _ => (vec![serde_json::Value::Object(object)], next_page_token),
+    }
+}
@@ -0,0 +1,227 @@
+use serde::Serialize;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::auth::oauth;
+use crate::auth::TokenManager;
+use crate::commands::api::service_client;
+use crate::error::{CliError, ErrorCode, WorkspaceError};
+
+/// One cheap read call per service, made with the client the service's commands use
+struct Probe {
+    service: &'static str,
+    path: &'static str,
+    query: &'static [(&'static str, &'static str)],
+    /// Docs, Sheets and Slides have no list call, so they fetch a document that
+    /// does not exist: 404 means the API is enabled and the token was accepted.
+    not_found_ok: bool,
+}
+
+const fn probe(service: &'static str, path: &'static str, query: &'static [(&'static str, &'static str)], not_found_ok: bool) -> Probe {
+    Probe { service, path, query, not_found_ok }
+}
+
+const PROBES: &[Probe] = &[
+    probe("gmail", "/users/me/profile", &[], false),
+    probe("drive", "/about", &[("fields", "user")], false),
+    probe("calendar", "/users/me/calendarList", &[("maxResults", "1")], false),
+    probe("docs", "/documents/workspace-cli-doctor", &[], true),
+    probe("sheets", "/spreadsheets/workspace-cli-doctor", &[], true),
+    probe("slides", "/presentations/workspace-cli-doctor", &[], true),
+    probe("tasks", "/users/@me/lists", &[("maxResults", "1")], false),
+    probe("chat", "/spaces", &[("pageSize", "1")], false),
+    probe("contacts", "/people/me", &[("personFields", "names")], false),
+    probe("groups", "/groups", &[("parent", "customers/my_customer"), ("pageSize", "1")], false),
+    probe("admin", "/users", &[("customer", "my_customer"), ("maxResults", "1")], false),
+];
+
+/// Result of `auth doctor`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DoctorReport {
+    pub profile: String,
+    /// Impersonated user (`--as`), when delegating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Scopes reported by tokeninfo (without `--as`, one token serves every service)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granted_scopes: Option<Vec<String>>,
+    pub services: Vec<ServiceCheck>,
+    pub ok: usize,
+    pub warnings: usize,
+    pub errors: usize,
+}
+
+/// Outcome for one service
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ServiceCheck {
+    pub service: String,
+    /// `ok`, `warning` (works, but some commands need scopes that are missing) or `error`
+    pub status: &'static str,
+    pub probe: String,
+    pub required_scopes: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_scopes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CliError>,
+}
+
+impl DoctorReport {
+    /// Whether every service passed its probe
+    pub fn healthy(&self) -> bool {
+        self.errors == 0
+    }
+}
+
+/// Check every service end to end: granted scopes via tokeninfo, then one read call per API
+pub async fn doctor(token_manager: Arc<RwLock<TokenManager>>) -> DoctorReport {
+    let (profile, subject) = {
+        let tm = token_manager.read().await;
+        (tm.profile().to_string(), tm.subject().map(|s| s.to_string()))
+    };
+
+    // tokeninfo is asked once per distinct token
+    let mut scopes_by_token: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    let mut services = Vec::new();
+    let mut report_scopes = None;
+
+    for probe in PROBES {
+        let service = probe.service;
+        let required = token_manager.read().await.requested_scopes(service);
+        let query: Vec<(&str, String)> = probe.query.iter().map(|(k, v)| (*k, v.to_string())).collect();
+        let mut check = ServiceCheck {
+            service: service.to_string(),
+            status: "ok",
+            probe: match probe.query {
+                [] => format!("GET {}", probe.path),
+                pairs => format!(
+                    "GET {}?{}",
+                    probe.path,
+                    pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&")
+                ),
+            },
+            required_scopes: required.clone(),
+            missing_scopes: Vec::new(),
+            error: None,
+        };
+
+        let token = {
+            let mut tm = token_manager.write().await;
+            tm.set_service(service);
+            tm.get_access_token().await
+        };
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                check.status = "error";
+                check.error = Some(token_error(service, subject.as_deref(), &required, &e.to_string()));
+                services.push(check);
+                continue;
+            }
+        };
+
+        if !scopes_by_token.contains_key(&token) {
+            let granted = oauth::granted_scopes(&token).await.ok();
+            scopes_by_token.insert(token.clone(), granted);
+        }
+        let granted = scopes_by_token[&token].clone();
+        if let Some(ref granted) = granted {
+            check.missing_scopes = required.iter()
+                .filter(|scope| !granted.contains(scope))
+                .cloned()
+                .collect();
+        }
+        if subject.is_none() {
+            report_scopes = granted;
+        }
+
+        let client = service_client(service, token_manager.clone());
+        match client.get_with_query::<serde_json::Value, _>(probe.path, &query).await {
+            Ok(_) => {}
+            Err(WorkspaceError::Api(ref e)) if probe.not_found_ok && e.code == 404 => {}
+            Err(e) => {
+                check.status = "error";
+                check.error = Some(probe_error(service, subject.as_deref(), &check.required_scopes, &check.missing_scopes, &e));
+            }
+        }
+        if check.status == "ok" && !check.missing_scopes.is_empty() {
+            check.status = "warning";
+        }
+        services.push(check);
+    }
+
+    let count = |status: &str| services.iter().filter(|c| c.status == status).count();
+    DoctorReport {
+        profile,
+        subject,
+        granted_scopes: report_scopes,
+        ok: count("ok"),
+        warnings: count("warning"),
+        errors: count("error"),
+        services,
+    }
+}
+
+/// Explain a failure to obtain a token for a service
+fn token_error(service: &str, subject: Option<&str>, required: &[String], message: &str) -> CliError {
+    let error = CliError::new(ErrorCode::AuthenticationFailed, service, message);
+    match subject {
+        Some(subject) if message.contains("unauthorized_client") || message.contains("access_denied") => error.with_fix(format!(
+            "Domain-wide delegation is not set up for these scopes. In the Admin console (Security > Access and data control > API controls > Domain-wide delegation), authorize the service account's client ID for: {}. Also check that {} exists in the domain.",
+            required.join(","),
+            subject
+        )),
+        _ => error.with_fix("Run 'workspace-cli auth login' to re-authenticate"),
+    }
+}
+
+/// Classify a failed probe as a missing scope, disabled API or permission problem
+fn probe_error(service: &str, subject: Option<&str>, required: &[String], missing: &[String], error: &WorkspaceError) -> CliError {
+    let WorkspaceError::Api(api) = error else {
+        return CliError::from(error);
+    };
+    let message = api.message.as_str();
+    let lower = message.to_lowercase();
+
+    if lower.contains("has not been used") || lower.contains("is disabled") || lower.contains("service_disabled") {
+        let url = message.split_whitespace()
+            .find(|w| w.starts_with("https://console."))
+            .map(|u| u.trim_end_matches(['.', ',']).to_string())
+            .unwrap_or_else(|| "https://console.cloud.google.com/apis/library".to_string());
+        return CliError::new(ErrorCode::ConfigurationError, service, message)
+            .with_fix(format!("Enable the {} API for the OAuth client's Google Cloud project: {}", service, url));
+    }
+
+    if lower.contains("insufficient authentication scopes")
+        || lower.contains("access_token_scope_insufficient")
+        || (api.code == 403 && !missing.is_empty())
+    {
+        let scopes = if missing.is_empty() {
+            required.join(",")
+        } else {
+            missing.join(",")
+        };
+        let fix = match subject {
+            Some(_) => format!(
+                "Add these scopes to the service account's domain-wide delegation in the Admin console: {}",
+                scopes
+            ),
+            None => format!(
+                "The login did not grant {}. Run 'workspace-cli {} ...' in a terminal to authorize it, or 'workspace-cli auth login --services <services>,{}'",
+                scopes, service, service
+            ),
+        };
+        return CliError::new(ErrorCode::PermissionDenied, service, message).with_fix(fix);
+    }
+
+    let cli_error = CliError::from(error);
+    match api.code {
+        401 => cli_error.with_fix("Run 'workspace-cli auth login' to re-authenticate"),
+        403 => cli_error.with_fix(format!(
+            "The account is not allowed to use {} (it may need an admin role, a Workspace edition that includes it, or the service turned on for its organizational unit)",
+            service
+        )),
+        _ => cli_error,
+    }
+}
@@ -1,9 +1,10 @@
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use crate::client::batch::{BatchClient, BatchRequest, BatchError};
 
 /// Input format for a single batch request (agent-friendly JSON)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct BatchRequestInput {
     /// Unique identifier for this request (used to match responses)
     pub id: String,
@@ -38,7 +39,7 @@ impl BatchRequestInput {
 }
 
 /// Output format for batch results
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct BatchOutput {
     /// Overall status: "success" if all succeeded, "partial" if some failed
     pub status: &'static str,
@@ -49,7 +50,7 @@ pub struct BatchOutput {
 }
 
 /// A successful result from the batch
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct BatchResultOutput {
     /// Request ID (matches input id)
     pub id: String,
@@ -60,7 +61,7 @@ pub struct BatchResultOutput {
 }
 
 /// An error from the batch
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct BatchErrorOutput {
     /// Request ID (matches input id)
     pub id: String,
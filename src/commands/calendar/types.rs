@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
     pub id: Option<String>,
@@ -19,7 +20,7 @@ pub struct Event {
     pub recurrence: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EventDateTime {
     pub date: Option<String>,      // For all-day events
@@ -27,7 +28,7 @@ pub struct EventDateTime {
     pub time_zone: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Attendee {
     pub email: String,
     #[serde(default)]
@@ -35,7 +36,7 @@ pub struct Attendee {
     pub response_status: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Organizer {
     pub email: Option<String>,
@@ -44,7 +45,7 @@ pub struct Organizer {
     pub is_self: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EventList {
     #[serde(default)]
@@ -55,7 +56,7 @@ pub struct EventList {
     pub time_zone: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CalendarList {
     #[serde(default)]
@@ -63,7 +64,7 @@ pub struct CalendarList {
     pub next_page_token: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CalendarListEntry {
     pub id: String,
@@ -75,7 +76,7 @@ pub struct CalendarListEntry {
 
 /// Minimal event format optimized for AI agents (reduced token usage)
 /// Excludes: attendees, organizer, description, location, htmlLink, created, updated, recurrence
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MinimalEvent {
     pub id: Option<String>,
@@ -98,7 +99,7 @@ impl MinimalEvent {
 }
 
 /// Minimal event list response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MinimalEventList {
     #[serde(default)]
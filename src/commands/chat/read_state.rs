@@ -1,6 +1,6 @@
 use crate::client::ApiClient;
 use crate::client::batch::{BatchClient, BatchRequest};
-use crate::error::Result;
+use crate::error::{Result, WorkspaceError};
 use super::types::{SpaceReadState, ThreadReadState, SpaceNotificationSetting, UnreadResult, UnreadSpace, Space};
 use super::spaces::{list_spaces, ListSpacesParams};
 use super::messages::{list_messages, ListMessagesParams};
@@ -122,7 +122,7 @@ pub async fn get_unread_messages(client: &ApiClient, limit_per_space: u32, space
 
         // Execute batch (up to 100 requests = 50 spaces × 2)
         let batch_responses = batch_client.execute(batch_requests, &token).await
-            .map_err(|e| crate::error::WorkspaceError::Config(format!("Batch request failed: {}", e)))?;
+            .map_err(|e| WorkspaceError::Config(format!("Batch request failed: {}", e)))?;
 
         // Parse batch responses into read states and notification settings
         let mut read_states: std::collections::HashMap<String, SpaceReadState> = std::collections::HashMap::new();
@@ -195,19 +195,22 @@ pub async fn get_unread_messages(client: &ApiClient, limit_per_space: u32, space
         let msg_results = join_all(msg_futures).await;
 
         for (i, msg_result) in msg_results.into_iter().enumerate() {
-            if let Ok(response) = msg_result {
-                if !response.messages.is_empty() {
-                    let (space_name, last_read, space_meta) = &needs_messages[i];
-                    let count = response.messages.len();
-                    total_messages += count;
-                    unread_spaces.push(UnreadSpace {
-                        space_name: Some(space_name.clone()),
-                        display_name: space_meta.as_ref().and_then(|s| s.display_name.clone()),
-                        space_type: space_meta.as_ref().and_then(|s| s.space_type.clone()),
-                        last_read_time: Some(last_read.clone()),
-                        messages: response.messages,
-                    });
-                }
+            let response = match msg_result {
+                Err(e) if matches!(e, WorkspaceError::DryRun) => return Err(e),
+                Err(_) => continue,
+                Ok(response) => response,
+            };
+            if !response.messages.is_empty() {
+                let (space_name, last_read, space_meta) = &needs_messages[i];
+                let count = response.messages.len();
+                total_messages += count;
+                unread_spaces.push(UnreadSpace {
+                    space_name: Some(space_name.clone()),
+                    display_name: space_meta.as_ref().and_then(|s| s.display_name.clone()),
+                    space_type: space_meta.as_ref().and_then(|s| s.space_type.clone()),
+                    last_read_time: Some(last_read.clone()),
+                    messages: response.messages,
+                });
             }
         }
     }
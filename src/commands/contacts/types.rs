@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Person {
     pub resource_name: Option<String>,
@@ -26,7 +27,7 @@ pub struct Person {
     pub metadata: Option<PersonMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Name {
     pub given_name: Option<String>,
@@ -36,7 +37,7 @@ pub struct Name {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EmailAddress {
     pub value: Option<String>,
@@ -46,7 +47,7 @@ pub struct EmailAddress {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PhoneNumber {
     pub value: Option<String>,
@@ -56,7 +57,7 @@ pub struct PhoneNumber {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Organization {
     pub name: Option<String>,
@@ -65,7 +66,7 @@ pub struct Organization {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Url {
     pub value: Option<String>,
@@ -74,7 +75,7 @@ pub struct Url {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Birthday {
     pub date: Option<DateValue>,
@@ -82,7 +83,7 @@ pub struct Birthday {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DateValue {
     pub year: Option<i32>,
@@ -90,7 +91,7 @@ pub struct DateValue {
     pub day: Option<i32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Biography {
     pub value: Option<String>,
@@ -98,7 +99,7 @@ pub struct Biography {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Address {
     pub formatted_value: Option<String>,
@@ -113,7 +114,7 @@ pub struct Address {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UserDefined {
     pub key: Option<String>,
@@ -121,7 +122,7 @@ pub struct UserDefined {
     pub metadata: Option<FieldMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldMetadata {
     pub primary: Option<bool>,
@@ -129,7 +130,7 @@ pub struct FieldMetadata {
     pub source: Option<Source>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Source {
     #[serde(rename = "type")]
@@ -137,7 +138,7 @@ pub struct Source {
     pub id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonMetadata {
     #[serde(default)]
@@ -146,7 +147,7 @@ pub struct PersonMetadata {
 
 // Response types
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionsResponse {
     #[serde(default)]
@@ -157,20 +158,20 @@ pub struct ConnectionsResponse {
     pub next_sync_token: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResponse {
     #[serde(default)]
     pub results: Vec<SearchResult>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResult {
     pub person: Option<Person>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DirectoryPeopleResponse {
     #[serde(default)]
@@ -181,7 +182,7 @@ pub struct DirectoryPeopleResponse {
 
 // Request body for creating/updating contacts
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateContactRequest {
     #[serde(default)]
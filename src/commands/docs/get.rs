@@ -133,7 +133,7 @@ fn table_to_markdown(table: &super::types::Table) -> String {
         markdown.push('|');
         for cell in &row.table_cells {
             let cell_text = cell.content.iter()
-                .filter_map(|e| element_to_markdown(e))
+                .filter_map(element_to_markdown)
                 .collect::<Vec<_>>()
                 .join(" ")
                 .trim()
@@ -1,6 +1,7 @@
 pub mod types;
 pub mod list;
 pub mod upload;
+pub mod upload_session;
 pub mod download;
 pub mod delete;
 pub mod mkdir;
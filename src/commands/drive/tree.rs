@@ -1,12 +1,13 @@
 use crate::client::ApiClient;
-use crate::error::Result;
+use crate::error::{Result, WorkspaceError};
 use super::list::{list_files, ListParams};
 use super::types::File;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TreeNode {
     pub id: String,
@@ -33,7 +34,7 @@ pub struct TreeNode {
     pub shortcut_details: Option<TreeShortcutDetails>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TreeShortcutDetails {
     pub target_id: String,
@@ -41,7 +42,7 @@ pub struct TreeShortcutDetails {
     pub target_mime_type: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TreePermission {
     #[serde(rename = "type")]
     pub perm_type: String,
@@ -52,7 +53,7 @@ pub struct TreePermission {
     pub domain: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct TreeResult {
     pub root_id: String,
     pub total_items: usize,
@@ -135,9 +136,9 @@ async fn list_all_children(client: &ApiClient, parent_id: &str, include_permissi
 /// Validate that a folder ID contains only safe characters (alphanumeric, dashes, underscores)
 fn validate_folder_id(id: &str) -> Result<()> {
     if id.is_empty() || !id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
-        return Err(crate::error::WorkspaceError::Config(
+        return Err(WorkspaceError::Config(
             format!("Invalid folder ID '{}': must contain only alphanumeric characters, dashes, and underscores", id)
-        ).into());
+        ));
     }
     Ok(())
 }
@@ -195,6 +196,8 @@ pub async fn crawl_tree(
                     }
                     eprint!("\r  {} items found, depth {}...", all_nodes.len(), depth);
                 }
+                // A dry run stops at the first preview instead of warning per folder
+                Ok((_, _, Err(e))) if matches!(e, WorkspaceError::DryRun) => return Err(e),
                 Ok((parent_id, depth, Err(e))) => {
                     eprintln!("\nWarning: failed to list children of {} at depth {}: {}", parent_id, depth, e);
                 }
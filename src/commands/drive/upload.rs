@@ -1,22 +1,34 @@
+use std::io::SeekFrom;
 use std::path::Path;
-use reqwest::Client;
+use std::time::Duration;
+use reqwest::{Body, Method, Response};
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
-use crate::error::{WorkspaceError, ApiError};
+use crate::client::{endpoints, ApiClient, RetryConfig, RetryState, Retryable};
+use crate::error::WorkspaceError;
 use super::types::{File as DriveFile, FileMetadata};
+use super::upload_session::{fingerprint, SessionStore, UploadSession};
 
 const RESUMABLE_THRESHOLD: u64 = 5 * 1024 * 1024; // 5MB
 
+/// Resumable chunk size; must be a multiple of 256KB
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024; // 8MB
+
+/// Per-request timeout for media transfers (overrides the client's 30s default)
+pub(crate) const MEDIA_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
 pub struct UploadParams {
     pub file_path: String,
     pub name: Option<String>,
     pub parent_id: Option<String>,
     pub mime_type: Option<String>,
+    /// Continue a previously interrupted resumable upload of the same file
+    pub resume: bool,
 }
 
 pub async fn upload_file(
-    access_token: &str,
+    client: &ApiClient,
     params: UploadParams,
 ) -> Result<DriveFile, WorkspaceError> {
     let path = Path::new(&params.file_path);
@@ -28,7 +40,7 @@ pub async fn upload_file(
     });
 
     let metadata = std::fs::metadata(path)
-        .map_err(|e| WorkspaceError::Io(e))?;
+        .map_err(WorkspaceError::Io)?;
     let file_size = metadata.len();
 
     let mime_type = params.mime_type.unwrap_or_else(|| {
@@ -37,167 +49,306 @@ pub async fn upload_file(
             .to_string()
     });
 
+    let metadata = FileMetadata {
+        name: file_name,
+        mime_type: Some(mime_type.clone()),
+        parents: params.parent_id.map(|p| vec![p]),
+    };
+
     if file_size > RESUMABLE_THRESHOLD {
-        resumable_upload(access_token, path, &file_name, &mime_type, params.parent_id).await
+        resumable_upload(client, path, file_size, &mime_type, &metadata, params.resume).await
     } else {
-        simple_upload(access_token, path, &file_name, &mime_type, params.parent_id).await
+        simple_upload(client, path, file_size, &mime_type, &metadata).await
     }
 }
 
+/// Dry-run preview of an upload: metadata plus a description of the media
+fn upload_preview(path: &Path, file_size: u64, mime_type: &str, metadata: &FileMetadata) -> serde_json::Value {
+    serde_json::json!({
+        "metadata": metadata,
+        "media": {
+            "path": path.display().to_string(),
+            "size": file_size,
+            "mimeType": mime_type,
+        }
+    })
+}
+
+fn upload_url(client: &ApiClient, upload_type: &str) -> String {
+    client.url(&format!(
+        "{}/files?uploadType={}",
+        endpoints::resolve("drive_upload", endpoints::DRIVE_UPLOAD),
+        upload_type
+    ))
+}
+
 async fn simple_upload(
-    access_token: &str,
+    client: &ApiClient,
     path: &Path,
-    name: &str,
+    file_size: u64,
     mime_type: &str,
-    parent_id: Option<String>,
+    metadata: &FileMetadata,
 ) -> Result<DriveFile, WorkspaceError> {
-    let client = Client::new();
-
-    let mut file = File::open(path).await?;
-    let mut content = Vec::new();
-    file.read_to_end(&mut content).await?;
-
-    let metadata = FileMetadata {
-        name: name.to_string(),
-        mime_type: Some(mime_type.to_string()),
-        parents: parent_id.map(|p| vec![p]),
-    };
-
-    let metadata_json = serde_json::to_string(&metadata)?;
-
-    // Multipart upload
+    let metadata_json = serde_json::to_string(metadata)?;
     let boundary = "workspace_cli_boundary";
-    let mut body = Vec::new();
-
-    // Metadata part
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(b"Content-Type: application/json; charset=UTF-8\r\n\r\n");
-    body.extend_from_slice(metadata_json.as_bytes());
-    body.extend_from_slice(b"\r\n");
-
-    // Content part
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", mime_type).as_bytes());
-    body.extend_from_slice(&content);
-    body.extend_from_slice(format!("\r\n--{}--", boundary).as_bytes());
-
-    let response = client
-        .post("https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart&supportsAllDrives=true")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("Content-Type", format!("multipart/related; boundary={}", boundary))
-        .body(body)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let status = response.status().as_u16();
-        let text = response.text().await.unwrap_or_default();
-        return Err(WorkspaceError::Api(ApiError {
-            code: status,
-            message: text,
-            domain: "drive".to_string(),
-            retry_after: None,
-        }));
-    }
+    let url = upload_url(client, "multipart");
+
+    let response = client.send_media(
+        Method::POST,
+        &url,
+        upload_preview(path, file_size, mime_type, metadata),
+        |builder| async {
+            let mut content = Vec::new();
+            File::open(path).await?.read_to_end(&mut content).await?;
+
+            // Multipart upload
+            let mut body = Vec::new();
+
+            // Metadata part
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            body.extend_from_slice(b"Content-Type: application/json; charset=UTF-8\r\n\r\n");
+            body.extend_from_slice(metadata_json.as_bytes());
+            body.extend_from_slice(b"\r\n");
+
+            // Content part
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", mime_type).as_bytes());
+            body.extend_from_slice(&content);
+            body.extend_from_slice(format!("\r\n--{}--", boundary).as_bytes());
+
+            Ok(builder
+                .timeout(MEDIA_TIMEOUT)
+                .header("Content-Type", format!("multipart/related; boundary={}", boundary))
+                .body(body))
+        },
+    ).await?;
 
     response.json().await.map_err(WorkspaceError::from)
 }
 
 async fn resumable_upload(
-    access_token: &str,
+    client: &ApiClient,
     path: &Path,
-    name: &str,
+    file_size: u64,
     mime_type: &str,
-    parent_id: Option<String>,
+    metadata: &FileMetadata,
+    resume: bool,
 ) -> Result<DriveFile, WorkspaceError> {
-    let client = Client::new();
-
-    let file_size = std::fs::metadata(path)
-        .map_err(|e| WorkspaceError::Io(e))?
-        .len();
+    let store = SessionStore::open();
+    let fingerprint = fingerprint(path)?;
+
+    // Step 1: Reuse a persisted session for this exact file, or initiate a new one
+    let previous = if resume {
+        store.as_ref()
+            .and_then(|s| s.get(path))
+            .filter(|session| session.fingerprint == fingerprint && session.file_size == file_size)
+    } else {
+        None
+    };
 
-    let metadata = FileMetadata {
-        name: name.to_string(),
-        mime_type: Some(mime_type.to_string()),
-        parents: parent_id.map(|p| vec![p]),
+    let mut session = match previous {
+        Some(mut session) => match query_status(client, &session, file_size).await? {
+            SessionStatus::Incomplete(offset) => {
+                session.confirmed_offset = offset;
+                session
+            }
+            SessionStatus::Complete(file) => {
+                if let Some(ref store) = store {
+                    store.remove(path)?;
+                }
+                return Ok(*file);
+            }
+            SessionStatus::Expired => {
+                start_session(client, path, file_size, mime_type, metadata, fingerprint).await?
+            }
+        },
+        None => start_session(client, path, file_size, mime_type, metadata, fingerprint).await?,
     };
 
-    // Step 1: Initiate resumable upload
-    let init_response = client
-        .post("https://www.googleapis.com/upload/drive/v3/files?uploadType=resumable&supportsAllDrives=true")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("Content-Type", "application/json")
-        .header("X-Upload-Content-Type", mime_type)
-        .header("X-Upload-Content-Length", file_size.to_string())
-        .json(&metadata)
-        .send()
-        .await?;
-
-    if !init_response.status().is_success() {
-        let status = init_response.status().as_u16();
-        let text = init_response.text().await.unwrap_or_default();
-        return Err(WorkspaceError::Api(ApiError {
-            code: status,
-            message: text,
-            domain: "drive".to_string(),
-            retry_after: None,
-        }));
+    if let Some(ref store) = store {
+        store.put(path, &session)?;
     }
 
-    let upload_uri = init_response
+    // Step 2: Stream the file content in chunks from the confirmed offset.
+    // Chunks are not replayed blindly: after a retryable failure the server may
+    // have persisted part of the range, so ask it where to continue from.
+    // The retry budget is per chunk: it starts over whenever the server
+    // confirms more bytes, so long uploads survive more than `max_retries` blips.
+    let chunk_client = client.clone().with_retry_config(RetryConfig::new().max_retries(0));
+    let mut retry = RetryState::new(client.retry_config().clone());
+    while session.confirmed_offset < file_size {
+        let offset = session.confirmed_offset;
+        let chunk_len = CHUNK_SIZE.min(file_size - offset);
+        let content_range = format!("bytes {}-{}/{}", offset, offset + chunk_len - 1, file_size);
+
+        let result = chunk_client.send_media(
+            Method::PUT,
+            &session.session_uri,
+            serde_json::Value::Null,
+            |builder| async {
+                Ok(builder
+                    .timeout(MEDIA_TIMEOUT)
+                    .header("Content-Type", mime_type)
+                    .header("Content-Length", chunk_len.to_string())
+                    .header("Content-Range", content_range.as_str())
+                    .body(file_range_body(path, offset, chunk_len).await?))
+            },
+        ).await;
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) if e.is_retryable() => {
+                let Some(backoff) = retry.next_backoff() else { return Err(e) };
+                tokio::time::sleep(e.retry_after().unwrap_or(backoff)).await;
+                match query_status(client, &session, file_size).await? {
+                    SessionStatus::Incomplete(offset) => session.confirmed_offset = offset,
+                    SessionStatus::Complete(file) => {
+                        if let Some(ref store) = store {
+                            store.remove(path)?;
+                        }
+                        return Ok(*file);
+                    }
+                    SessionStatus::Expired => return Err(e),
+                }
+                if session.confirmed_offset > offset {
+                    retry = RetryState::new(client.retry_config().clone());
+                }
+                if let Some(ref store) = store {
+                    store.put(path, &session)?;
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        // 308 Resume Incomplete: continue from the last byte the server confirmed
+        // (no Range header means nothing has been persisted yet)
+        if response.status().as_u16() == 308 {
+            session.confirmed_offset = confirmed_offset(&response).unwrap_or(0);
+            if let Some(ref store) = store {
+                store.put(path, &session)?;
+            }
+            if session.confirmed_offset > offset {
+                retry = RetryState::new(client.retry_config().clone());
+            } else {
+                // The server kept none of the chunk; resend it within the same budget
+                let Some(backoff) = retry.next_backoff() else {
+                    return Err(WorkspaceError::Config(format!(
+                        "Upload made no progress past byte {} after {} attempts; rerun with --resume",
+                        offset,
+                        retry.attempt() + 1
+                    )));
+                };
+                tokio::time::sleep(backoff).await;
+            }
+            continue;
+        }
+
+        if let Some(ref store) = store {
+            store.remove(path)?;
+        }
+        return response.json().await.map_err(WorkspaceError::from);
+    }
+
+    // If we get here, the upload completed but didn't get a final response
+    Err(WorkspaceError::Config("Upload completed but no response received".to_string()))
+}
+
+/// Initiate a new resumable upload session
+async fn start_session(
+    client: &ApiClient,
+    path: &Path,
+    file_size: u64,
+    mime_type: &str,
+    metadata: &FileMetadata,
+    fingerprint: String,
+) -> Result<UploadSession, WorkspaceError> {
+    let url = upload_url(client, "resumable");
+    let init_response = client.send_media(
+        Method::POST,
+        &url,
+        upload_preview(path, file_size, mime_type, metadata),
+        |builder| async {
+            Ok(builder
+                .header("X-Upload-Content-Type", mime_type)
+                .header("X-Upload-Content-Length", file_size.to_string())
+                .json(metadata))
+        },
+    ).await?;
+
+    let session_uri = init_response
         .headers()
         .get("location")
         .and_then(|v| v.to_str().ok())
         .ok_or_else(|| WorkspaceError::Config("No upload URI in response".to_string()))?
         .to_string();
 
-    // Step 2: Upload the file content in chunks
-    const CHUNK_SIZE: usize = 256 * 1024; // 256KB chunks
-    let mut file = File::open(path).await?;
-    let mut buffer = vec![0u8; CHUNK_SIZE];
-    let mut uploaded = 0u64;
-
-    loop {
-        let bytes_read = file.read(&mut buffer).await?;
-        if bytes_read == 0 {
-            break;
-        }
-
-        let chunk_end = uploaded + bytes_read as u64 - 1;
-        let content_range = format!("bytes {}-{}/{}", uploaded, chunk_end, file_size);
-
-        let response = client
-            .put(&upload_uri)
-            .header("Content-Type", mime_type)
-            .header("Content-Length", bytes_read.to_string())
-            .header("Content-Range", content_range)
-            .body(buffer[..bytes_read].to_vec())
-            .send()
-            .await?;
-
-        uploaded += bytes_read as u64;
+    Ok(UploadSession {
+        session_uri,
+        fingerprint,
+        file_size,
+        mime_type: mime_type.to_string(),
+        confirmed_offset: 0,
+        created_at: chrono::Utc::now().timestamp(),
+    })
+}
 
-        // 308 Resume Incomplete means continue uploading
-        if response.status().as_u16() == 308 {
-            continue;
-        }
+enum SessionStatus {
+    /// Server has persisted bytes up to (excluding) this offset
+    Incomplete(u64),
+    /// Upload had already finished before the process was interrupted
+    Complete(Box<DriveFile>),
+    /// Session URI is no longer valid; a new session is needed
+    Expired,
+}
 
-        // Check for success (200 or 201)
-        if response.status().is_success() {
-            return response.json().await.map_err(WorkspaceError::from);
+/// Ask the server how much of a session it has received (`Content-Range: bytes */size`)
+async fn query_status(
+    client: &ApiClient,
+    session: &UploadSession,
+    file_size: u64,
+) -> Result<SessionStatus, WorkspaceError> {
+    let content_range = format!("bytes */{}", file_size);
+    let preview = serde_json::json!({ "resume": { "confirmedOffset": session.confirmed_offset } });
+    let result = client.send_media(Method::PUT, &session.session_uri, preview, |builder| async {
+        Ok(builder
+            .header("Content-Length", "0")
+            .header("Content-Range", content_range.as_str()))
+    }).await;
+
+    match result {
+        Ok(response) if response.status().as_u16() == 308 => {
+            Ok(SessionStatus::Incomplete(confirmed_offset(&response).unwrap_or(0)))
         }
-
-        // Handle error
-        let status = response.status().as_u16();
-        let text = response.text().await.unwrap_or_default();
-        return Err(WorkspaceError::Api(ApiError {
-            code: status,
-            message: text,
-            domain: "drive".to_string(),
-            retry_after: None,
-        }));
+        Ok(response) => Ok(SessionStatus::Complete(Box::new(response.json().await?))),
+        Err(WorkspaceError::Api(e)) if e.code == 404 || e.code == 410 => Ok(SessionStatus::Expired),
+        Err(e) => Err(e),
     }
+}
 
-    // If we get here, the upload completed but didn't get a final response
-    Err(WorkspaceError::Config("Upload completed but no response received".to_string()))
+/// Next byte to send, from a 308 response's `Range: bytes=0-N` header
+fn confirmed_offset(response: &Response) -> Option<u64> {
+    let range = response.headers().get("range")?.to_str().ok()?;
+    let end: u64 = range.rsplit('-').next()?.parse().ok()?;
+    Some(end + 1)
+}
+
+/// Stream `len` bytes of a file starting at `offset` without buffering the chunk
+async fn file_range_body(path: &Path, offset: u64, len: u64) -> Result<Body, WorkspaceError> {
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let reader = file.take(len);
+
+    let stream = futures::stream::unfold(
+        (reader, vec![0u8; 64 * 1024]),
+        |(mut reader, mut buf)| async move {
+            match reader.read(&mut buf).await {
+                Ok(0) => None,
+                Ok(n) => Some((Ok::<_, std::io::Error>(buf[..n].to_vec()), (reader, buf))),
+                Err(e) => Some((Err(e), (reader, buf))),
+            }
+        },
+    );
+
+    Ok(Body::wrap_stream(stream))
 }
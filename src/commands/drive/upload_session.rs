@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::WorkspaceError;
+
+/// Drive expires resumable upload sessions a week after they start
+const SESSION_LIFETIME_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// A resumable upload session persisted across process restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSession {
+    /// Session URI returned by the resumable upload initiation request
+    pub session_uri: String,
+    /// Size and modification time of the local file when the session started
+    pub fingerprint: String,
+    pub file_size: u64,
+    pub mime_type: String,
+    /// Next byte the server has not yet confirmed
+    pub confirmed_offset: u64,
+    /// Unix timestamp when the session was created (Google expires them after a week)
+    pub created_at: i64,
+}
+
+impl UploadSession {
+    /// Whether Drive has expired the session by now
+    pub fn is_expired(&self) -> bool {
+        chrono::Utc::now().timestamp() - self.created_at >= SESSION_LIFETIME_SECS
+    }
+}
+
+/// On-disk store of upload sessions, keyed by canonical local file path
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    /// Store under the config dir; `None` when no config dir is available
+    pub fn open() -> Option<Self> {
+        Config::config_dir().map(|dir| Self { path: dir.join("upload_sessions.json") })
+    }
+
+    fn load_all(&self) -> BTreeMap<String, UploadSession> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write all sessions to a temp file and rename it into place, so readers
+    /// never see a partly written store
+    fn save_all(&self, sessions: &BTreeMap<String, UploadSession>) -> Result<(), WorkspaceError> {
+        let temp = self.path.with_extension(format!("json.{}.tmp", std::process::id()));
+        let _ = std::fs::remove_file(&temp);
+
+        // Session URIs authorize uploads on their own; create the file user-only
+        let mut options = std::fs::File::options();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&temp)?.write_all(serde_json::to_string_pretty(sessions)?.as_bytes())?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+
+    /// Load, change and save the store under an exclusive file lock, so
+    /// concurrent uploads of different files do not drop each other's sessions
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<String, UploadSession>) -> bool) -> Result<(), WorkspaceError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let lock = std::fs::File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.path.with_extension("json.lock"))?;
+        lock.lock()?;
+
+        let mut sessions = self.load_all();
+        let count = sessions.len();
+        sessions.retain(|_, session| !session.is_expired());
+        let pruned = sessions.len() != count;
+        if f(&mut sessions) || pruned {
+            self.save_all(&sessions)?;
+        }
+        Ok(())
+    }
+
+    /// Get the session for a local file, unless Drive has already expired it
+    pub fn get(&self, file: &Path) -> Option<UploadSession> {
+        self.load_all().remove(&session_key(file)).filter(|session| !session.is_expired())
+    }
+
+    /// Insert or replace the session for a local file
+    pub fn put(&self, file: &Path, session: &UploadSession) -> Result<(), WorkspaceError> {
+        self.update(|sessions| {
+            sessions.insert(session_key(file), session.clone());
+            true
+        })
+    }
+
+    /// Forget the session for a local file
+    pub fn remove(&self, file: &Path) -> Result<(), WorkspaceError> {
+        self.update(|sessions| sessions.remove(&session_key(file)).is_some())
+    }
+}
+
+fn session_key(file: &Path) -> String {
+    std::fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Fingerprint a local file by size and modification time, so a resumed
+/// session is never continued with different bytes
+pub fn fingerprint(file: &Path) -> Result<String, WorkspaceError> {
+    let metadata = std::fs::metadata(file)?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    Ok(format!("{}:{}", metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_puts_keep_every_session() {
+        let dir = std::env::temp_dir().join(format!("workspace-cli-upload-sessions-{}", std::process::id()));
+        let path = dir.join("upload_sessions.json");
+        let _ = std::fs::remove_file(&path);
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let store = SessionStore { path };
+                    let session = UploadSession {
+                        session_uri: format!("https://upload.example/{}", i),
+                        fingerprint: "1:1".to_string(),
+                        file_size: 1,
+                        mime_type: "text/plain".to_string(),
+                        confirmed_offset: 0,
+                        created_at: chrono::Utc::now().timestamp(),
+                    };
+                    for _ in 0..10 {
+                        store.put(Path::new(&format!("/nonexistent/file{}", i)), &session).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(SessionStore { path }.load_all().len(), 8);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn expired_sessions_are_not_resumed() {
+        let dir = std::env::temp_dir().join(format!("workspace-cli-upload-sessions-expired-{}", std::process::id()));
+        let store = SessionStore { path: dir.join("upload_sessions.json") };
+        let now = chrono::Utc::now().timestamp();
+        let session = |created_at| UploadSession {
+            session_uri: "https://upload.example/1".to_string(),
+            fingerprint: "1:1".to_string(),
+            file_size: 1,
+            mime_type: "text/plain".to_string(),
+            confirmed_offset: 0,
+            created_at,
+        };
+
+        store.put(Path::new("/nonexistent/old"), &session(now - SESSION_LIFETIME_SECS)).unwrap();
+        store.put(Path::new("/nonexistent/new"), &session(now - SESSION_LIFETIME_SECS + 60)).unwrap();
+        assert!(store.get(Path::new("/nonexistent/old")).is_none());
+        assert!(store.get(Path::new("/nonexistent/new")).is_some());
+        // The next write prunes the stale entry from disk
+        assert_eq!(store.load_all().len(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&store.path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,265 @@
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::sync::{RwLock, Semaphore};
+
+use crate::auth::TokenManager;
+use crate::client::ApiClient;
+use crate::commands::admin_types::UsersListResponse;
+use crate::commands::groups::members::{self, ListMembersParams};
+use crate::error::{Result, WorkspaceError};
+
+/// Global flags taking a value that the parent handles for every subject
+const PARENT_VALUE_FLAGS: &[&str] = &[
+    "--as-each", "--as-each-concurrency", "--as", "--format", "-f", "--output", "-o", "--jq", "--max-tokens", "--continue",
+    "--redact",
+];
+
+/// Global switches the parent handles for every subject
+const PARENT_SWITCHES: &[&str] = &["--quiet", "-q"];
+
+/// Who `--as-each` runs a command as
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubjectSource {
+    /// File with one email per line (`#` comments and blank lines are skipped)
+    File(PathBuf),
+    /// Direct user members of a group (`group:team@example.com`)
+    Group(String),
+    /// Users in an organizational unit (`ou:/Sales`)
+    OrgUnit(String),
+}
+
+impl SubjectSource {
+    pub fn parse(spec: &str) -> Result<Self> {
+        if let Some(email) = spec.strip_prefix("group:") {
+            if email.is_empty() {
+                return Err(WorkspaceError::Config("--as-each group: needs a group email".to_string()));
+            }
+            Ok(Self::Group(email.to_string()))
+        } else if let Some(path) = spec.strip_prefix("ou:") {
+            if !path.starts_with('/') {
+                return Err(WorkspaceError::Config(format!(
+                    "Invalid organizational unit '{}': use a path such as ou:/Sales",
+                    path
+                )));
+            }
+            Ok(Self::OrgUnit(path.to_string()))
+        } else {
+            Ok(Self::File(PathBuf::from(spec)))
+        }
+    }
+
+    /// Whether listing the subjects needs Admin SDK / Cloud Identity access
+    pub fn needs_directory(&self) -> bool {
+        !matches!(self, Self::File(_))
+    }
+}
+
+/// Expand a subject source into email addresses. `directory` is a token
+/// manager for an admin allowed to read groups and users (group/ou sources only).
+pub async fn resolve_subjects(
+    source: &SubjectSource,
+    directory: Option<Arc<RwLock<TokenManager>>>,
+) -> Result<Vec<String>> {
+    let directory = || directory.clone().ok_or_else(|| WorkspaceError::Config(
+        "Listing group or organizational unit members needs an admin to impersonate. Pass --as <admin email>".to_string()
+    ));
+
+    let mut subjects = Vec::new();
+    match source {
+        SubjectSource::File(path) => {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                WorkspaceError::Config(format!("Cannot read subjects file {}: {}", path.display(), e))
+            })?;
+            subjects.extend(content.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string));
+        }
+        SubjectSource::Group(email) => {
+            let client = ApiClient::groups(directory()?);
+            let group_name = members::lookup_group(&client, email).await?.name.ok_or_else(|| {
+                WorkspaceError::NotFound(format!("Group not found: {}", email))
+            })?;
+            let mut page_token = None;
+            loop {
+                let page = members::list_members(&client, ListMembersParams {
+                    group_name: group_name.clone(),
+                    page_size: 200,
+                    page_token,
+                }).await?;
+                // Nested groups and service accounts cannot be impersonated
+                subjects.extend(page.memberships.into_iter()
+                    .filter(|m| m.r#type.as_deref().is_none_or(|t| t == "USER"))
+                    .filter_map(|m| m.preferred_member_key.and_then(|k| k.id)));
+                match page.next_page_token {
+                    Some(token) if !token.is_empty() => page_token = Some(token),
+                    _ => break,
+                }
+            }
+        }
+        SubjectSource::OrgUnit(path) => {
+            let client = ApiClient::admin(directory()?);
+            let mut page_token: Option<String> = None;
+            loop {
+                let mut query = vec![
+                    ("customer", "my_customer".to_string()),
+                    ("query", format!("orgUnitPath='{}'", path)),
+                    ("maxResults", "500".to_string()),
+                ];
+                if let Some(ref token) = page_token {
+                    query.push(("pageToken", token.clone()));
+                }
+                let page: UsersListResponse = client.get_with_query("/users", &query).await?;
+                subjects.extend(page.users.into_iter().filter_map(|u| u.primary_email));
+                match page.next_page_token {
+                    Some(token) if !token.is_empty() => page_token = Some(token),
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    subjects.retain(|s| seen.insert(s.to_lowercase()));
+    if subjects.is_empty() {
+        return Err(WorkspaceError::NotFound("--as-each matched no users".to_string()));
+    }
+    Ok(subjects)
+}
+
+/// Arguments for one subject's run: the original command line without the
+/// flags the parent handles, impersonating `subject` and emitting JSON lines.
+/// `parent_query` says the line has a top-level `--query`, which comes before
+/// the command; any later `--query` is the command's own search option.
+pub fn child_args(args: &[String], subject: &str, parent_query: bool) -> Vec<String> {
+    // The parent masks the merged output, so every subject shares one set of placeholders
+    let mut child = vec![
+        "--as".to_string(), subject.to_string(),
+        "--format".to_string(), "jsonl".to_string(),
+        "--redact".to_string(), "none".to_string(),
+    ];
+    let mut parent_query = parent_query;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if parent_query && (arg == "--query" || arg.starts_with("--query=")) {
+            parent_query = false;
+            if arg == "--query" {
+                iter.next();
+            }
+            continue;
+        }
+        if PARENT_SWITCHES.contains(&arg.as_str()) {
+            continue;
+        }
+        if PARENT_VALUE_FLAGS.contains(&arg.as_str()) {
+            iter.next();
+            continue;
+        }
+        let attached = PARENT_VALUE_FLAGS.iter().any(|flag| match flag.strip_prefix("--") {
+            Some(_) => arg.starts_with(&format!("{}=", flag)),
+            None => arg.len() > 2 && arg.starts_with(flag),
+        });
+        if !attached {
+            child.push(arg.clone());
+        }
+    }
+    child
+}
+
+/// Parse a child's JSON lines output, adding `subject` to every record.
+/// Arrays are split into their items.
+pub fn tag_records(stdout: &str, subject: &str) -> Vec<Value> {
+    let tag = |value: Value| match value {
+        Value::Object(mut map) => {
+            map.insert("subject".to_string(), json!(subject));
+            Value::Object(map)
+        }
+        other => json!({ "subject": subject, "value": other }),
+    };
+
+    let mut records = Vec::new();
+    for line in stdout.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<Value>(line) {
+            Ok(Value::Array(items)) => records.extend(items.into_iter().map(tag)),
+            Ok(value) => records.push(tag(value)),
+            Err(_) => records.push(json!({ "subject": subject, "value": line })),
+        }
+    }
+    records
+}
+
+/// Records from running the command once per subject
+pub struct FanoutResult {
+    pub records: Vec<Value>,
+    /// Subjects whose run failed (each also has an error record)
+    pub failed: usize,
+}
+
+/// Run the current command once per subject in child processes, at most
+/// `concurrency` at a time. Each child gets a token minted from `base`
+/// (which must hold service account or metadata credentials) for its subject.
+pub async fn run(
+    base: &TokenManager,
+    subjects: Vec<String>,
+    args: Vec<String>,
+    parent_query: bool,
+    concurrency: usize,
+) -> Result<FanoutResult> {
+    let exe = std::env::current_exe()
+        .map_err(|e| WorkspaceError::Config(format!("Cannot locate the workspace-cli executable: {}", e)))?;
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+
+    let runs = subjects.into_iter().map(|subject| {
+        let semaphore = semaphore.clone();
+        let exe = exe.clone();
+        let args = child_args(&args, &subject, parent_query);
+        async move {
+            let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+            let token = match base.for_subject(&subject).await {
+                Ok(tm) => tm.get_access_token().await,
+                Err(e) => Err(e),
+            };
+            let token = match token {
+                Ok(token) => token,
+                Err(e) => return Err(error_record(&subject, json!({ "message": e.to_string() }))),
+            };
+
+            let output = tokio::process::Command::new(&exe)
+                .args(&args)
+                .env("WORKSPACE_ACCESS_TOKEN", token)
+                .stdin(Stdio::null())
+                .output()
+                .await
+                .map_err(|e| error_record(&subject, json!({ "message": e.to_string() })))?;
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if output.status.success() {
+                return Ok(tag_records(&stdout, &subject));
+            }
+            // Commands report failures as JSON on stderr (or stdout)
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let text = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+            let error = serde_json::from_str::<Value>(text)
+                .unwrap_or_else(|_| json!({ "message": text }));
+            Err(error_record(&subject, error))
+        }
+    });
+
+    let mut result = FanoutResult { records: Vec::new(), failed: 0 };
+    for outcome in futures::future::join_all(runs).await {
+        match outcome {
+            Ok(records) => result.records.extend(records),
+            Err(record) => {
+                result.failed += 1;
+                result.records.push(record);
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn error_record(subject: &str, error: Value) -> Value {
+    json!({ "subject": subject, "status": "error", "error": error })
+}
@@ -1,8 +1,9 @@
 use crate::client::ApiClient;
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Label {
     pub id: String,
@@ -17,7 +18,7 @@ pub struct Label {
     pub threads_unread: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ListLabelsResponse {
     #[serde(default)]
     pub labels: Vec<Label>,
@@ -34,7 +35,7 @@ pub async fn get_label(client: &ApiClient, label_id: &str) -> Result<Label> {
     client.get(&path).await
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ModifyLabelsRequest {
     pub add_label_ids: Vec<String>,
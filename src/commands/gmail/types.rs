@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Message {
     pub id: String,
@@ -13,7 +14,7 @@ pub struct Message {
     pub internal_date: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessagePayload {
     pub headers: Vec<Header>,
@@ -23,13 +24,13 @@ pub struct MessagePayload {
     pub parts: Vec<MessagePart>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Header {
     pub name: String,
     pub value: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageBody {
     pub data: Option<String>,
@@ -37,7 +38,7 @@ pub struct MessageBody {
     pub attachment_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessagePart {
     #[serde(default)]
@@ -49,7 +50,7 @@ pub struct MessagePart {
     pub filename: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ListMessagesResponse {
     #[serde(default)]
@@ -58,7 +59,7 @@ pub struct ListMessagesResponse {
     pub result_size_estimate: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageRef {
     pub id: String,
@@ -66,7 +67,7 @@ pub struct MessageRef {
 }
 
 /// Enriched message summary with headers (used by list with metadata)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageSummary {
     pub id: String,
@@ -82,7 +83,7 @@ pub struct MessageSummary {
 }
 
 /// Enriched list response with message metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EnrichedListResponse {
     #[serde(default)]
@@ -92,7 +93,7 @@ pub struct EnrichedListResponse {
 }
 
 /// Minimal message format optimized for AI agents (reduced token usage)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MinimalMessage {
     pub id: String,
@@ -112,7 +113,7 @@ pub struct MinimalMessage {
 }
 
 /// Response for label modification operations (minimal token usage)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ModifyResponse {
     pub success: bool,
@@ -131,7 +132,7 @@ impl ModifyResponse {
 }
 
 /// Response for send/reply operations (minimal token usage)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SendResponse {
     pub success: bool,
@@ -150,7 +151,7 @@ impl SendResponse {
 }
 
 /// Response for draft operations (minimal token usage)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DraftResponse {
     pub success: bool,
@@ -160,7 +161,7 @@ pub struct DraftResponse {
 }
 
 // For sending emails
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct SendMessageRequest {
     pub raw: String,
 }
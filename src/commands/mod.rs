@@ -10,6 +10,10 @@ pub mod chat;
 pub mod contacts;
 pub mod groups;
 pub mod admin;
+pub mod api;
+pub mod auth;
+pub mod fanout;
+pub mod schema;
 
 // Re-export commonly used types
 pub use gmail::types as gmail_types;
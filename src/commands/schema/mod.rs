@@ -0,0 +1,342 @@
+use std::any::TypeId;
+
+use clap::{ArgAction, Command};
+use schemars::{schema_for, JsonSchema, Schema};
+use serde_json::{json, Map, Value};
+
+use crate::auth::{AuthStatus, ProfileStatus};
+use crate::commands::{
+    admin, admin_types, auth, batch, calendar_types, chat_types, contacts_types, docs_types, drive,
+    drive_types, gmail, gmail_types, groups_types, sheets, sheets_types, slides, slides_types,
+    tasks_types,
+};
+use crate::error::{Result, WorkspaceError};
+
+/// Builds one JSON Schema
+pub type SchemaFn = fn() -> Schema;
+
+/// What one command prints, and what it prints instead under a shape-changing flag
+pub struct Output {
+    pub command: &'static str,
+    pub schema: SchemaFn,
+    pub variants: &'static [(&'static str, SchemaFn)],
+}
+
+fn of<T: JsonSchema>() -> Schema {
+    schema_for!(T)
+}
+
+const fn output(command: &'static str, schema: SchemaFn) -> Output {
+    Output { command, schema, variants: &[] }
+}
+
+/// `{"status":"success","message":...}` printed by deletes and auth commands
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct StatusMessage {
+    status: String,
+    message: Option<String>,
+    /// `auth logout`: whether the token was revoked with Google
+    revoked: Option<bool>,
+}
+
+/// `drive download`
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct DownloadResult {
+    status: String,
+    file: String,
+    bytes: u64,
+}
+
+/// `auth revoke`, one entry per profile
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct RevokeResult {
+    profile: String,
+    /// `revoked` or `error`
+    status: String,
+    revoked: Option<bool>,
+    message: Option<String>,
+}
+
+/// `auth export`
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct ExportResult {
+    status: String,
+    storage_type: String,
+    token_cache_path: String,
+    access_token: String,
+    setup: ExportSetup,
+}
+
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct ExportSetup {
+    note: String,
+    env_command: String,
+}
+
+/// `chat mark-read --all`
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct MarkedRead {
+    status: String,
+    spaces_marked_read: u64,
+}
+
+/// `slides create`
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct PresentationCreated {
+    success: bool,
+    presentation_id: String,
+    title: Option<String>,
+    slide_count: u64,
+}
+
+/// `slides add-slide`
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct SlideAdded {
+    success: bool,
+    slide_object_id: String,
+    replies: Vec<Value>,
+}
+
+/// `slides add-shape`, `add-table` and `add-chart`
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ElementAdded {
+    success: bool,
+    object_id: String,
+    replies: Vec<Value>,
+}
+
+/// `slides delete`
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct ElementDeleted {
+    success: bool,
+    deleted: String,
+}
+
+/// Output types of every command with a fixed shape. `api` and `mcp` are
+/// passthrough or long-running and have none.
+pub const OUTPUTS: &[Output] = &[
+    output("gmail list", of::<gmail_types::EnrichedListResponse>),
+    Output { command: "gmail get", schema: of::<gmail_types::MinimalMessage>, variants: &[("--full", of::<gmail_types::Message>)] },
+    output("gmail send", of::<gmail_types::SendResponse>),
+    output("gmail draft", of::<gmail_types::DraftResponse>),
+    output("gmail delete", of::<StatusMessage>),
+    output("gmail trash", of::<gmail_types::Message>),
+    output("gmail untrash", of::<gmail_types::Message>),
+    output("gmail labels", of::<gmail::labels::ListLabelsResponse>),
+    output("gmail modify", of::<gmail_types::ModifyResponse>),
+    output("gmail reply", of::<gmail_types::SendResponse>),
+    output("gmail reply-draft", of::<gmail_types::DraftResponse>),
+    output("drive list", of::<drive_types::FileList>),
+    output("drive upload", of::<drive_types::File>),
+    output("drive download", of::<DownloadResult>),
+    output("drive get", of::<drive_types::File>),
+    output("drive delete", of::<StatusMessage>),
+    output("drive trash", of::<drive_types::File>),
+    output("drive untrash", of::<drive_types::File>),
+    output("drive mkdir", of::<drive_types::File>),
+    output("drive move", of::<drive_types::File>),
+    output("drive copy", of::<drive_types::File>),
+    output("drive rename", of::<drive_types::File>),
+    output("drive share", of::<drive::share::Permission>),
+    output("drive permissions", of::<drive::share::PermissionList>),
+    output("drive unshare", of::<StatusMessage>),
+    output("drive drives-list", of::<drive_types::SharedDriveList>),
+    output("drive tree", of::<drive::tree::TreeResult>),
+    Output { command: "calendar list", schema: of::<calendar_types::MinimalEventList>, variants: &[("--full", of::<calendar_types::EventList>)] },
+    output("calendar create", of::<calendar_types::Event>),
+    output("calendar update", of::<calendar_types::Event>),
+    output("calendar delete", of::<StatusMessage>),
+    Output {
+        command: "docs get",
+        schema: of::<docs_types::Document>,
+        variants: &[("--markdown", of::<String>), ("--text", of::<String>)],
+    },
+    output("docs append", of::<docs_types::BatchUpdateResponse>),
+    output("docs create", of::<docs_types::Document>),
+    output("docs replace", of::<docs_types::BatchUpdateResponse>),
+    output("docs batch-update", of::<Value>),
+    output("docs suggest", of::<Value>),
+    Output { command: "sheets get", schema: of::<Vec<Vec<Value>>>, variants: &[("--full", of::<sheets_types::ValueRange>)] },
+    output("sheets update", of::<sheets_types::UpdateValuesResponse>),
+    output("sheets append", of::<sheets_types::AppendValuesResponse>),
+    output("sheets create", of::<sheets_types::Spreadsheet>),
+    output("sheets clear", of::<Value>),
+    output("sheets add-sheet", of::<sheets::manage::BatchUpdateResponse>),
+    output("sheets delete-sheet", of::<sheets::manage::BatchUpdateResponse>),
+    output("sheets list-sheets", of::<std::collections::HashMap<String, i64>>),
+    Output { command: "slides get", schema: of::<String>, variants: &[("--full", of::<slides_types::Presentation>)] },
+    Output { command: "slides page", schema: of::<String>, variants: &[("--full", of::<slides_types::Page>)] },
+    output("slides create", of::<PresentationCreated>),
+    output("slides add-slide", of::<SlideAdded>),
+    output("slides add-shape", of::<ElementAdded>),
+    output("slides add-table", of::<ElementAdded>),
+    output("slides add-chart", of::<ElementAdded>),
+    output("slides delete", of::<ElementDeleted>),
+    output("slides batch-update", of::<slides::batch_types::SlidesBatchUpdateResponse>),
+    output("tasks lists", of::<tasks_types::TaskLists>),
+    Output { command: "tasks list", schema: of::<tasks_types::MinimalTasks>, variants: &[("--full", of::<tasks_types::Tasks>)] },
+    output("tasks create", of::<tasks_types::Task>),
+    output("tasks update", of::<tasks_types::Task>),
+    output("tasks delete", of::<StatusMessage>),
+    output("auth login", of::<StatusMessage>),
+    output("auth logout", of::<StatusMessage>),
+    output("auth revoke", of::<Vec<RevokeResult>>),
+    output("auth status", of::<AuthStatus>),
+    output("auth list", of::<Vec<ProfileStatus>>),
+    output("auth switch", of::<StatusMessage>),
+    output("auth doctor", of::<auth::DoctorReport>),
+    output("auth export", of::<ExportResult>),
+    output("batch gmail", of::<batch::BatchOutput>),
+    output("batch drive", of::<batch::BatchOutput>),
+    output("batch calendar", of::<batch::BatchOutput>),
+    output("chat spaces-list", of::<chat_types::SpaceListResponse>),
+    output("chat spaces-find", of::<Vec<chat_types::Space>>),
+    output("chat find-dm", of::<chat_types::Space>),
+    output("chat spaces-create", of::<chat_types::Space>),
+    output("chat messages-list", of::<chat_types::MessageListResponse>),
+    output("chat read-state", of::<chat_types::SpaceReadState>),
+    output("chat thread-read-state", of::<chat_types::ThreadReadState>),
+    output("chat unread", of::<chat_types::UnreadResult>),
+    Output { command: "chat mark-read", schema: of::<chat_types::SpaceReadState>, variants: &[("--all", of::<MarkedRead>)] },
+    output("chat send", of::<chat_types::Message>),
+    output("chat get", of::<chat_types::Message>),
+    output("contacts list", of::<contacts_types::ConnectionsResponse>),
+    output("contacts search", of::<contacts_types::SearchResponse>),
+    output("contacts get", of::<contacts_types::Person>),
+    output("contacts create", of::<contacts_types::Person>),
+    output("contacts delete", of::<StatusMessage>),
+    output("contacts directory-list", of::<contacts_types::DirectoryPeopleResponse>),
+    output("contacts directory-search", of::<contacts_types::DirectoryPeopleResponse>),
+    output("groups list", of::<groups_types::DirectoryGroupsResponse>),
+    output("groups members", of::<groups_types::MembershipsResponse>),
+    output("admin users-list", of::<admin_types::UsersListResponse>),
+    output("admin users-get", of::<admin_types::User>),
+    output("admin reports-drive-activity", of::<Vec<admin::reports::FlatViewEvent>>),
+];
+
+/// Look up the output entry for a command path such as `drive tree`
+pub fn find(path: &[String]) -> Option<&'static Output> {
+    let command = path.join(" ");
+    OUTPUTS.iter().find(|o| o.command == command)
+}
+
+/// Input and output schemas of the command at `path` under `root`
+pub fn describe(root: &Command, path: &[String]) -> Result<Value> {
+    let mut cmd = root;
+    for name in path {
+        cmd = cmd.find_subcommand(name).ok_or_else(|| {
+            WorkspaceError::NotFound(format!("Unknown command '{}'", path.join(" ")))
+        })?;
+    }
+    let entry = find(path).ok_or_else(|| {
+        WorkspaceError::NotFound(format!("No output schema for '{}'", path.join(" ")))
+    })?;
+
+    let mut out = Map::new();
+    out.insert("command".to_string(), json!(entry.command));
+    out.insert("input".to_string(), input_schema(cmd));
+    out.insert("output".to_string(), (entry.schema)().to_value());
+    if !entry.variants.is_empty() {
+        let variants: Map<String, Value> = entry.variants.iter()
+            .map(|(flag, schema)| (flag.to_string(), schema().to_value()))
+            .collect();
+        out.insert("variants".to_string(), Value::Object(variants));
+    }
+    Ok(Value::Object(out))
+}
+
+/// JSON Schema of a command's arguments. Flags are keyed by their long
+/// name, positionals by their id with their position in `x-positional`.
+pub fn input_schema(cmd: &Command) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    let mut position = 0;
+    for arg in cmd.get_arguments().filter(|a| !a.is_global_set() && a.get_id() != "help") {
+        let key = match arg.get_long() {
+            Some(long) => long.to_string(),
+            None => arg.get_id().to_string(),
+        };
+        let value_type = value_type(arg);
+        let multiple = matches!(arg.get_action(), ArgAction::Append)
+            || arg.get_num_args().is_some_and(|n| n.max_values() > 1);
+
+        let mut property = Map::new();
+        if multiple {
+            property.insert("type".to_string(), json!("array"));
+            property.insert("items".to_string(), json!({ "type": value_type }));
+        } else {
+            property.insert("type".to_string(), json!(value_type));
+        }
+        if let Some(help) = arg.get_help() {
+            property.insert("description".to_string(), json!(help.to_string()));
+        }
+        let choices: Vec<String> = arg.get_possible_values().iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        if !choices.is_empty() && value_type == "string" {
+            property.insert("enum".to_string(), json!(choices));
+        }
+        if let [default] = arg.get_default_values() {
+            let default = default.to_string_lossy();
+            let value = match value_type {
+                "boolean" => default.parse().map(Value::Bool).ok(),
+                "integer" | "number" => serde_json::from_str(&default).ok(),
+                _ => Some(json!(default)),
+            };
+            if let Some(value) = value {
+                property.insert("default".to_string(), value);
+            }
+        }
+        if arg.is_positional() {
+            property.insert("x-positional".to_string(), json!(position));
+            position += 1;
+        }
+        if arg.is_required_set() {
+            required.push(key.clone());
+        }
+        properties.insert(key, Value::Object(property));
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn value_type(arg: &clap::Arg) -> &'static str {
+    if matches!(arg.get_action(), ArgAction::SetTrue | ArgAction::SetFalse) {
+        return "boolean";
+    }
+    if matches!(arg.get_action(), ArgAction::Count) {
+        return "integer";
+    }
+    let id = arg.get_value_parser().type_id();
+    let integers = [
+        TypeId::of::<u8>(), TypeId::of::<u16>(), TypeId::of::<u32>(), TypeId::of::<u64>(), TypeId::of::<usize>(),
+        TypeId::of::<i8>(), TypeId::of::<i16>(), TypeId::of::<i32>(), TypeId::of::<i64>(), TypeId::of::<isize>(),
+    ];
+    if integers.iter().any(|t| id == *t) {
+        "integer"
+    } else if id == TypeId::of::<f64>() || id == TypeId::of::<f32>() {
+        "number"
+    } else if id == TypeId::of::<bool>() {
+        "boolean"
+    } else {
+        "string"
+    }
+}
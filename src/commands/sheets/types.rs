@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Spreadsheet {
     pub spreadsheet_id: String,
@@ -9,7 +10,7 @@ pub struct Spreadsheet {
     pub sheets: Vec<Sheet>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SpreadsheetProperties {
     pub title: String,
     pub locale: Option<String>,
@@ -17,12 +18,12 @@ pub struct SpreadsheetProperties {
     pub time_zone: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Sheet {
     pub properties: SheetProperties,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SheetProperties {
     pub sheet_id: i64,
@@ -30,7 +31,7 @@ pub struct SheetProperties {
     pub index: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ValueRange {
     pub range: String,
@@ -39,7 +40,7 @@ pub struct ValueRange {
     pub values: Vec<Vec<serde_json::Value>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateValuesResponse {
     pub spreadsheet_id: String,
@@ -49,7 +50,7 @@ pub struct UpdateValuesResponse {
     pub updated_cells: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AppendValuesResponse {
     pub spreadsheet_id: String,
@@ -57,7 +58,7 @@ pub struct AppendValuesResponse {
     pub updates: Option<UpdateValuesResponse>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchGetValuesResponse {
     pub spreadsheet_id: String,
@@ -9,9 +9,10 @@ pub struct UpdateParams {
     pub value_input_option: ValueInputOption,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ValueInputOption {
     Raw,
+    #[default]
     UserEntered,
 }
 
@@ -24,12 +25,6 @@ impl ValueInputOption {
     }
 }
 
-impl Default for ValueInputOption {
-    fn default() -> Self {
-        Self::UserEntered
-    }
-}
-
 pub async fn update_values(
     client: &ApiClient,
     params: UpdateParams,
@@ -1,12 +1,13 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use schemars::JsonSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SlidesBatchUpdateRequest {
     pub requests: Vec<Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SlidesBatchUpdateResponse {
     pub presentation_id: Option<String>,
@@ -217,6 +218,7 @@ pub fn update_table_cell_fill_request(
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_sheets_chart_request(
     object_id: &str,
     slide_id: &str,
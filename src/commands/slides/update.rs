@@ -3,6 +3,7 @@ use crate::error::Result;
 use super::batch_types::*;
 
 /// Add a shape to a slide, optionally with text, fill color, and text styling
+#[allow(clippy::too_many_arguments)]
 pub async fn add_shape(
     client: &ApiClient,
     presentation_id: &str,
@@ -33,7 +34,7 @@ pub async fn add_shape(
 
     if let Some(color) = fill {
         let (r, g, b) = parse_hex_color(color)
-            .map_err(|e| crate::error::WorkspaceError::Config(e))?;
+            .map_err(crate::error::WorkspaceError::Config)?;
         requests.push(update_shape_fill_request(object_id, r, g, b));
 
         // Auto-set text to white if fill is dark
@@ -48,6 +49,7 @@ pub async fn add_shape(
 }
 
 /// Add a table to a slide, optionally populated with data and header color
+#[allow(clippy::too_many_arguments)]
 pub async fn add_table(
     client: &ApiClient,
     presentation_id: &str,
@@ -78,7 +80,7 @@ pub async fn add_table(
     // Apply header row color
     if let Some(color) = header_color {
         let (r, g, b) = parse_hex_color(color)
-            .map_err(|e| crate::error::WorkspaceError::Config(e))?;
+            .map_err(crate::error::WorkspaceError::Config)?;
         requests.push(update_table_cell_fill_request(object_id, 0, 0, cols, r, g, b));
     }
 
@@ -88,6 +90,7 @@ pub async fn add_table(
 }
 
 /// Embed a Google Sheets chart on a slide
+#[allow(clippy::too_many_arguments)]
 pub async fn add_chart(
     client: &ApiClient,
     presentation_id: &str,
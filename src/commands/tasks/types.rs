@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskList {
     pub kind: Option<String>,
@@ -12,7 +13,7 @@ pub struct TaskList {
     pub self_link: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskLists {
     #[serde(default)]
@@ -20,7 +21,7 @@ pub struct TaskLists {
     pub next_page_token: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Task {
     pub kind: Option<String>,
@@ -42,14 +43,14 @@ pub struct Task {
     pub deleted: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TaskLink {
     pub r#type: String,
     pub description: Option<String>,
     pub link: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Tasks {
     #[serde(default)]
@@ -59,7 +60,7 @@ pub struct Tasks {
 
 /// Minimal task format optimized for AI agents (reduced token usage)
 /// Excludes: kind, etag, selfLink, links, parent, position, hidden, deleted, updated
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MinimalTask {
     pub id: Option<String>,
@@ -84,7 +85,7 @@ impl MinimalTask {
 }
 
 /// Minimal task list response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MinimalTasks {
     #[serde(default)]
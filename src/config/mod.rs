@@ -1,3 +1,6 @@
 mod settings;
 
-pub use settings::{ApiConfig, AuthConfig, Config, OutputConfig};
+pub use settings::{
+    is_valid_profile_name, ApiConfig, AuthConfig, CacheConfig, Config, OutputConfig, ProfileConfig, ServiceConfig,
+    DEFAULT_PROFILE,
+};
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub auth: AuthConfig,
@@ -9,9 +10,43 @@ pub struct Config {
     pub output: OutputConfig,
     #[serde(default)]
     pub api: ApiConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Profile used when neither `--profile` nor `WORKSPACE_PROFILE` is given (set by `auth switch`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// Named accounts, each with its own credentials, token storage and output defaults
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Name of the implicit profile that uses the top-level `[auth]` and `[output]` settings
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profile names become file and keyring entry names, so keep them simple
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Settings for one named profile; unset fields fall back to `[auth]` / `[output]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_account_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonate_subject: Option<String>,
+    /// Default output format for this profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AuthConfig {
     /// Path to OAuth2 client credentials JSON
     #[serde(default)]
@@ -22,16 +57,38 @@ pub struct AuthConfig {
     /// Email to impersonate via domain-wide delegation (service account only)
     #[serde(default)]
     pub impersonate_subject: Option<String>,
+    /// Where tokens come from instead of a user login: `service_account`
+    /// (key or external_account file) or `metadata` (GCE/GKE metadata server)
+    #[serde(default)]
+    pub credential_source: Option<String>,
+    /// Pre-issued access token (WORKSPACE_ACCESS_TOKEN), bypasses the OAuth flow
+    #[serde(skip)]
+    pub access_token: Option<String>,
+    /// Encrypt token files at rest (prompts for a passphrase when no key is configured)
+    #[serde(default)]
+    pub token_encryption: bool,
+    /// File whose contents key token encryption
+    #[serde(default)]
+    pub token_key_file: Option<PathBuf>,
+    /// Passphrase for token encryption (WORKSPACE_TOKEN_PASSPHRASE), never read from config
+    #[serde(skip)]
+    pub token_passphrase: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputConfig {
-    /// Default output format: toon, json, jsonl, csv
+    /// Default output format: toon, json, jsonl, csv, tsv, md, yaml
     #[serde(default = "default_format")]
     pub format: String,
     /// Whether to use compact JSON (no pretty printing)
     #[serde(default)]
     pub compact: bool,
+    /// Separator joining array values in CSV/TSV cells (default "; ")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub array_separator: Option<String>,
+    /// Personal data masked in all output unless `--redact` says otherwise: emails, phones, urls
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,9 +96,66 @@ pub struct ApiConfig {
     /// Request timeout in seconds
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
+    /// Maximum retries on failure; unset keeps each service's built-in policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// HTTP(S) proxy for all API traffic, e.g. `http://proxy.corp:3128`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM bundle of extra root certificates (e.g. a TLS-inspecting corporate proxy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// User-Agent header sent with every request (defaults to `workspace-cli/<version>`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Per-service overrides, keyed by service (`gmail`, `sheets`, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, ServiceConfig>,
+    /// Replace scheme and host of every Google endpoint (e.g. a local fake or proxy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Per-endpoint base URL overrides, keyed by service (`gmail`, `drive_upload`, `gmail_batch`, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub endpoints: BTreeMap<String, String>,
+    /// Share rate-limit buckets across concurrent CLI processes (file-locked, per service and account)
+    #[serde(default)]
+    pub shared_rate_limit: bool,
+}
+
+/// Network settings for one service; unset fields fall back to `[api]` and built-in defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    /// Request timeout in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
     /// Maximum retries on failure
-    #[serde(default = "default_retries")]
-    pub max_retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Backoff before the first retry, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_backoff_ms: Option<u64>,
+    /// Upper bound for a single backoff, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
+    /// Rate-limit bucket size (quota units or requests)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_capacity: Option<u32>,
+    /// Rate-limit refill, in units per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_refill: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Cache GET responses on disk and revalidate them with ETags
+    #[serde(default = "default_cache_enabled")]
+    pub enabled: bool,
+    /// Maximum cache size in megabytes; least-recently-used entries are evicted
+    #[serde(default = "default_cache_max_mb")]
+    pub max_size_mb: u64,
+    /// Cache directory (defaults to the user cache dir)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
 }
 
 fn default_format() -> String {
@@ -52,44 +166,47 @@ fn default_timeout() -> u64 {
     30
 }
 
-fn default_retries() -> u32 {
-    3
+fn default_cache_enabled() -> bool {
+    true
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            auth: AuthConfig::default(),
-            output: OutputConfig::default(),
-            api: ApiConfig::default(),
-        }
-    }
+fn default_cache_max_mb() -> u64 {
+    50
 }
 
-impl Default for AuthConfig {
+impl Default for OutputConfig {
     fn default() -> Self {
         Self {
-            credentials_path: None,
-            service_account_path: None,
-            impersonate_subject: None,
+            format: default_format(),
+            compact: false,
+            array_separator: None,
+            redact: Vec::new(),
         }
     }
 }
 
-impl Default for OutputConfig {
+impl Default for ApiConfig {
     fn default() -> Self {
         Self {
-            format: default_format(),
-            compact: false,
+            timeout_seconds: default_timeout(),
+            max_retries: None,
+            proxy: None,
+            ca_bundle: None,
+            user_agent: None,
+            services: BTreeMap::new(),
+            base_url: None,
+            endpoints: BTreeMap::new(),
+            shared_rate_limit: false,
         }
     }
 }
 
-impl Default for ApiConfig {
+impl Default for CacheConfig {
     fn default() -> Self {
         Self {
-            timeout_seconds: default_timeout(),
-            max_retries: default_retries(),
+            enabled: default_cache_enabled(),
+            max_size_mb: default_cache_max_mb(),
+            dir: None,
         }
     }
 }
@@ -125,6 +242,37 @@ impl Config {
         Ok(())
     }
 
+    /// Profile name in effect after [`Config::with_profile`]
+    pub fn profile_name(&self) -> &str {
+        self.active_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Select a profile (`None` keeps `active_profile`) and apply its settings
+    /// over `[auth]` and `[output]`
+    pub fn with_profile(mut self, profile: Option<&str>) -> Self {
+        if let Some(name) = profile {
+            self.active_profile = Some(name.to_string());
+        }
+        let Some(profile) = self.profiles.get(self.profile_name()).cloned() else { return self };
+
+        if profile.credentials_path.is_some() {
+            self.auth.credentials_path = profile.credentials_path;
+        }
+        if profile.service_account_path.is_some() {
+            self.auth.service_account_path = profile.service_account_path;
+        }
+        if profile.impersonate_subject.is_some() {
+            self.auth.impersonate_subject = profile.impersonate_subject;
+        }
+        if let Some(format) = profile.format {
+            self.output.format = format;
+        }
+        if let Some(compact) = profile.compact {
+            self.output.compact = compact;
+        }
+        self
+    }
+
     /// Override with environment variables
     pub fn with_env_overrides(mut self) -> Self {
         if let Ok(path) = std::env::var("WORKSPACE_CREDENTIALS_PATH") {
@@ -136,6 +284,24 @@ impl Config {
         if let Ok(email) = std::env::var("WORKSPACE_IMPERSONATE") {
             self.auth.impersonate_subject = Some(email);
         }
+        if let Ok(source) = std::env::var("WORKSPACE_CREDENTIAL_SOURCE") {
+            if !source.is_empty() {
+                self.auth.credential_source = Some(source);
+            }
+        }
+        if let Ok(token) = std::env::var("WORKSPACE_ACCESS_TOKEN") {
+            if !token.is_empty() {
+                self.auth.access_token = Some(token);
+            }
+        }
+        if let Ok(passphrase) = std::env::var("WORKSPACE_TOKEN_PASSPHRASE") {
+            if !passphrase.is_empty() {
+                self.auth.token_passphrase = Some(passphrase);
+            }
+        }
+        if let Ok(path) = std::env::var("WORKSPACE_TOKEN_KEY_FILE") {
+            self.auth.token_key_file = Some(PathBuf::from(path));
+        }
         if let Ok(format) = std::env::var("WORKSPACE_OUTPUT_FORMAT") {
             self.output.format = format;
         }
@@ -149,7 +315,33 @@ impl Config {
         }
         if let Ok(retries) = std::env::var("WORKSPACE_API_MAX_RETRIES") {
             if let Ok(max) = retries.parse::<u32>() {
-                self.api.max_retries = max;
+                self.api.max_retries = Some(max);
+            }
+        }
+        if let Ok(proxy) = std::env::var("WORKSPACE_PROXY") {
+            self.api.proxy = Some(proxy);
+        }
+        if let Ok(path) = std::env::var("WORKSPACE_CA_BUNDLE") {
+            self.api.ca_bundle = Some(PathBuf::from(path));
+        }
+        if let Ok(agent) = std::env::var("WORKSPACE_USER_AGENT") {
+            self.api.user_agent = Some(agent);
+        }
+        if let Ok(max) = std::env::var("WORKSPACE_CACHE_MAX_MB") {
+            if let Ok(mb) = max.parse::<u64>() {
+                self.cache.max_size_mb = mb;
+            }
+        }
+        if let Ok(shared) = std::env::var("WORKSPACE_SHARED_RATE_LIMIT") {
+            self.api.shared_rate_limit = shared.eq_ignore_ascii_case("true") || shared == "1";
+        }
+        if let Ok(url) = std::env::var("WORKSPACE_API_BASE_URL") {
+            self.api.base_url = Some(url);
+        }
+        // WORKSPACE_API_ENDPOINT_GMAIL_BATCH=... -> [api.endpoints] gmail_batch = ...
+        for (key, value) in std::env::vars() {
+            if let Some(service) = key.strip_prefix("WORKSPACE_API_ENDPOINT_") {
+                self.api.endpoints.insert(service.to_lowercase(), value);
             }
         }
         self
@@ -1,8 +1,9 @@
 use serde::Serialize;
+use schemars::JsonSchema;
 use thiserror::Error;
 
 /// Structured error response for agent consumption
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CliError {
     pub status: &'static str,  // Always "error"
     pub error_code: ErrorCode,
@@ -14,7 +15,7 @@ pub struct CliError {
     pub actionable_fix: Option<String>,
 }
 
-#[derive(Debug, Serialize, Clone, Copy)]
+#[derive(Debug, Serialize, Clone, Copy, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorCode {
     AuthenticationFailed,
@@ -52,6 +53,11 @@ pub enum WorkspaceError {
 
     #[error("Resource not found: {0}")]
     NotFound(String),
+
+    /// `--dry-run` printed the request instead of sending it. Not a failure:
+    /// callers stop and the process exits successfully.
+    #[error("Dry run: request not sent")]
+    DryRun,
 }
 
 #[derive(Debug, Error)]
@@ -136,6 +142,9 @@ impl From<&WorkspaceError> for CliError {
             WorkspaceError::NotFound(msg) => {
                 CliError::new(ErrorCode::NotFound, "resource", msg.clone())
             }
+            WorkspaceError::DryRun => {
+                CliError::new(ErrorCode::Unknown, "dry_run", err.to_string())
+            }
         }
     }
 }
@@ -1,7 +1,8 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use workspace_cli::Config;
+use workspace_cli::error::WorkspaceError;
 use workspace_cli::auth::TokenManager;
 use workspace_cli::client::ApiClient;
 use workspace_cli::output::{Formatter, OutputFormat};
@@ -22,14 +23,43 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Output format: toon, json, jsonl, csv
-    #[arg(long, short = 'f', global = true, default_value = "toon")]
-    format: String,
+    /// Output format: toon, json, jsonl, csv, tsv, md, yaml (default: the profile's format, else toon)
+    #[arg(long, short = 'f', global = true)]
+    format: Option<String>,
 
     /// Fields to include in response (comma-separated)
     #[arg(long, global = true)]
     fields: Option<String>,
 
+    /// Filter and reshape output with a jq expression, e.g. '.files[] | select(.size > 1000000) | {id, name}'.
+    /// Goes before the command, since commands such as `gmail list` have their own --query search
+    #[arg(long, value_name = "EXPR", conflicts_with = "jq")]
+    query: Option<String>,
+
+    /// Same as --query, and accepted after the command too
+    #[arg(long, global = true, value_name = "EXPR")]
+    jq: Option<String>,
+
+    /// Separator joining array values into one CSV/TSV cell (default "; ")
+    #[arg(long, global = true, value_name = "SEP")]
+    array_separator: Option<String>,
+
+    /// Estimated token budget for the output: list items are dropped from the tail, then long strings cut
+    #[arg(long, global = true, value_name = "N")]
+    max_tokens: Option<usize>,
+
+    /// Cut a string field to N characters, e.g. body=2000 (repeatable)
+    #[arg(long, global = true, value_name = "FIELD=N")]
+    truncate: Vec<String>,
+
+    /// Resume a list cut short by --max-tokens, from the continuation token it printed
+    #[arg(long = "continue", global = true, value_name = "TOKEN")]
+    continue_from: Option<String>,
+
+    /// Mask personal data in output: emails, phones, urls (comma-separated; none disables the config default)
+    #[arg(long, global = true, value_name = "KINDS")]
+    redact: Option<String>,
+
     /// Write output to file instead of stdout
     #[arg(long, short = 'o', global = true)]
     output: Option<String>,
@@ -38,10 +68,22 @@ struct Cli {
     #[arg(long, short = 'q', global = true)]
     quiet: bool,
 
+    /// Account profile to use (see `auth list`)
+    #[arg(long, global = true, value_name = "NAME", env = "WORKSPACE_PROFILE")]
+    profile: Option<String>,
+
     /// Impersonate user via domain-wide delegation (requires service account)
     #[arg(long = "as", global = true, value_name = "EMAIL")]
     impersonate: Option<String>,
 
+    /// Run the command once per user: a file of emails, group:EMAIL or ou:/PATH (requires a service account)
+    #[arg(long, global = true, value_name = "SUBJECTS")]
+    as_each: Option<String>,
+
+    /// Users processed in parallel with --as-each (default: 4)
+    #[arg(long, global = true, default_value = "4")]
+    as_each_concurrency: usize,
+
     /// Fetch all pages automatically (streams items one by one)
     #[arg(long, global = true)]
     page_all: bool,
@@ -57,6 +99,22 @@ struct Cli {
     /// Preview API request without executing it (prints request details and exits)
     #[arg(long, global = true)]
     dry_run: bool,
+
+    /// Record every HTTP request/response to cassette files in DIR (auth is scrubbed)
+    #[arg(long, global = true, value_name = "DIR", env = "WORKSPACE_RECORD", conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Serve HTTP responses from cassette files in DIR instead of the network (no credentials needed)
+    #[arg(long, global = true, value_name = "DIR", env = "WORKSPACE_REPLAY")]
+    replay: Option<String>,
+
+    /// Bypass the on-disk ETag response cache
+    #[arg(long, global = true, env = "WORKSPACE_NO_CACHE")]
+    no_cache: bool,
+
+    /// Log every HTTP call as JSON to stderr, or to FILE with --trace=FILE, plus a per-service quota summary at exit
+    #[arg(long, global = true, value_name = "FILE", num_args = 0..=1, require_equals = true, default_missing_value = "-", env = "WORKSPACE_TRACE")]
+    trace: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -185,10 +243,17 @@ enum Commands {
         workspace-cli auth login\n\n\
         Login with custom credentials file:\n  \
         workspace-cli auth login --credentials /path/to/credentials.json\n\n\
+        Login on a headless machine (enter the printed code on any device):\n  \
+        workspace-cli auth login --device --services calendar,tasks\n\n\
         Check authentication status:\n  \
         workspace-cli auth status\n\n\
         Logout and clear stored tokens:\n  \
         workspace-cli auth logout\n\n\
+        Login a second account as a named profile, then make it the default:\n  \
+        workspace-cli auth login --profile work --credentials work-credentials.json\n  \
+        workspace-cli auth switch work\n\n\
+        List profiles:\n  \
+        workspace-cli auth list\n\n\
         Note: First-time login requires OAuth2 credentials from Google Cloud Console.")]
     Auth {
         #[command(subcommand)]
@@ -213,6 +278,40 @@ enum Commands {
         #[command(subcommand)]
         command: BatchCommands,
     },
+    /// Make an authenticated request to any Google Workspace API endpoint
+    #[command(long_about = "Make an authenticated request to any Google Workspace API endpoint.
+
+        Reuses auth, retries, rate limiting, --page-all pagination, --fields and all output formats.
+        Paths are relative to the --service base URL; absolute URLs infer the service.
+
+        Examples:
+        Gmail settings:
+          workspace-cli api GET /users/me/settings/sendAs --service gmail
+
+        Drive revisions (all pages):
+          workspace-cli api GET /files/<file-id>/revisions --service drive --page-all
+
+        Calendar ACL with query parameters:
+          workspace-cli api GET /calendars/primary/acl --service calendar --query maxResults=50
+
+        Request body from a file:
+          workspace-cli api POST https://gmail.googleapis.com/gmail/v1/users/me/settings/filters --body @filter.json")]
+    Api {
+        /// HTTP method: GET, POST, PUT, PATCH, DELETE
+        method: String,
+        /// Path relative to the service base URL, or an absolute URL
+        path: String,
+        /// Service whose base URL, rate limits and scopes to use (inferred from absolute URLs)
+        #[arg(long)]
+        service: Option<String>,
+        /// JSON request body: inline JSON, @file, or @- for stdin
+        #[arg(long)]
+        body: Option<String>,
+        /// Query parameter as key=value (repeatable).
+        /// A --query before `api` is the jq output filter instead
+        #[arg(long = "query", value_name = "KEY=VALUE")]
+        query: Vec<String>,
+    },
     /// Google Chat operations
     #[command(long_about = "Google Chat operations for messaging and space management.\n\n\
         Examples:\n\
@@ -265,6 +364,20 @@ enum Commands {
         #[command(subcommand)]
         command: AdminCommands,
     },
+    /// Print the JSON Schema of a command's output and arguments
+    #[command(long_about = "Print the JSON Schema of the output a command emits, plus the schema of its arguments.\n\n\
+        Flags that change the output shape (--full, --markdown, ...) are listed under \"variants\".\n\n\
+        Examples:\n\
+        List commands with a schema:\n  \
+        workspace-cli schema\n\n\
+        Schema of drive tree:\n  \
+        workspace-cli schema drive tree\n\n\
+        Just the output schema:\n  \
+        workspace-cli --query .output schema gmail get")]
+    Schema {
+        /// Command path, e.g. `drive tree` (omit to list every command)
+        command: Vec<String>,
+    },
     /// Start MCP (Model Context Protocol) server over stdio
     #[cfg(feature = "mcp")]
     Mcp,
@@ -274,7 +387,8 @@ enum Commands {
 enum GmailCommands {
     /// List messages
     List {
-        /// Search query (Gmail search syntax)
+        /// Search query (Gmail search syntax).
+        /// A --query before `gmail` is the jq output filter instead
         #[arg(long)]
         query: Option<String>,
         /// Maximum number of results
@@ -393,7 +507,8 @@ enum GmailCommands {
 enum DriveCommands {
     /// List files
     List {
-        /// Search query (Drive query syntax)
+        /// Search query (Drive query syntax).
+        /// A --query before `drive` is the jq output filter instead
         #[arg(long)]
         query: Option<String>,
         /// Maximum results
@@ -422,6 +537,9 @@ enum DriveCommands {
         /// Custom name for uploaded file
         #[arg(long)]
         name: Option<String>,
+        /// Resume an interrupted upload of the same file from the last confirmed byte
+        #[arg(long)]
+        resume: bool,
     },
     /// Download a file
     Download {
@@ -958,11 +1076,38 @@ enum AuthCommands {
         /// Path to OAuth2 client credentials JSON
         #[arg(long)]
         credentials: Option<String>,
+        /// Use the device authorization flow: print a URL and code to enter on another device.
+        /// Needs --services, since Google refuses Gmail and full Drive scopes on this flow.
+        #[arg(long, requires = "services")]
+        device: bool,
+        /// Only request scopes for these services (comma-separated, e.g. gmail,drive).
+        /// Other services ask for access the first time they are used.
+        #[arg(long, value_delimiter = ',')]
+        services: Vec<String>,
+    },
+    /// Revoke the grant at Google, then clear stored tokens
+    Logout {
+        /// Only delete local tokens, leaving the grant active at Google
+        #[arg(long)]
+        local_only: bool,
+    },
+    /// Revoke grants at Google and delete local tokens
+    Revoke {
+        /// Revoke every profile on this machine (offboarding)
+        #[arg(long)]
+        all_profiles: bool,
     },
-    /// Logout and clear stored tokens
-    Logout,
     /// Show current authentication status
     Status,
+    /// List account profiles and which one is active
+    List,
+    /// Make a profile the default for future commands
+    Switch {
+        /// Profile name
+        name: String,
+    },
+    /// Check scopes, enabled APIs and delegation for every service with one cheap read call each
+    Doctor,
     /// Export stored credentials for headless/CI use
     Export {
         /// Show full unmasked access token (default: masked)
@@ -1135,7 +1280,8 @@ enum ContactsCommands {
     },
     /// Search contacts by query
     Search {
-        /// Search query (name, email, phone)
+        /// Search query (name, email, phone).
+        /// A --query before `contacts` is the jq output filter instead
         #[arg(long)]
         query: String,
         /// Maximum results
@@ -1181,7 +1327,8 @@ enum ContactsCommands {
     },
     /// Search workspace directory
     DirectorySearch {
-        /// Search query
+        /// Search query.
+        /// A --query before `contacts` is the jq output filter instead
         #[arg(long)]
         query: String,
         /// Maximum results
@@ -1221,7 +1368,8 @@ enum AdminCommands {
         /// Domain to list users for
         #[arg(long)]
         domain: Option<String>,
-        /// Search query (Admin SDK query syntax)
+        /// Search query (Admin SDK query syntax).
+        /// A --query before `admin` is the jq output filter instead
         #[arg(long)]
         query: Option<String>,
         /// Maximum results
@@ -1261,11 +1409,105 @@ async fn main() {
         .init();
 
     let cli = Cli::parse();
+    // clap checks `conflicts_with` before global args given after the
+    // subcommand are propagated up, so `--query X gmail list --jq Y` gets past it
+    if cli.query.is_some() && cli.jq.is_some() {
+        Cli::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, "the argument '--query <EXPR>' cannot be used with '--jq <EXPR>'")
+            .exit();
+    }
 
     if let Err(e) = run(cli).await {
+        if is_dry_run(&e) {
+            exit(0);
+        }
         eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-        std::process::exit(1);
+        exit(1);
+    }
+    workspace_cli::client::trace::finish();
+}
+
+/// Exit the process, flushing the `--trace` summary first
+fn exit(code: i32) -> ! {
+    workspace_cli::client::trace::finish();
+    std::process::exit(code)
+}
+
+/// Whether an error is the `--dry-run` stop after a printed request preview,
+/// either a `WorkspaceError` or one boxed by `?`, anywhere in its source chain
+fn is_dry_run(e: &dyn std::any::Any) -> bool {
+    let error: Option<&(dyn std::error::Error + 'static)> = if let Some(e) = e.downcast_ref::<WorkspaceError>() {
+        Some(e)
+    } else if let Some(e) = e.downcast_ref::<Box<dyn std::error::Error>>() {
+        Some(e.as_ref())
+    } else if let Some(e) = e.downcast_ref::<Box<dyn std::error::Error + Send + Sync>>() {
+        Some(e.as_ref())
+    } else {
+        None
+    };
+    std::iter::successors(error, |e| e.source())
+        .any(|e| matches!(e.downcast_ref::<WorkspaceError>(), Some(WorkspaceError::DryRun)))
+}
+
+/// Report a failed command and exit with status 1. A dry run ends here as
+/// well, successfully, once its request preview has been printed.
+fn fail<E: std::fmt::Display + 'static>(e: E) -> ! {
+    if is_dry_run(&e) {
+        exit(0);
+    }
+    eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
+    exit(1)
+}
+
+/// Fan the command out over the `--as-each` subjects. Returns the exit code.
+async fn run_as_each(
+    spec: &str,
+    cli: &Cli,
+    config: &Config,
+    token_manager: &Arc<RwLock<TokenManager>>,
+    format: OutputFormat,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    use workspace_cli::commands::fanout;
+
+    if matches!(cli.command, Commands::Auth { .. }) {
+        return Err("--as-each cannot be used with auth commands".into());
+    }
+    let source = fanout::SubjectSource::parse(spec)?;
+
+    // One service account (or metadata) login, reused to mint every subject's token
+    let mut base = token_manager.write().await;
+    base.set_subject(None);
+    let login = match config.auth.credential_source.as_deref() {
+        Some("metadata") => base.login_metadata().await,
+        _ => base.login_service_account(None).await,
+    };
+    if let Err(e) = login {
+        return Err(format!("--as-each requires a service account with domain-wide delegation: {}", e).into());
+    }
+
+    let directory = match cli.impersonate.clone().or_else(|| config.auth.impersonate_subject.clone()) {
+        Some(ref admin) if source.needs_directory() => {
+            let mut tm = base.for_subject(admin).await?;
+            tm.set_service(match source {
+                fanout::SubjectSource::Group(_) => "groups",
+                _ => "admin",
+            });
+            Some(Arc::new(RwLock::new(tm)))
+        }
+        _ => None,
+    };
+    let subjects = fanout::resolve_subjects(&source, directory).await?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = fanout::run(&base, subjects, args, cli.query.is_some(), cli.as_each_concurrency).await?;
+
+    let mut formatter = Formatter::new(format).with_quiet(cli.quiet);
+    if let Some(ref output_path) = cli.output {
+        formatter = formatter.with_writer(std::fs::File::create(output_path)?);
     }
+    formatter.write(&result.records)?;
+    formatter.flush()?;
+    Ok(if result.failed > 0 { 1 } else { 0 })
 }
 
 /// Validate and normalize a Chat space type filter value
@@ -1279,7 +1521,78 @@ fn validate_space_type(t: &str) -> Result<String, Box<dyn std::error::Error>> {
 
 async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
     // Load config and create shared token manager
-    let config = Config::load().with_env_overrides();
+    if let Some(ref name) = cli.profile {
+        if !workspace_cli::config::is_valid_profile_name(name) {
+            return Err(format!("Invalid profile name '{}': use letters, digits, '-' and '_'", name).into());
+        }
+    }
+    let mut config = Config::load().with_profile(cli.profile.as_deref()).with_env_overrides();
+    workspace_cli::client::endpoints::configure(&config.api);
+    workspace_cli::client::network::configure(&config.api)?;
+    workspace_cli::auth::encryption::configure(&config.auth);
+    workspace_cli::output::query::configure(
+        cli.query.as_deref().or(cli.jq.as_deref()).map(workspace_cli::output::query::Query::parse).transpose()?,
+    );
+    workspace_cli::output::flatten::configure(
+        cli.array_separator.clone().or_else(|| config.output.array_separator.clone()),
+    );
+    let budget = workspace_cli::output::budget::Budget {
+        max_tokens: cli.max_tokens,
+        truncate: cli.truncate.iter()
+            .map(|spec| workspace_cli::output::budget::parse_truncate(spec))
+            .collect::<Result<_, _>>()?,
+        offset: cli.continue_from.as_deref()
+            .map(workspace_cli::output::budget::parse_continuation)
+            .transpose()?
+            .unwrap_or(0),
+    };
+    workspace_cli::output::budget::configure(Some(budget).filter(|b| !b.is_empty()));
+    let redact = cli.redact.clone()
+        .or_else(|| (!config.output.redact.is_empty()).then(|| config.output.redact.join(",")));
+    workspace_cli::output::redact::configure(
+        redact.as_deref().map(workspace_cli::output::redact::Redactor::parse).transpose()?.flatten(),
+    );
+
+    if let Some(ref trace) = cli.trace {
+        workspace_cli::client::trace::configure(Some(match trace.as_str() {
+            "-" => workspace_cli::client::trace::TraceSink::Stderr,
+            path => workspace_cli::client::trace::TraceSink::File(path.into()),
+        }));
+    }
+
+    let subject = cli.impersonate.clone().or_else(|| config.auth.impersonate_subject.clone());
+
+    // ETag cache for GET responses (--no-cache disables it for this run).
+    // Cassettes must capture real responses, so it is also off while recording/replaying.
+    if config.cache.enabled && !cli.no_cache && cli.record.is_none() && cli.replay.is_none() {
+        let dir = config.cache.dir.clone().or_else(workspace_cli::client::cache::ResponseCache::default_dir);
+        if let Some(dir) = dir {
+            let account = format!("{}:{}", config.profile_name(), subject.as_deref().unwrap_or_default());
+            workspace_cli::client::cache::configure(Some(workspace_cli::client::cache::ResponseCache::new(
+                dir,
+                config.cache.max_size_mb * 1024 * 1024,
+                &account,
+            )));
+        }
+    }
+
+    // Cross-process rate limiting: one bucket per service and account shared by all invocations
+    if config.api.shared_rate_limit {
+        let account = subject.clone().unwrap_or_else(|| config.profile_name().to_string());
+        workspace_cli::client::rate_limiter::configure_shared(
+            workspace_cli::client::rate_limiter::default_shared_dir(),
+            &account,
+        );
+    }
+
+    // --record / --replay: route all HTTP through cassette files
+    if let Some(ref dir) = cli.replay {
+        workspace_cli::client::cassette::configure(workspace_cli::client::cassette::Mode::Replay(dir.into()));
+        // Replayed requests are never sent, so skip the OAuth flow entirely
+        config.auth.access_token.get_or_insert_with(|| "replay".to_string());
+    } else if let Some(ref dir) = cli.record {
+        workspace_cli::client::cassette::configure(workspace_cli::client::cassette::Mode::Record(dir.into()));
+    }
     let mut tm = TokenManager::new(config.clone());
 
     // --as flag: set impersonation subject (CLI overrides config/env)
@@ -1305,38 +1618,98 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             BatchCommands::Drive { .. } => "drive",
             BatchCommands::Calendar { .. } => "calendar",
         },
+        Commands::Api { ref service, ref path, .. } => {
+            match workspace_cli::commands::api::resolve_service(service.as_deref(), path) {
+                Ok(name) => name,
+                Err(e) => {
+                    fail(e);
+                }
+            }
+        }
         Commands::Auth { .. } => "gmail", // auth doesn't make API calls, scope is irrelevant
+        Commands::Schema { .. } => "gmail", // schema is offline, scope is irrelevant
         #[cfg(feature = "mcp")]
         Commands::Mcp => "gmail",
     };
     tm.set_service(service_name);
+    // auth and mcp span every service, so they don't demand one service's scopes
+    #[cfg(feature = "mcp")]
+    let spans_services = matches!(cli.command, Commands::Auth { .. } | Commands::Schema { .. } | Commands::Mcp);
+    #[cfg(not(feature = "mcp"))]
+    let spans_services = matches!(cli.command, Commands::Auth { .. } | Commands::Schema { .. });
+    tm.set_scope_check(!spans_services);
 
     let token_manager = Arc::new(RwLock::new(tm));
 
     // Determine output format
-    let format = OutputFormat::from_str(&cli.format).unwrap_or(OutputFormat::Json);
+    let format = match cli.format {
+        Some(ref name) => OutputFormat::from_str(name).unwrap_or(OutputFormat::Json),
+        None => match OutputFormat::from_str(&config.output.format).unwrap_or(OutputFormat::Json) {
+            OutputFormat::Json if config.output.compact => OutputFormat::JsonCompact,
+            format => format,
+        },
+    };
 
     // Parse fields for filtering
-    let fields: Option<Vec<String>> = cli.fields.as_ref().map(|f| {
-        f.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
-    });
+    let fields: Option<Vec<String>> = cli.fields.as_deref().map(workspace_cli::utils::parse_field_mask);
     let quiet = cli.quiet;
 
+    // Read commands that print API resources also send --fields as a server-side
+    // fields= mask; anything that writes back what it fetched must see it whole
+    let prints_resources = matches!(
+        cli.command,
+        Commands::Gmail { command: GmailCommands::Labels }
+            | Commands::Drive { command: DriveCommands::List { .. } | DriveCommands::Get { .. } | DriveCommands::Permissions { .. } | DriveCommands::DrivesList { .. } }
+            | Commands::Calendar { command: CalendarCommands::List { .. } }
+            | Commands::Docs { command: DocsCommands::Get { markdown: false, text: false, .. } }
+            | Commands::Sheets { command: SheetsCommands::Get { full: true, .. } }
+            | Commands::Slides { command: SlidesCommands::Get { full: true, .. } }
+            | Commands::Tasks { command: TasksCommands::Lists | TasksCommands::List { .. } }
+            | Commands::Chat { command: ChatCommands::SpacesList { .. } | ChatCommands::MessagesList { .. } | ChatCommands::Get { .. } }
+            | Commands::Contacts { command: ContactsCommands::List { .. } | ContactsCommands::Get { .. } | ContactsCommands::DirectoryList { .. } }
+            | Commands::Groups { command: GroupsCommands::List { .. } | GroupsCommands::Members { .. } }
+            | Commands::Admin { command: AdminCommands::UsersList { .. } | AdminCommands::UsersGet { .. } }
+    );
+    workspace_cli::client::partial::configure(fields.clone().filter(|_| prints_resources));
+
     let page_cfg = PageConfig {
         page_all: cli.page_all,
         page_limit: cli.page_limit,
         page_delay: cli.page_delay,
     };
 
+    // --as-each: run this command once per user and merge the tagged records
+    if let Some(ref spec) = cli.as_each {
+        let code = run_as_each(spec, &cli, &config, &token_manager, format).await?;
+        exit(code);
+    }
+
     // Route commands
     match cli.command {
+        Commands::Schema { command } => {
+            let mut formatter = Formatter::new(format).with_quiet(quiet);
+            if command.is_empty() {
+                let commands: Vec<&str> = workspace_cli::commands::schema::OUTPUTS.iter().map(|o| o.command).collect();
+                formatter.write(&commands)?;
+            } else {
+                match workspace_cli::commands::schema::describe(&Cli::command(), &command) {
+                    Ok(schema) => formatter.write(&schema)?,
+                    Err(e) => {
+                        let error = workspace_cli::CliError::from(&e)
+                            .with_fix("Run 'workspace-cli schema' to list command paths");
+                        eprintln!("{}", error.to_json());
+                        exit(1);
+                    }
+                }
+            }
+        }
         Commands::Gmail { command } => {
             // Ensure we're authenticated before making API calls
             {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -1356,7 +1729,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         let tm = token_manager.read().await;
                         tm.get_access_token().await.map_err(|e| {
                             eprintln!(r#"{{"status":"error","message":"Failed to get token: {}"}}"#, e);
-                            std::process::exit(1);
+                            exit(1);
                         }).unwrap()
                     };
 
@@ -1384,8 +1757,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     params.page_token = next_token;
                                 }
                                 Err(e) => {
-                                    eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                    std::process::exit(1);
+                                    fail(e);
                                 }
                             }
                         }
@@ -1402,8 +1774,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                std::process::exit(1);
+                                fail(e);
                             }
                         }
                     }
@@ -1422,8 +1793,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                std::process::exit(1);
+                                fail(e);
                             }
                         }
                     } else {
@@ -1439,8 +1809,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                std::process::exit(1);
+                                fail(e);
                             }
                         }
                     }
@@ -1476,8 +1845,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1513,8 +1881,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1526,8 +1893,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1543,8 +1909,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1560,8 +1925,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1577,8 +1941,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1621,8 +1984,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1630,9 +1992,10 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                     // Fetch original message to get headers
                     let original = match workspace_cli::commands::gmail::get::get_message(&client, &id, "metadata").await {
                         Ok(msg) => msg,
+                        Err(e) if is_dry_run(&e) => exit(0),
                         Err(e) => {
                             eprintln!(r#"{{"status":"error","message":"Failed to fetch original message: {}"}}"#, e);
-                            std::process::exit(1);
+                            exit(1);
                         }
                     };
 
@@ -1641,7 +2004,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         Some(m) => m,
                         None => {
                             eprintln!(r#"{{"status":"error","message":"Could not extract reply metadata from message (missing Message-ID or From header)"}}"#);
-                            std::process::exit(1);
+                            exit(1);
                         }
                     };
 
@@ -1651,7 +2014,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             Ok(content) => content,
                             Err(e) => {
                                 eprintln!(r#"{{"status":"error","message":"Failed to read body file: {}"}}"#, e);
-                                std::process::exit(1);
+                                exit(1);
                             }
                         }
                     } else {
@@ -1683,8 +2046,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1692,9 +2054,10 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                     // Fetch original message to get headers
                     let original = match workspace_cli::commands::gmail::get::get_message(&client, &id, "metadata").await {
                         Ok(msg) => msg,
+                        Err(e) if is_dry_run(&e) => exit(0),
                         Err(e) => {
                             eprintln!(r#"{{"status":"error","message":"Failed to fetch original message: {}"}}"#, e);
-                            std::process::exit(1);
+                            exit(1);
                         }
                     };
 
@@ -1703,7 +2066,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         Some(m) => m,
                         None => {
                             eprintln!(r#"{{"status":"error","message":"Could not extract reply metadata from message (missing Message-ID or From header)"}}"#);
-                            std::process::exit(1);
+                            exit(1);
                         }
                     };
 
@@ -1737,8 +2100,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1750,7 +2112,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -1801,8 +2163,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     params.page_token = next_token;
                                 }
                                 Err(e) => {
-                                    eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                    std::process::exit(1);
+                                    fail(e);
                                 }
                             }
                         }
@@ -1819,30 +2180,21 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                std::process::exit(1);
+                                fail(e);
                             }
                         }
                     }
                 }
-                DriveCommands::Upload { file, parent, name } => {
-                    // Get access token for direct upload
-                    let token = {
-                        let tm = token_manager.read().await;
-                        tm.get_access_token().await.map_err(|e| {
-                            eprintln!(r#"{{"status":"error","message":"Failed to get token: {}"}}"#, e);
-                            std::process::exit(1);
-                        }).unwrap()
-                    };
-
+                DriveCommands::Upload { file, parent, name, resume } => {
                     let params = workspace_cli::commands::drive::upload::UploadParams {
                         file_path: file,
                         name,
                         parent_id: parent,
                         mime_type: None,
+                        resume,
                     };
 
-                    match workspace_cli::commands::drive::upload::upload_file(&token, params).await {
+                    match workspace_cli::commands::drive::upload::upload_file(&client, params).await {
                         Ok(response) => {
                             if let Some(ref output_path) = cli.output {
                                 let file = std::fs::File::create(output_path)?;
@@ -1853,34 +2205,23 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
                 DriveCommands::Download { id, output } => {
-                    // Get access token for direct download
-                    let token = {
-                        let tm = token_manager.read().await;
-                        tm.get_access_token().await.map_err(|e| {
-                            eprintln!(r#"{{"status":"error","message":"Failed to get token: {}"}}"#, e);
-                            std::process::exit(1);
-                        }).unwrap()
-                    };
-
                     let output_path = output
                         .map(std::path::PathBuf::from)
                         .unwrap_or_else(|| std::path::PathBuf::from(&id));
 
-                    match workspace_cli::commands::drive::download::download_file(&token, &id, &output_path).await {
+                    match workspace_cli::commands::drive::download::download_file(&client, &id, &output_path).await {
                         Ok(bytes) => {
                             if !quiet {
                                 println!(r#"{{"status":"success","file":"{}","bytes":{}}}"#, output_path.display(), bytes);
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1896,8 +2237,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1909,8 +2249,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1926,8 +2265,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1943,8 +2281,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1960,8 +2297,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1977,8 +2313,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -1994,8 +2329,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2011,8 +2345,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2023,7 +2356,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         workspace_cli::commands::drive::share::share_with_user(&client, &id, &email, &role).await
                     } else {
                         eprintln!(r#"{{"status":"error","message":"Must specify --email or --anyone"}}"#);
-                        std::process::exit(1);
+                        exit(1);
                     };
 
                     match result {
@@ -2037,8 +2370,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2054,8 +2386,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2067,8 +2398,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2084,8 +2414,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2101,8 +2430,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2114,7 +2442,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -2165,8 +2493,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     params.page_token = next_token;
                                 }
                                 Err(e) => {
-                                    eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                    std::process::exit(1);
+                                    fail(e);
                                 }
                             }
                         }
@@ -2196,8 +2523,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                std::process::exit(1);
+                                fail(e);
                             }
                         }
                     }
@@ -2225,8 +2551,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2253,8 +2578,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2266,8 +2590,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2279,7 +2602,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -2292,10 +2615,14 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         Ok(doc) => {
                             if text {
                                 // Plain text output (most token-efficient)
-                                let txt = workspace_cli::commands::docs::get::document_to_text(&doc);
+                                let txt = workspace_cli::output::budget::truncate_text(
+                                    workspace_cli::output::redact::text(workspace_cli::commands::docs::get::document_to_text(&doc)),
+                                );
                                 println!("{}", txt);
                             } else if markdown {
-                                let md = workspace_cli::commands::docs::get::document_to_markdown(&doc);
+                                let md = workspace_cli::output::budget::truncate_text(
+                                    workspace_cli::output::redact::text(workspace_cli::commands::docs::get::document_to_markdown(&doc)),
+                                );
                                 println!("{}", md);
                             } else if let Some(ref output_path) = cli.output {
                                 let file = std::fs::File::create(output_path)?;
@@ -2306,8 +2633,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2323,8 +2649,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2340,8 +2665,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2357,8 +2681,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2369,7 +2692,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         std::fs::read_to_string(&f).map_err(|e| format!("Failed to read file: {}", e))?
                     } else {
                         eprintln!(r#"{{"status":"error","message":"Provide --payload or --file"}}"#);
-                        std::process::exit(1);
+                        exit(1);
                     };
                     let body: serde_json::Value = serde_json::from_str(&json_str)
                         .map_err(|e| format!("Invalid JSON: {}", e))?;
@@ -2385,8 +2708,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2412,8 +2734,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2425,7 +2746,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -2464,8 +2785,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2489,8 +2809,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2514,8 +2833,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2536,8 +2854,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2553,8 +2870,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2570,8 +2886,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2587,8 +2902,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2603,8 +2917,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             formatter.write(&tab_ids)?;
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2616,7 +2929,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -2638,7 +2951,9 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             } else {
                                 // Default: text extraction (minimal, token-efficient)
-                                let text = workspace_cli::commands::slides::get::extract_all_text(&presentation);
+                                let text = workspace_cli::output::budget::truncate_text(
+                                    workspace_cli::output::redact::text(workspace_cli::commands::slides::get::extract_all_text(&presentation)),
+                                );
                                 if let Some(ref output_path) = cli.output {
                                     std::fs::write(output_path, &text)?;
                                 } else {
@@ -2647,8 +2962,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2659,7 +2973,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             let page_index = page as usize;
                             if page_index >= presentation.slides.len() {
                                 eprintln!(r#"{{"status":"error","message":"Page {} not found. Presentation has {} slides."}}"#, page, presentation.slides.len());
-                                std::process::exit(1);
+                                exit(1);
                             }
 
                             let slide = &presentation.slides[page_index];
@@ -2674,7 +2988,9 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             } else {
                                 // Default: text extraction (minimal, token-efficient)
-                                let text = workspace_cli::commands::slides::get::extract_page_text(slide);
+                                let text = workspace_cli::output::budget::truncate_text(
+                                    workspace_cli::output::redact::text(workspace_cli::commands::slides::get::extract_page_text(slide)),
+                                );
                                 if let Some(ref output_path) = cli.output {
                                     std::fs::write(output_path, &text)?;
                                 } else {
@@ -2683,8 +2999,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2706,8 +3021,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2729,8 +3043,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2756,20 +3069,18 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
                 SlidesCommands::AddTable { id, slide, rows, cols, data, header_color, object_id } => {
                     let oid = object_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-                    let parsed_data: Option<Vec<Vec<String>>> = match data {
-                        Some(ref json_str) => Some(serde_json::from_str(json_str).map_err(|e| {
+                    let parsed_data: Option<Vec<Vec<String>>> = data.as_ref().map(|json_str| {
+                        serde_json::from_str(json_str).map_err(|e| {
                             eprintln!(r#"{{"status":"error","message":"Invalid --data JSON: {}"}}"#, e);
-                            std::process::exit(1);
-                        }).unwrap()),
-                        None => None,
-                    };
+                            exit(1);
+                        }).unwrap()
+                    });
                     match workspace_cli::commands::slides::update::add_table(
                         &client, &id, &oid, &slide, rows, cols,
                         parsed_data.as_ref(), header_color.as_deref(),
@@ -2789,8 +3100,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2815,8 +3125,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2836,8 +3145,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2848,11 +3156,11 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         std::fs::read_to_string(path)?
                     } else {
                         eprintln!(r#"{{"status":"error","message":"Provide --requests or --file"}}"#);
-                        std::process::exit(1);
+                        exit(1);
                     };
                     let parsed: Vec<serde_json::Value> = serde_json::from_str(&json_str).map_err(|e| {
                         eprintln!(r#"{{"status":"error","message":"Invalid JSON: {}"}}"#, e);
-                        std::process::exit(1);
+                        exit(1);
                     }).unwrap();
                     match workspace_cli::commands::slides::update::batch_update(&client, &id, parsed).await {
                         Ok(response) => {
@@ -2865,8 +3173,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2878,7 +3185,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -2898,8 +3205,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -2943,8 +3249,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     params.page_token = next_token;
                                 }
                                 Err(e) => {
-                                    eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                    std::process::exit(1);
+                                    fail(e);
                                 }
                             }
                         }
@@ -2974,8 +3279,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                                std::process::exit(1);
+                                fail(e);
                             }
                         }
                     }
@@ -2999,8 +3303,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -3025,8 +3328,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
@@ -3038,26 +3340,102 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
             }
         }
+        Commands::Api { method, path, service, body, query } => {
+            {
+                let mut tm = token_manager.write().await;
+                if let Err(e) = tm.ensure_authenticated().await {
+                    eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
+                    exit(1);
+                }
+            }
+
+            let params = match workspace_cli::commands::api::ApiParams::parse(&method, &path, &query, body.as_deref()) {
+                Ok(params) => params,
+                Err(e) => {
+                    fail(e);
+                }
+            };
+
+            // Service was validated when selecting scopes above
+            let service = workspace_cli::commands::api::resolve_service(service.as_deref(), &path)?;
+            let client = workspace_cli::commands::api::service_client(service, token_manager.clone()).with_dry_run(cli.dry_run);
+            let mut formatter = if let Some(ref output_path) = cli.output {
+                let file = std::fs::File::create(output_path)?;
+                Formatter::new(format).with_fields(fields.clone()).with_quiet(quiet).with_writer(file)
+            } else {
+                Formatter::new(format).with_fields(fields.clone()).with_quiet(quiet)
+            };
+
+            if page_cfg.is_enabled() {
+                formatter.start_stream()?;
+                let mut page_token: Option<String> = None;
+                let mut page_num = 0u32;
+                loop {
+                    match workspace_cli::commands::api::call(&client, &params, page_token.as_deref()).await {
+                        Ok(response) => {
+                            let (items, next_token) = workspace_cli::commands::api::page_items(response);
+                            for item in &items {
+                                formatter.stream_item(item)?;
+                            }
+                            page_num += 1;
+                            if next_token.is_none() || !page_cfg.should_continue(page_num) {
+                                break;
+                            }
+                            page_cfg.delay().await;
+                            page_token = next_token;
+                        }
+                        Err(e) => {
+                            fail(e);
+                        }
+                    }
+                }
+                formatter.end_stream()?;
+            } else {
+                match workspace_cli::commands::api::call(&client, &params, None).await {
+                    Ok(response) => formatter.write(&response)?,
+                    Err(e) => {
+                        fail(e);
+                    }
+                }
+            }
+        }
         Commands::Auth { command } => {
             match command {
-                AuthCommands::Login { credentials } => {
+                AuthCommands::Login { credentials, device, services } => {
                     let creds_path = credentials.map(std::path::PathBuf::from);
                     let mut tm = token_manager.write().await;
-                    match tm.login_interactive(creds_path.clone()).await {
+                    if !services.is_empty() {
+                        if let Err(e) = tm.set_login_services(&services) {
+                            fail(e);
+                        }
+                    }
+                    let result = if device {
+                        tm.login_device(creds_path.clone()).await
+                    } else {
+                        tm.login_interactive(creds_path.clone()).await
+                    };
+                    match result {
                         Ok(()) => {
-                            // Save credentials path to config for future use
-                            if let Some(path) = creds_path {
+                            // Save credentials path to config for future use (per profile)
+                            let profile = tm.profile().to_string();
+                            if creds_path.is_some() || profile != workspace_cli::config::DEFAULT_PROFILE {
                                 // Canonicalize to absolute path
-                                let abs_path = std::fs::canonicalize(&path).unwrap_or(path);
+                                let abs_path = creds_path.map(|path| std::fs::canonicalize(&path).unwrap_or(path));
                                 let mut config = workspace_cli::config::Config::load();
-                                config.auth.credentials_path = Some(abs_path);
+                                if profile == workspace_cli::config::DEFAULT_PROFILE {
+                                    config.auth.credentials_path = abs_path;
+                                } else {
+                                    let entry = config.profiles.entry(profile).or_default();
+                                    if abs_path.is_some() {
+                                        entry.credentials_path = abs_path;
+                                    }
+                                }
                                 if let Err(e) = config.save() {
                                     eprintln!(r#"{{"status":"warning","message":"Login succeeded but failed to save config: {}"}}"#, e);
                                 }
@@ -3067,24 +3445,77 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            fail(e);
                         }
                     }
                 }
-                AuthCommands::Logout => {
+                AuthCommands::Logout { local_only } => {
                     let mut tm = token_manager.write().await;
-                    match tm.logout() {
-                        Ok(()) => {
+                    match tm.logout(local_only).await {
+                        Ok(revoked) => {
                             if !quiet {
-                                println!(r#"{{"status":"success","message":"Logged out"}}"#);
+                                println!(r#"{{"status":"success","message":"Logged out","revoked":{}}}"#, revoked);
                             }
                         }
                         Err(e) => {
-                            eprintln!(r#"{{"status":"error","message":"{}"}}"#, e);
-                            std::process::exit(1);
+                            let message = serde_json::json!({
+                                "status": "error",
+                                "message": format!("{}. Local tokens were kept; retry, or pass --local-only to delete them anyway.", e),
+                            });
+                            eprintln!("{}", message);
+                            exit(1);
+                        }
+                    }
+                }
+                AuthCommands::Revoke { all_profiles } => {
+                    let profiles: Vec<String> = if all_profiles {
+                        workspace_cli::auth::list_profiles(&config)
+                            .into_iter()
+                            .filter(|p| p.authenticated)
+                            .map(|p| p.name)
+                            .collect()
+                    } else {
+                        vec![config.profile_name().to_string()]
+                    };
+
+                    let mut failed = false;
+                    let mut results = Vec::new();
+                    for profile in profiles {
+                        match workspace_cli::auth::revoke_profile(&profile).await {
+                            Ok(revoked) => results.push(serde_json::json!({
+                                "profile": profile,
+                                "status": "revoked",
+                                "revoked": revoked,
+                            })),
+                            Err(e) => {
+                                failed = true;
+                                results.push(serde_json::json!({
+                                    "profile": profile,
+                                    "status": "error",
+                                    "message": e.to_string(),
+                                }));
+                            }
                         }
                     }
+                    let mut formatter = Formatter::new(format).with_quiet(quiet);
+                    formatter.write(&results)?;
+                    if failed {
+                        exit(1);
+                    }
+                }
+                AuthCommands::Doctor => {
+                    if let Err(e) = token_manager.write().await.ensure_authenticated().await {
+                        let error = workspace_cli::CliError::new(workspace_cli::ErrorCode::AuthenticationFailed, "auth", e.to_string())
+                            .with_fix("Run 'workspace-cli auth login' to authenticate");
+                        eprintln!("{}", error.to_json());
+                        exit(1);
+                    }
+                    let report = workspace_cli::commands::auth::doctor(token_manager.clone()).await;
+                    let mut formatter = Formatter::new(format).with_quiet(quiet);
+                    formatter.write(&report)?;
+                    if !report.healthy() {
+                        exit(1);
+                    }
                 }
                 AuthCommands::Status => {
                     let tm = token_manager.read().await;
@@ -3093,19 +3524,39 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                         println!("{}", serde_json::to_string_pretty(&status).unwrap());
                     }
                 }
+                AuthCommands::List => {
+                    let profiles = workspace_cli::auth::list_profiles(&config);
+                    let mut formatter = Formatter::new(format).with_quiet(quiet);
+                    formatter.write(&profiles)?;
+                }
+                AuthCommands::Switch { name } => {
+                    let mut stored = workspace_cli::config::Config::load();
+                    let known = name == workspace_cli::config::DEFAULT_PROFILE
+                        || stored.profiles.contains_key(&name)
+                        || workspace_cli::auth::token_cache_path(&name).exists();
+                    if !known {
+                        eprintln!(r#"{{"status":"error","message":"Unknown profile '{}'. Run 'workspace-cli auth login --profile {}' first."}}"#, name, name);
+                        exit(1);
+                    }
+                    stored.active_profile = (name != workspace_cli::config::DEFAULT_PROFILE).then(|| name.clone());
+                    stored.save()?;
+                    if !quiet {
+                        println!(r#"{{"status":"success","message":"Switched to profile {}"}}"#, name);
+                    }
+                }
                 AuthCommands::Export { unmasked, output } => {
                     // Use token_manager directly (tokens stored as JSON cache, not keyring)
                     let (access_token, cache_path, storage_type) = {
                         let mut tm = token_manager.write().await;
                         if let Err(e) = tm.ensure_authenticated().await {
                             eprintln!(r#"{{"status":"error","message":"Not authenticated: {}. Run 'workspace-cli auth login' first."}}"#, e);
-                            std::process::exit(1);
+                            exit(1);
                         }
                         let token = match tm.get_access_token().await {
                             Ok(t) => t,
                             Err(e) => {
                                 eprintln!(r#"{{"status":"error","message":"Failed to get token: {}"}}"#, e);
-                                std::process::exit(1);
+                                exit(1);
                             }
                         };
                         let status = tm.status();
@@ -3151,13 +3602,13 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
                 match tm.get_access_token().await {
                     Ok(token) => token,
                     Err(e) => {
                         eprintln!(r#"{{"status":"error","message":"Failed to get access token: {}"}}"#, e);
-                        std::process::exit(1);
+                        exit(1);
                     }
                 }
             };
@@ -3177,7 +3628,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                     Ok(content) => content,
                     Err(e) => {
                         eprintln!(r#"{{"status":"error","message":"Failed to read file '{}': {}"}}"#, path, e);
-                        std::process::exit(1);
+                        exit(1);
                     }
                 }
             } else if let Some(json) = requests_json {
@@ -3188,7 +3639,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut buffer = String::new();
                 if let Err(e) = std::io::stdin().read_to_string(&mut buffer) {
                     eprintln!(r#"{{"status":"error","message":"Failed to read from stdin: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
                 buffer
             };
@@ -3198,7 +3649,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 Ok(inputs) => inputs,
                 Err(e) => {
                     eprintln!(r#"{{"status":"error","message":"Invalid JSON input: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             };
 
@@ -3215,7 +3666,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 Err(e) => {
                     eprintln!(r#"{{"status":"error","message":"Batch request failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
         }
@@ -3225,7 +3676,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -3268,7 +3719,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     page_cfg.delay().await;
                                     params.page_token = next_token;
                                 }
-                                Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                                Err(e) => { fail(e); }
                             }
                         }
                         active_formatter.end_stream()?;
@@ -3281,7 +3732,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     ff.write(&response)?;
                                 } else { formatter.write(&response)?; }
                             }
-                            Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                            Err(e) => { fail(e); }
                         }
                     }
                 }
@@ -3294,7 +3745,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&spaces)?;
                             } else { formatter.write(&spaces)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::FindDm { email } => {
@@ -3306,7 +3757,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&space)?;
                             } else { formatter.write(&space)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::SpacesCreate { name, member } => {
@@ -3318,7 +3769,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&space)?;
                             } else { formatter.write(&space)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::MessagesList { space, limit, order, after, before, today } => {
@@ -3365,7 +3816,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     page_cfg.delay().await;
                                     params.page_token = next_token;
                                 }
-                                Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                                Err(e) => { fail(e); }
                             }
                         }
                         active_formatter.end_stream()?;
@@ -3384,12 +3835,14 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     let admin_client = ApiClient::admin(token_manager.clone()).with_dry_run(cli.dry_run);
                                     let mut name_map: std::collections::HashMap<String, String> = std::collections::HashMap::new();
                                     for uid in &user_ids {
-                                        if let Ok(user) = workspace_cli::commands::admin::users::get_user(&admin_client, uid).await {
-                                            if let Some(uname) = user.name {
-                                                if let Some(full) = uname.full_name {
+                                        match workspace_cli::commands::admin::users::get_user(&admin_client, uid).await {
+                                            Ok(user) => {
+                                                if let Some(full) = user.name.and_then(|n| n.full_name) {
                                                     name_map.insert(uid.clone(), full);
                                                 }
                                             }
+                                            Err(e) if matches!(e, WorkspaceError::DryRun) => return Err(e.into()),
+                                            Err(_) => {}
                                         }
                                     }
                                     for msg in &mut response.messages {
@@ -3412,7 +3865,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     ff.write(&response)?;
                                 } else { formatter.write(&response)?; }
                             }
-                            Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                            Err(e) => { fail(e); }
                         }
                     }
                 }
@@ -3425,7 +3878,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&state)?;
                             } else { formatter.write(&state)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::ThreadReadState { space, thread } => {
@@ -3437,7 +3890,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&state)?;
                             } else { formatter.write(&state)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::Unread { limit, r#type, since, include_muted } => {
@@ -3451,7 +3904,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&result)?;
                             } else { formatter.write(&result)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::MarkRead { space, all, time, r#type, since } => {
@@ -3471,9 +3924,13 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                         let mark_time = us.messages.first()
                                             .and_then(|m| m.create_time.as_deref())
                                             .unwrap_or(read_time);
-                                        if let Ok(_) = workspace_cli::commands::chat::read_state::update_space_read_state(&client, sn, mark_time).await {
-                                            marked += 1;
-                                            eprintln!("Marked read: {} ({})", us.display_name.as_deref().unwrap_or(sn), mark_time);
+                                        match workspace_cli::commands::chat::read_state::update_space_read_state(&client, sn, mark_time).await {
+                                            Ok(_) => {
+                                                marked += 1;
+                                                eprintln!("Marked read: {} ({})", us.display_name.as_deref().unwrap_or(sn), mark_time);
+                                            }
+                                            Err(e) if matches!(e, WorkspaceError::DryRun) => return Err(e.into()),
+                                            Err(_) => {}
                                         }
                                     }
                                 }
@@ -3484,7 +3941,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     ff.write(&summary)?;
                                 } else { formatter.write(&summary)?; }
                             }
-                            Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                            Err(e) => { fail(e); }
                         }
                     } else if let Some(ref space_name) = space {
                         match workspace_cli::commands::chat::read_state::update_space_read_state(&client, space_name, read_time).await {
@@ -3495,11 +3952,11 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     ff.write(&state)?;
                                 } else { formatter.write(&state)?; }
                             }
-                            Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                            Err(e) => { fail(e); }
                         }
                     } else {
                         eprintln!(r#"{{"status":"error","message":"Provide --space or --all"}}"#);
-                        std::process::exit(1);
+                        exit(1);
                     }
                 }
                 ChatCommands::Send { space, text, thread } => {
@@ -3511,7 +3968,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&msg)?;
                             } else { formatter.write(&msg)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ChatCommands::Get { name } => {
@@ -3523,7 +3980,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&msg)?;
                             } else { formatter.write(&msg)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
             }
@@ -3534,7 +3991,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -3570,7 +4027,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     page_cfg.delay().await;
                                     params.page_token = next_token;
                                 }
-                                Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                                Err(e) => { fail(e); }
                             }
                         }
                         active_formatter.end_stream()?;
@@ -3583,7 +4040,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     ff.write(&response)?;
                                 } else { formatter.write(&response)?; }
                             }
-                            Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                            Err(e) => { fail(e); }
                         }
                     }
                 }
@@ -3596,7 +4053,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&response)?;
                             } else { formatter.write(&response)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ContactsCommands::Get { name } => {
@@ -3608,7 +4065,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&person)?;
                             } else { formatter.write(&person)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ContactsCommands::Create { given, family, email, phone, org, title } => {
@@ -3624,7 +4081,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&person)?;
                             } else { formatter.write(&person)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ContactsCommands::Delete { name } => {
@@ -3634,7 +4091,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 println!(r#"{{"status":"success","message":"Contact deleted"}}"#);
                             }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 ContactsCommands::DirectoryList { limit } => {
@@ -3665,7 +4122,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     page_cfg.delay().await;
                                     params.page_token = next_token;
                                 }
-                                Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                                Err(e) => { fail(e); }
                             }
                         }
                         active_formatter.end_stream()?;
@@ -3678,7 +4135,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     ff.write(&response)?;
                                 } else { formatter.write(&response)?; }
                             }
-                            Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                            Err(e) => { fail(e); }
                         }
                     }
                 }
@@ -3691,7 +4148,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&response)?;
                             } else { formatter.write(&response)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
             }
@@ -3702,7 +4159,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -3713,7 +4170,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 GroupsCommands::List { email, domain, limit } => {
                     if email.is_none() && domain.is_none() {
                         eprintln!(r#"{{"status":"error","message":"Either --email or --domain is required"}}"#);
-                        std::process::exit(1);
+                        exit(1);
                     }
                     let admin_client = ApiClient::admin(token_manager.clone()).with_dry_run(cli.dry_run);
                     let params = workspace_cli::commands::groups::list::ListGroupsParams {
@@ -3727,7 +4184,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&response)?;
                             } else { formatter.write(&response)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 GroupsCommands::Members { group_email, limit } => {
@@ -3739,7 +4196,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&response)?;
                             } else { formatter.write(&response)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
             }
@@ -3749,7 +4206,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Authentication failed: {}"}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
 
@@ -3769,7 +4226,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&response)?;
                             } else { formatter.write(&response)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 AdminCommands::UsersGet { user_key } => {
@@ -3781,7 +4238,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&response)?;
                             } else { formatter.write(&response)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
                 AdminCommands::ReportsDriveActivity { event_name, start_time, end_time, filters, max_results } => {
@@ -3798,7 +4255,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 ff.write(&events)?;
                             } else { formatter.write(&events)?; }
                         }
-                        Err(e) => { eprintln!(r#"{{"status":"error","message":"{}"}}"#, e); std::process::exit(1); }
+                        Err(e) => { fail(e); }
                     }
                 }
             }
@@ -3809,7 +4266,7 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 let mut tm = token_manager.write().await;
                 if let Err(e) = tm.ensure_authenticated().await {
                     eprintln!(r#"{{"status":"error","message":"Not authenticated: {}. Run 'workspace-cli auth login' first."}}"#, e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
             eprintln!("workspace-cli MCP server starting (stdio)...");
@@ -0,0 +1,327 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use std::sync::RwLock;
+
+use crate::error::{Result, WorkspaceError};
+
+/// Rough size of a token in characters of compact JSON
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Strings shortened to fit `--max-tokens` keep at least this many characters
+const MIN_KEEP: usize = 200;
+
+/// Output limits for agents with a fixed context window
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Budget {
+    /// Estimated tokens for everything one command prints
+    pub max_tokens: Option<usize>,
+    /// Character limits for named string fields (`--truncate body=2000`)
+    pub truncate: Vec<(String, usize)>,
+    /// List items to skip, from the continuation token of an earlier run
+    pub offset: usize,
+}
+
+impl Budget {
+    pub fn is_empty(&self) -> bool {
+        self.max_tokens.is_none() && self.truncate.is_empty() && self.offset == 0
+    }
+}
+
+static BUDGET: RwLock<Option<Budget>> = RwLock::new(None);
+
+/// Install the output budget. Call once at startup.
+pub fn configure(budget: Option<Budget>) {
+    *BUDGET.write().unwrap_or_else(|e| e.into_inner()) = budget;
+}
+
+fn current() -> Option<Budget> {
+    BUDGET.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Parse a `--truncate FIELD=N` option. FIELD matches a key at any depth,
+/// or the end of a dotted path (`payload.body`).
+pub fn parse_truncate(spec: &str) -> Result<(String, usize)> {
+    spec.split_once('=')
+        .and_then(|(field, limit)| Some((field.trim(), limit.trim().parse().ok()?)))
+        .filter(|(field, _)| !field.is_empty())
+        .map(|(field, limit)| (field.to_string(), limit))
+        .ok_or_else(|| WorkspaceError::Config(format!(
+            "Invalid --truncate '{}': expected FIELD=CHARS, e.g. body=2000", spec
+        )))
+}
+
+/// Decode a `--continue` token into the number of items already printed
+pub fn parse_continuation(token: &str) -> Result<usize> {
+    URL_SAFE_NO_PAD.decode(token.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|text| text.strip_prefix("offset:")?.parse().ok())
+        .ok_or_else(|| WorkspaceError::Config(format!("Invalid --continue token '{}'", token)))
+}
+
+fn continuation(offset: usize) -> String {
+    URL_SAFE_NO_PAD.encode(format!("offset:{}", offset))
+}
+
+/// Estimated tokens of a value as compact JSON
+pub fn estimate(value: &Value) -> usize {
+    serde_json::to_string(value).map(|s| s.len()).unwrap_or(0).div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Fit a whole response: cut `--truncate` fields, skip items already seen
+/// with `--continue`, drop list items from the tail until the rest fits
+/// `--max-tokens`, then shorten the longest strings if it still does not.
+/// Returns the value and, when items were dropped, the marker describing them.
+pub fn apply(mut value: Value, wrapper: Option<&str>) -> (Value, Option<Value>) {
+    let Some(budget) = current() else { return (value, None) };
+    truncate_fields(&mut value, "", &budget.truncate);
+
+    let mut marker = None;
+    if let Some(items) = list_mut(&mut value, wrapper) {
+        items.drain(..budget.offset.min(items.len()));
+        if let Some(max) = budget.max_tokens {
+            let all = std::mem::take(items);
+            let mut spent = estimate(&value);
+            let mut kept = Vec::new();
+            for item in all.iter() {
+                let cost = estimate(item) + 1;
+                if !kept.is_empty() && spent + cost > max {
+                    break;
+                }
+                spent += cost;
+                kept.push(item.clone());
+            }
+            let omitted = all.len() - kept.len();
+            if let Some(items) = list_mut(&mut value, wrapper) {
+                *items = kept;
+            }
+            if omitted > 0 {
+                marker = Some(marker_for(omitted, budget.offset + all.len() - omitted));
+            }
+        }
+    }
+    if let Some(max) = budget.max_tokens {
+        shorten_strings(&mut value, max);
+    }
+    (value, marker)
+}
+
+/// Put a marker from [`apply`] back into the response: as keys of a list
+/// wrapper, or as the last element of an array
+pub fn attach(value: Value, wrapper: Option<&str>, marker: Value) -> Value {
+    match (value, marker) {
+        (Value::Object(mut map), Value::Object(keys)) if wrapper.is_some() => {
+            map.extend(keys);
+            Value::Object(map)
+        }
+        (Value::Array(mut items), marker) => {
+            items.push(marker);
+            Value::Array(items)
+        }
+        (value, _) => value,
+    }
+}
+
+fn list_mut<'a>(value: &'a mut Value, wrapper: Option<&str>) -> Option<&'a mut Vec<Value>> {
+    match (value, wrapper) {
+        (Value::Object(map), Some(wrapper)) => map.get_mut(wrapper).and_then(Value::as_array_mut),
+        (Value::Array(items), _) => Some(items),
+        _ => None,
+    }
+}
+
+fn marker_for(omitted: usize, offset: usize) -> Value {
+    json!({
+        "truncated": true,
+        "omitted": omitted,
+        "continuation": continuation(offset),
+    })
+}
+
+/// Running budget for items written one at a time (`--page-all`, JSONL)
+#[derive(Debug, Default)]
+pub struct Stream {
+    skipped: usize,
+    written: usize,
+    omitted: usize,
+    spent: usize,
+}
+
+impl Stream {
+    /// The item to write, or `None` when it was already seen or is over budget.
+    /// Once one item is left out, every later one is too, so the
+    /// continuation offset stays exact.
+    pub fn admit(&mut self, mut item: Value) -> Option<Value> {
+        let Some(budget) = current() else { return Some(item) };
+        if self.skipped < budget.offset {
+            self.skipped += 1;
+            return None;
+        }
+        truncate_fields(&mut item, "", &budget.truncate);
+        if let Some(max) = budget.max_tokens {
+            if self.omitted > 0 {
+                self.omitted += 1;
+                return None;
+            }
+            if self.written == 0 {
+                shorten_strings(&mut item, max);
+            }
+            let cost = estimate(&item) + 1;
+            if self.written > 0 && self.spent + cost > max {
+                self.omitted += 1;
+                return None;
+            }
+            self.spent += cost;
+        }
+        self.written += 1;
+        Some(item)
+    }
+
+    /// Marker record to write after the last item when some were left out
+    pub fn marker(&self) -> Option<Value> {
+        let offset = current().map(|b| b.offset).unwrap_or(0);
+        (self.omitted > 0).then(|| marker_for(self.omitted, offset + self.written))
+    }
+}
+
+/// Plain-text output (`docs get --text`, `slides get`): cut to `--truncate
+/// text=N` or the `--max-tokens` budget, with a closing note
+pub fn truncate_text(text: String) -> String {
+    let Some(budget) = current() else { return text };
+    let limit = budget.truncate.iter()
+        .filter(|(field, _)| field == "text")
+        .map(|(_, limit)| *limit)
+        .chain(budget.max_tokens.map(|max| max * CHARS_PER_TOKEN))
+        .min();
+    let length = text.chars().count();
+    match limit {
+        Some(limit) if length > limit => format!(
+            "{}\n[truncated: {} of {} characters]",
+            text.chars().take(limit).collect::<String>(),
+            limit,
+            length
+        ),
+        _ => text,
+    }
+}
+
+/// A string cut to `keep` characters, with its original length
+fn truncated(text: &str, keep: usize) -> Value {
+    json!({
+        "text": text.chars().take(keep).collect::<String>(),
+        "truncated": true,
+        "originalLength": text.chars().count(),
+    })
+}
+
+fn is_truncated(map: &Map<String, Value>) -> bool {
+    map.get("truncated") == Some(&Value::Bool(true)) && map.contains_key("originalLength")
+}
+
+fn truncate_fields(value: &mut Value, path: &str, limits: &[(String, usize)]) {
+    if limits.is_empty() {
+        return;
+    }
+    match value {
+        Value::Object(map) if !is_truncated(map) => {
+            for (key, field) in map.iter_mut() {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                let limit = limits.iter()
+                    .filter(|(name, _)| path == *name || path.ends_with(&format!(".{}", name)))
+                    .map(|(_, limit)| *limit)
+                    .min();
+                let cut = match (&*field, limit) {
+                    (Value::String(text), Some(limit)) if text.chars().count() > limit => Some(truncated(text, limit)),
+                    _ => None,
+                };
+                match cut {
+                    Some(cut) => *field = cut,
+                    None => truncate_fields(field, &path, limits),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                truncate_fields(item, path, limits);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Shorten the longest strings, down to a floor, until the value fits `max` tokens
+fn shorten_strings(value: &mut Value, max: usize) {
+    loop {
+        let excess = estimate(value).saturating_sub(max) * CHARS_PER_TOKEN;
+        if excess == 0 {
+            return;
+        }
+        let mut strings = Vec::new();
+        collect_strings(value, String::new(), false, &mut strings);
+        let Some((pointer, length, in_marker)) = strings.into_iter()
+            .filter(|(_, length, _)| *length > MIN_KEEP)
+            .max_by_key(|(_, length, _)| *length)
+        else {
+            return;
+        };
+        let keep = length.saturating_sub(excess).max(MIN_KEEP);
+        if let Some(slot) = value.pointer_mut(&pointer) {
+            let text = slot.as_str().unwrap_or_default();
+            // Text already cut by --truncate keeps its first originalLength
+            *slot = if in_marker {
+                Value::String(text.chars().take(keep).collect())
+            } else {
+                truncated(text, keep)
+            };
+        }
+    }
+}
+
+/// JSON pointers and lengths of every string, and whether it is the text of a truncation marker
+fn collect_strings(value: &Value, pointer: String, in_marker: bool, out: &mut Vec<(String, usize, bool)>) {
+    let escape = |key: &str| key.replace('~', "~0").replace('/', "~1");
+    match value {
+        Value::String(text) => out.push((pointer, text.chars().count(), in_marker)),
+        Value::Object(map) if is_truncated(map) => {
+            if let Some(text) = map.get("text") {
+                collect_strings(text, format!("{}/text", pointer), true, out);
+            }
+        }
+        Value::Object(map) => {
+            for (key, field) in map {
+                collect_strings(field, format!("{}/{}", pointer, escape(key)), false, out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_strings(item, format!("{}/{}", pointer, i), false, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_options_and_round_trips_continuation() {
+        assert_eq!(parse_truncate("body=2000").unwrap(), ("body".to_string(), 2000));
+        assert!(parse_truncate("body").is_err());
+        assert!(parse_truncate("=10").is_err());
+        assert_eq!(parse_continuation(&continuation(42)).unwrap(), 42);
+        assert!(parse_continuation("not-a-token").is_err());
+    }
+
+    #[test]
+    fn shortens_the_longest_string_to_fit() {
+        let mut value = json!({"id": "m1", "subject": "hi", "body": "x".repeat(10_000)});
+        shorten_strings(&mut value, 500);
+        assert!(estimate(&value) <= 500);
+        assert_eq!(value["subject"], "hi");
+        assert_eq!(value["body"]["truncated"], true);
+        assert_eq!(value["body"]["originalLength"], 10_000);
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::Value;
+use std::sync::RwLock;
+
+/// Joins array elements into one cell unless `--array-separator` says otherwise
+pub const DEFAULT_SEPARATOR: &str = "; ";
+
+/// Streamed rows buffered before the CSV/TSV header is fixed
+pub const SAMPLE_SIZE: usize = 100;
+
+static SEPARATOR: RwLock<Option<String>> = RwLock::new(None);
+
+/// Install the array separator for CSV/TSV cells. Call once at startup.
+pub fn configure(separator: Option<String>) {
+    *SEPARATOR.write().unwrap_or_else(|e| e.into_inner()) = separator;
+}
+
+fn separator() -> String {
+    SEPARATOR.read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| DEFAULT_SEPARATOR.to_string())
+}
+
+/// One record as (column, text) pairs. Nested objects become dotted columns
+/// (`owners.displayName`); arrays are joined with the separator, and arrays
+/// of objects are joined per column. A bare array is a positional row
+/// (`col0`, `col1`, ...), as for Sheets values.
+pub fn flatten(record: &Value) -> Vec<(String, String)> {
+    let separator = separator();
+    let mut cells = Vec::new();
+    match record {
+        Value::Object(_) => flatten_into("", record, &separator, &mut cells),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_into(&format!("col{}", i), item, &separator, &mut cells);
+            }
+        }
+        scalar => cells.push(("value".to_string(), text(scalar))),
+    }
+    cells
+}
+
+fn flatten_into(prefix: &str, value: &Value, separator: &str, cells: &mut Vec<(String, String)>) {
+    let column = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten_into(&column(key), value, separator, cells);
+            }
+        }
+        Value::Array(items) if items.iter().any(Value::is_object) => {
+            // Flatten each element, then join element values column by column.
+            // Elements without a column keep their slot so positions line up.
+            let flattened: Vec<Vec<(String, String)>> = items.iter()
+                .map(|item| {
+                    let mut item_cells = Vec::new();
+                    flatten_into("", item, separator, &mut item_cells);
+                    item_cells
+                })
+                .collect();
+            for key in union(&flattened) {
+                let joined: Vec<&str> = flattened.iter()
+                    .map(|item| item.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str()).unwrap_or(""))
+                    .collect();
+                let name = if key.is_empty() { prefix.to_string() } else { column(&key) };
+                cells.push((name, joined.join(separator)));
+            }
+        }
+        Value::Array(items) => {
+            let joined: Vec<String> = items.iter().map(text).collect();
+            cells.push((prefix.to_string(), joined.join(separator)));
+        }
+        scalar => cells.push((prefix.to_string(), text(scalar))),
+    }
+}
+
+/// Every column of the rows, in order of first appearance. An empty
+/// object or array in one row does not add a column when other rows
+/// have columns nested under it.
+pub fn union(rows: &[Vec<(String, String)>]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        for (key, _) in row {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let parents: Vec<String> = columns.iter()
+        .filter(|c| columns.iter().any(|other| other.len() > c.len() && other.starts_with(&format!("{}.", c))))
+        .cloned()
+        .collect();
+    columns.retain(|c| !parents.contains(c));
+    columns
+}
+
+/// Order and restrict columns by `--fields`: a field selects its own column
+/// and every column nested under it. A field with no data still gets a
+/// column, so the header is the same whatever the first rows hold.
+pub fn select(columns: Vec<String>, fields: Option<&[String]>, wrapper: Option<&str>) -> Vec<String> {
+    let Some(fields) = fields.filter(|f| !f.is_empty()) else { return columns };
+    let mut selected: Vec<String> = Vec::new();
+    for field in fields {
+        let field = match wrapper {
+            Some(w) => field.strip_prefix(&format!("{}.", w)).unwrap_or(field),
+            None => field.as_str(),
+        };
+        let nested = format!("{}.", field);
+        let matching: Vec<&String> = columns.iter()
+            .filter(|c| *c == field || c.starts_with(&nested))
+            .collect();
+        if matching.is_empty() && !selected.iter().any(|s| s == field) {
+            selected.push(field.to_string());
+        }
+        for column in matching {
+            if !selected.contains(column) {
+                selected.push(column.clone());
+            }
+        }
+    }
+    selected
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn flattens_nested_objects_and_arrays() {
+        let record = json!({
+            "id": "f1",
+            "name": {"fullName": "Ann Lee", "givenName": "Ann"},
+            "parents": ["p1", "p2"],
+            "owners": [{"displayName": "Ann", "email": "ann@example.com"}, {"displayName": "Bob"}],
+            "shared": null,
+        });
+        assert_eq!(flatten(&record), vec![
+            ("id".to_string(), "f1".to_string()),
+            ("name.fullName".to_string(), "Ann Lee".to_string()),
+            ("name.givenName".to_string(), "Ann".to_string()),
+            ("parents".to_string(), "p1; p2".to_string()),
+            ("owners.displayName".to_string(), "Ann; Bob".to_string()),
+            ("owners.email".to_string(), "ann@example.com; ".to_string()),
+            ("shared".to_string(), String::new()),
+        ]);
+    }
+
+    #[test]
+    fn fields_select_nested_columns_and_keep_missing_ones() {
+        let columns = vec!["id".to_string(), "name.fullName".to_string(), "name.givenName".to_string()];
+        let fields = vec!["files.name".to_string(), "size".to_string(), "id".to_string()];
+        assert_eq!(
+            select(columns, Some(&fields), Some("files")),
+            vec!["name.fullName", "name.givenName", "size", "id"]
+        );
+    }
+}
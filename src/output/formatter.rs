@@ -1,6 +1,8 @@
 use serde::Serialize;
 use std::io::{self, Write};
 
+use super::{budget, flatten, markdown, query, redact, yaml};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OutputFormat {
     Toon,
@@ -8,9 +10,15 @@ pub enum OutputFormat {
     JsonCompact,
     Jsonl,
     Csv,
+    /// Tab-separated values
+    Tsv,
+    /// GitHub-flavored markdown tables
+    Markdown,
+    Yaml,
 }
 
 impl OutputFormat {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "toon" => Some(Self::Toon),
@@ -18,6 +26,9 @@ impl OutputFormat {
             "json-compact" | "jsoncompact" => Some(Self::JsonCompact),
             "jsonl" | "ndjson" => Some(Self::Jsonl),
             "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            "md" | "markdown" => Some(Self::Markdown),
+            "yaml" | "yml" => Some(Self::Yaml),
             _ => None,
         }
     }
@@ -27,9 +38,14 @@ pub struct Formatter {
     format: OutputFormat,
     writer: Box<dyn Write>,
     first_item: bool,
-    csv_headers: Option<Vec<String>>,
+    /// Columns fixed by the first rows written (CSV/TSV, markdown)
+    headers: Option<Vec<String>>,
+    /// Streamed CSV/TSV rows held back until the header is fixed
+    pending: Vec<serde_json::Value>,
     fields: Option<Vec<String>>,
     quiet: bool,
+    /// `--max-tokens` spent by items written one at a time
+    stream_budget: budget::Stream,
 }
 
 impl Formatter {
@@ -38,9 +54,11 @@ impl Formatter {
             format,
             writer: Box::new(io::stdout()),
             first_item: true,
-            csv_headers: None,
+            headers: None,
+            pending: Vec::new(),
             fields: None,
             quiet: false,
+            stream_budget: budget::Stream::default(),
         }
     }
 
@@ -65,6 +83,12 @@ impl Formatter {
         self.format
     }
 
+    /// Everything written goes through here: `--fields` filtering, then
+    /// `--redact` masking
+    fn prepare(&self, value: serde_json::Value) -> serde_json::Value {
+        redact::apply(self.filter_fields(value))
+    }
+
     /// Filter a JSON value to only include specified fields
     fn filter_fields(&self, value: serde_json::Value) -> serde_json::Value {
         let fields = match &self.fields {
@@ -74,15 +98,17 @@ impl Formatter {
 
         match value {
             serde_json::Value::Object(map) => {
-                // Check for known list wrapper keys (API responses wrap arrays)
-                const WRAPPER_KEYS: &[&str] = &["files", "messages", "items", "labels", "permissions"];
-
                 // Find if this is a wrapper object with an array to filter
-                for wrapper_key in WRAPPER_KEYS {
-                    if let Some(serde_json::Value::Array(arr)) = map.get(*wrapper_key) {
+                if let Some(wrapper_key) = list_wrapper(&map) {
+                    if let Some(serde_json::Value::Array(arr)) = map.get(wrapper_key) {
                         // This is a list wrapper - filter the array items
+                        // ("files.name" and "name" select the same item field)
+                        let prefix = format!("{}.", wrapper_key);
+                        let item_fields: Vec<String> = fields.iter()
+                            .map(|f| f.strip_prefix(&prefix).unwrap_or(f).to_string())
+                            .collect();
                         let filtered_items: Vec<serde_json::Value> = arr.iter()
-                            .map(|item| self.filter_item_fields(item.clone(), fields))
+                            .map(|item| self.filter_item_fields(item.clone(), &item_fields))
                             .collect();
 
                         // Reconstruct wrapper with filtered items + metadata
@@ -91,7 +117,7 @@ impl Formatter {
 
                         // Preserve metadata keys (nextPageToken, resultSizeEstimate, etc.)
                         for (key, val) in map.iter() {
-                            if key != *wrapper_key {
+                            if key != wrapper_key {
                                 result.insert(key.clone(), val.clone());
                             }
                         }
@@ -184,31 +210,133 @@ impl Formatter {
         // Convert to JSON value for field filtering
         let value = serde_json::to_value(item)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        let filtered = self.filter_fields(value);
+        if query::active() {
+            return self.write_query_results(value);
+        }
+        let filtered = self.prepare(value);
+        self.write_fitted(filtered)
+    }
+
+    /// Run the `--query` expression, then write its outputs: one line or row each
+    /// for JSONL/CSV/TSV, otherwise a single value (an array unless exactly one)
+    fn write_query_results(&mut self, value: serde_json::Value) -> io::Result<()> {
+        let mut results = query::apply(value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("--query: {}", e)))?;
+        match self.format {
+            OutputFormat::Jsonl => {
+                for result in results {
+                    let filtered = self.prepare(result);
+                    self.write_line(filtered)?;
+                }
+                self.write_stream_marker()
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let rows = results.into_iter().map(|r| self.prepare(r)).collect();
+                let (rows, marker) = budget::apply(serde_json::Value::Array(rows), None);
+                self.report_marker(marker);
+                let rows = match rows {
+                    serde_json::Value::Array(rows) => rows,
+                    row => vec![row],
+                };
+                self.write_table(rows, None)
+            }
+            _ => {
+                let value = if results.len() == 1 {
+                    results.remove(0)
+                } else {
+                    serde_json::Value::Array(results)
+                };
+                let filtered = self.prepare(value);
+                self.write_fitted(filtered)
+            }
+        }
+    }
+
+    /// Fit a whole response in the output budget, then write it. Markers for
+    /// dropped list items go inside JSON-like output, or to stderr for tables.
+    fn write_fitted(&mut self, value: serde_json::Value) -> io::Result<()> {
+        let wrapper = value.as_object().and_then(list_wrapper);
+        let (value, marker) = budget::apply(value, wrapper);
+        let value = match marker {
+            Some(marker) if !self.is_table() => budget::attach(value, wrapper, marker),
+            marker => {
+                self.report_marker(marker);
+                value
+            }
+        };
+        self.write_value(&value)
+    }
+
+    /// Write one JSONL record against the running budget
+    fn write_line(&mut self, value: serde_json::Value) -> io::Result<()> {
+        match self.stream_budget.admit(value) {
+            Some(value) => self.write_value(&value),
+            None => Ok(()),
+        }
+    }
+
+    fn write_stream_marker(&mut self) -> io::Result<()> {
+        match self.stream_budget.marker() {
+            Some(marker) if !self.is_table() => self.stream_value(&marker),
+            marker => {
+                self.report_marker(marker);
+                Ok(())
+            }
+        }
+    }
+
+    fn report_marker(&self, marker: Option<serde_json::Value>) {
+        if let Some(marker) = marker {
+            eprintln!("{}", marker);
+        }
+    }
+
+    /// Formats whose rows cannot carry a truncation marker
+    fn is_table(&self) -> bool {
+        matches!(self.format, OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Markdown)
+    }
 
+    fn write_value(&mut self, filtered: &serde_json::Value) -> io::Result<()> {
         match self.format {
+            OutputFormat::Markdown => {
+                let wrapper = filtered.as_object().and_then(list_wrapper);
+                let md = markdown::render(filtered, wrapper, self.fields.as_deref());
+                write!(self.writer, "{}", md)
+            }
+            OutputFormat::Yaml => {
+                write!(self.writer, "{}", yaml::render(filtered))
+            }
             OutputFormat::Toon => {
-                let toon = toon_format::encode(&filtered, &toon_format::EncodeOptions::default())
+                let toon = toon_format::encode(filtered, &toon_format::EncodeOptions::default())
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
                 writeln!(self.writer, "{}", toon)
             }
             OutputFormat::Json => {
-                let json = serde_json::to_string_pretty(&filtered)
+                let json = serde_json::to_string_pretty(filtered)
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                 writeln!(self.writer, "{}", json)
             }
             OutputFormat::JsonCompact => {
-                let json = serde_json::to_string(&filtered)
+                let json = serde_json::to_string(filtered)
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                 writeln!(self.writer, "{}", json)
             }
             OutputFormat::Jsonl => {
-                let json = serde_json::to_string(&filtered)
+                let json = serde_json::to_string(filtered)
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                 writeln!(self.writer, "{}", json)
             }
-            OutputFormat::Csv => {
-                self.write_csv_row(&filtered)
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                // A list response becomes one row per item
+                let wrapper = filtered.as_object().and_then(list_wrapper);
+                let rows = match (filtered, wrapper) {
+                    (serde_json::Value::Object(map), Some(wrapper)) => {
+                        map.get(wrapper).and_then(|v| v.as_array()).cloned().unwrap_or_default()
+                    }
+                    (serde_json::Value::Array(items), _) => items.clone(),
+                    (value, _) => vec![value.clone()],
+                };
+                self.write_table(rows, wrapper)
             }
         }
     }
@@ -219,35 +347,34 @@ impl Formatter {
         if self.quiet {
             return Ok(());
         }
+        if query::active() {
+            let value = serde_json::to_value(items)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            return self.write_query_results(value);
+        }
 
         match self.format {
-            OutputFormat::Toon => {
-                // Convert to JSON value for field filtering, then encode as TOON
+            OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Markdown | OutputFormat::Yaml => {
                 let value = serde_json::to_value(items)
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                let filtered = self.filter_fields(value);
-                let toon = toon_format::encode(&filtered, &toon_format::EncodeOptions::default())
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
-                writeln!(self.writer, "{}", toon)
+                let filtered = self.prepare(value);
+                self.write_fitted(filtered)
             }
-            OutputFormat::Json | OutputFormat::JsonCompact => {
+            OutputFormat::Toon | OutputFormat::Json | OutputFormat::JsonCompact => {
                 // Convert to JSON value for field filtering
                 let value = serde_json::to_value(items)
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                let filtered = self.filter_fields(value);
-
-                let json = if self.format == OutputFormat::Json {
-                    serde_json::to_string_pretty(&filtered)
-                } else {
-                    serde_json::to_string(&filtered)
-                }.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                writeln!(self.writer, "{}", json)
+                let filtered = self.prepare(value);
+                self.write_fitted(filtered)
             }
-            OutputFormat::Jsonl | OutputFormat::Csv => {
+            OutputFormat::Jsonl => {
                 for item in items {
-                    self.write(item)?;
+                    let value = serde_json::to_value(item)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let filtered = self.prepare(value);
+                    self.write_line(filtered)?;
                 }
-                Ok(())
+                self.write_stream_marker()
             }
         }
     }
@@ -260,7 +387,8 @@ impl Formatter {
         match self.format {
             OutputFormat::Json => write!(self.writer, "["),
             OutputFormat::JsonCompact => write!(self.writer, "["),
-            OutputFormat::Toon | OutputFormat::Jsonl | OutputFormat::Csv => Ok(()),
+            OutputFormat::Toon | OutputFormat::Jsonl | OutputFormat::Csv | OutputFormat::Tsv
+            | OutputFormat::Markdown | OutputFormat::Yaml => Ok(()),
         }
     }
 
@@ -273,11 +401,36 @@ impl Formatter {
         // Convert to JSON value for field filtering
         let value = serde_json::to_value(item)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        let filtered = self.filter_fields(value);
+        // With --query each streamed item is one input; its outputs become stream items
+        let results = query::apply(value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("--query: {}", e)))?;
+        for result in results {
+            let filtered = self.prepare(result);
+            if let Some(item) = self.stream_budget.admit(filtered) {
+                self.stream_value(&item)?;
+            }
+        }
+        Ok(())
+    }
 
+    fn stream_value(&mut self, filtered: &serde_json::Value) -> io::Result<()> {
         match self.format {
+            OutputFormat::Markdown => {
+                if self.headers.is_none() {
+                    let columns = markdown::columns(std::slice::from_ref(filtered), self.fields.as_deref(), None);
+                    write!(self.writer, "{}", markdown::header(&columns))?;
+                    self.headers = Some(columns);
+                }
+                self.first_item = false;
+                let row = markdown::row(filtered, self.headers.as_deref().unwrap_or_default());
+                write!(self.writer, "{}", row)
+            }
+            OutputFormat::Yaml => {
+                self.first_item = false;
+                write!(self.writer, "{}", yaml::sequence_item(filtered))
+            }
             OutputFormat::Toon => {
-                let toon = toon_format::encode(&filtered, &toon_format::EncodeOptions::default())
+                let toon = toon_format::encode(filtered, &toon_format::EncodeOptions::default())
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
                 writeln!(self.writer, "{}", toon)
             }
@@ -289,7 +442,7 @@ impl Formatter {
 
                 let json = if self.format == OutputFormat::Json {
                     // For pretty JSON in streaming mode, add newline before each item
-                    let pretty = serde_json::to_string_pretty(&filtered)
+                    let pretty = serde_json::to_string_pretty(filtered)
                         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                     // Indent each line for proper array formatting
                     let indented = pretty.lines()
@@ -298,18 +451,27 @@ impl Formatter {
                         .join("\n");
                     format!("\n{}", indented)
                 } else {
-                    serde_json::to_string(&filtered)
+                    serde_json::to_string(filtered)
                         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
                 };
                 write!(self.writer, "{}", json)
             }
             OutputFormat::Jsonl => {
-                let json = serde_json::to_string(&filtered)
+                let json = serde_json::to_string(filtered)
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                 writeln!(self.writer, "{}", json)
             }
-            OutputFormat::Csv => {
-                self.write_csv_row(&filtered)
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                if self.headers.is_some() {
+                    return self.write_table(vec![filtered.clone()], None);
+                }
+                // Hold rows back until enough are seen to fix the header
+                self.pending.push(filtered.clone());
+                if self.pending.len() >= flatten::SAMPLE_SIZE {
+                    let rows = std::mem::take(&mut self.pending);
+                    self.write_table(rows, None)?;
+                }
+                Ok(())
             }
         }
     }
@@ -319,71 +481,62 @@ impl Formatter {
         if self.quiet {
             return Ok(());
         }
+        self.write_stream_marker()?;
         match self.format {
             OutputFormat::Json => writeln!(self.writer, "\n]"),
             OutputFormat::JsonCompact => writeln!(self.writer, "]"),
-            OutputFormat::Toon | OutputFormat::Jsonl | OutputFormat::Csv => Ok(()),
+            OutputFormat::Markdown if self.first_item => writeln!(self.writer, "_No results_"),
+            OutputFormat::Yaml if self.first_item => writeln!(self.writer, "[]"),
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let rows = std::mem::take(&mut self.pending);
+                if rows.is_empty() { Ok(()) } else { self.write_table(rows, None) }
+            }
+            OutputFormat::Toon | OutputFormat::Jsonl | OutputFormat::Markdown | OutputFormat::Yaml => Ok(()),
         }
     }
 
-    fn write_csv_row(&mut self, value: &serde_json::Value) -> io::Result<()> {
-        match value {
-            serde_json::Value::Object(map) => {
-                // Write header if first item and store the header order
-                if self.first_item {
-                    let headers: Vec<String> = map.keys().map(|s| s.to_string()).collect();
-                    writeln!(self.writer, "{}", headers.join(","))?;
-                    self.csv_headers = Some(headers);
-                    self.first_item = false;
-                }
-
-                // Write values in the same order as headers
-                if let Some(ref headers) = self.csv_headers {
-                    let values: Vec<String> = headers.iter()
-                        .map(|key| {
-                            map.get(key)
-                                .map(|v| self.csv_escape(v))
-                                .unwrap_or_default()
-                        })
-                        .collect();
-                    writeln!(self.writer, "{}", values.join(","))
-                } else {
-                    // Fallback if headers not set (shouldn't happen)
-                    let values: Vec<String> = map.values()
-                        .map(|v| self.csv_escape(v))
-                        .collect();
-                    writeln!(self.writer, "{}", values.join(","))
-                }
-            }
-            serde_json::Value::Array(arr) => {
-                // Write header if first item
-                if self.first_item {
-                    let headers: Vec<String> = (0..arr.len()).map(|i| format!("col{}", i)).collect();
-                    writeln!(self.writer, "{}", headers.join(","))?;
-                    self.first_item = false;
-                }
-                let values: Vec<String> = arr.iter()
-                    .map(|v| self.csv_escape(v))
-                    .collect();
-                writeln!(self.writer, "{}", values.join(","))
-            }
-            _ => {
-                writeln!(self.writer, "{}", self.csv_escape(value))
-            }
+    /// Write CSV/TSV rows with nested fields flattened. The header is fixed
+    /// by the first rows written (union of their columns, or `--fields`);
+    /// later rows are written against it.
+    fn write_table(&mut self, records: Vec<serde_json::Value>, wrapper: Option<&str>) -> io::Result<()> {
+        let rows: Vec<Vec<(String, String)>> = records.iter().map(flatten::flatten).collect();
+        if self.headers.is_none() {
+            let columns = flatten::select(flatten::union(&rows), self.fields.as_deref(), wrapper);
+            let header: Vec<String> = columns.iter().map(|c| self.escape_cell(c)).collect();
+            writeln!(self.writer, "{}", header.join(self.delimiter()))?;
+            self.headers = Some(columns);
         }
+        self.first_item = false;
+
+        let columns = self.headers.clone().unwrap_or_default();
+        for row in rows {
+            let cells: Vec<String> = columns.iter()
+                .map(|column| {
+                    row.iter()
+                        .find(|(key, _)| key == column)
+                        .map(|(_, text)| self.escape_cell(text))
+                        .unwrap_or_default()
+                })
+                .collect();
+            writeln!(self.writer, "{}", cells.join(self.delimiter()))?;
+        }
+        Ok(())
     }
 
-    fn csv_escape(&self, value: &serde_json::Value) -> String {
-        let s = match value {
-            serde_json::Value::String(s) => s.clone(),
-            serde_json::Value::Null => String::new(),
-            _ => value.to_string(),
-        };
+    fn delimiter(&self) -> &'static str {
+        if self.format == OutputFormat::Tsv { "\t" } else { "," }
+    }
+
+    fn escape_cell(&self, s: &str) -> String {
+        if self.format == OutputFormat::Tsv {
+            // TSV has no quoting: tabs and line breaks become spaces
+            return s.replace(['\t', '\n', '\r'], " ");
+        }
         // Escape quotes and wrap in quotes if contains comma, quote, or newline
-        if s.contains(',') || s.contains('"') || s.contains('\n') {
+        if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
             format!("\"{}\"", s.replace('"', "\"\""))
         } else {
-            s
+            s.to_string()
         }
     }
 
@@ -393,6 +546,21 @@ impl Formatter {
     }
 }
 
+/// Keys under which API list responses wrap their items
+fn wrapper_keys() -> impl Iterator<Item = &'static str> {
+    const WRAPPER_KEYS: &[&str] = &["files", "messages", "items", "labels", "permissions", "nodes"];
+    WRAPPER_KEYS.iter().copied().chain(crate::client::partial::list_wrappers())
+}
+
+/// The wrapper key when `map` is a list response. A resource with an id is
+/// a single object even if it has a `permissions` array.
+fn list_wrapper(map: &serde_json::Map<String, serde_json::Value>) -> Option<&'static str> {
+    if map.contains_key("id") {
+        return None;
+    }
+    wrapper_keys().find(|key| matches!(map.get(*key), Some(serde_json::Value::Array(_))))
+}
+
 /// Convenience function to output a single result
 pub fn output_json<T: Serialize>(item: &T) -> io::Result<()> {
     let mut formatter = Formatter::new(OutputFormat::Json);
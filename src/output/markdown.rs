@@ -0,0 +1,133 @@
+use serde_json::Value;
+
+/// Render a response as GitHub-flavored markdown. List responses (items
+/// under `wrapper`) and arrays of objects become tables, single objects
+/// become a Field/Value table.
+/// `fields` (from `--fields`) picks and orders the table columns.
+pub fn render(value: &Value, wrapper: Option<&str>, fields: Option<&[String]>) -> String {
+    match value {
+        Value::Object(map) => {
+            match wrapper {
+                Some(wrapper) => {
+                    let items = map.get(wrapper).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+                    let mut out = table(items, &columns(items, fields, Some(wrapper)));
+                    // Paging metadata (nextPageToken, resultSizeEstimate, ...)
+                    let rest: serde_json::Map<String, Value> = map.iter()
+                        .filter(|(key, value)| *key != wrapper && !is_empty(value))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect();
+                    if !rest.is_empty() {
+                        out.push('\n');
+                        out.push_str(&key_value_table(&rest));
+                    }
+                    out
+                }
+                None => key_value_table(map),
+            }
+        }
+        Value::Array(items) => table(items, &columns(items, fields, None)),
+        scalar => format!("{}\n", cell(scalar)),
+    }
+}
+
+/// Table columns: the `--fields` selection (without a wrapper prefix), else
+/// every key that has a value somewhere, in order of first appearance
+pub fn columns(items: &[Value], fields: Option<&[String]>, wrapper: Option<&str>) -> Vec<String> {
+    if let Some(fields) = fields.filter(|f| !f.is_empty()) {
+        return fields.iter()
+            .map(|f| match wrapper {
+                Some(w) => f.strip_prefix(&format!("{}.", w)).unwrap_or(f).to_string(),
+                None => f.clone(),
+            })
+            .collect();
+    }
+    let mut columns: Vec<String> = Vec::new();
+    for item in items {
+        match item {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    if !is_empty(value) && !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            _ if !columns.iter().any(|c| c == "value") => columns.push("value".to_string()),
+            _ => {}
+        }
+    }
+    columns
+}
+
+/// Header and delimiter rows
+pub fn header(columns: &[String]) -> String {
+    let names: Vec<String> = columns.iter().map(|c| escape(c)).collect();
+    format!("| {} |\n|{}\n", names.join(" | "), " --- |".repeat(columns.len()))
+}
+
+/// One table row. Dotted columns (`name.fullName`) read nested values.
+pub fn row(item: &Value, columns: &[String]) -> String {
+    let cells: Vec<String> = columns.iter()
+        .map(|column| match item {
+            Value::Object(_) => lookup(item, column).map(cell).unwrap_or_default(),
+            scalar if column == "value" => cell(scalar),
+            _ => String::new(),
+        })
+        .collect();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+fn table(items: &[Value], columns: &[String]) -> String {
+    if items.is_empty() || columns.is_empty() {
+        return "_No results_\n".to_string();
+    }
+    let mut out = header(columns);
+    for item in items {
+        out.push_str(&row(item, columns));
+    }
+    out
+}
+
+fn key_value_table(map: &serde_json::Map<String, Value>) -> String {
+    let mut out = header(&["Field".to_string(), "Value".to_string()]);
+    for (key, value) in map.iter().filter(|(_, value)| !is_empty(value)) {
+        out.push_str(&format!("| {} | {} |\n", escape(key), cell(value)));
+    }
+    out
+}
+
+/// Typed responses serialize absent fields as null or []
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |v, part| v.get(part))
+}
+
+/// Cell text: scalars as-is, lists of scalars comma-separated, anything else compact JSON
+fn cell(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.iter().all(|v| !v.is_object() && !v.is_array()) => {
+            items.iter().map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }).collect::<Vec<_>>().join(", ")
+        }
+        other => other.to_string(),
+    };
+    escape(&text)
+}
+
+/// Keep a value inside its cell: pipes escaped, line breaks as `<br>`
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
@@ -1,5 +1,11 @@
+pub mod budget;
+pub mod flatten;
 mod formatter;
+mod markdown;
 pub mod pagination;
+pub mod query;
+pub mod redact;
+mod yaml;
 
 pub use formatter::{Formatter, OutputFormat, output_json, output_jsonl, output_toon};
 pub use pagination::{
@@ -156,6 +156,10 @@ impl<T> PaginatedResult<T> {
     }
 }
 
+/// Boxed future returned by [`Paginator::fetch_page`]
+pub type PageFuture<'a, T, E> =
+    Pin<Box<dyn std::future::Future<Output = Result<PagedResponse<T>, E>> + Send + 'a>>;
+
 /// Trait for creating paginated streams
 pub trait Paginator {
     type Item: Send;
@@ -165,7 +169,7 @@ pub trait Paginator {
     fn fetch_page(
         &self,
         page_token: Option<&str>,
-    ) -> Pin<Box<dyn std::future::Future<Output = Result<PagedResponse<Self::Item>, Self::Error>> + Send + '_>>;
+    ) -> PageFuture<'_, Self::Item, Self::Error>;
 }
 
 /// Create a stream that yields items from all pages
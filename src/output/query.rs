@@ -0,0 +1,1195 @@
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::sync::RwLock;
+
+use crate::error::WorkspaceError;
+
+/// A parsed `--query` expression: a subset of jq covering paths, iteration,
+/// pipes, predicates, construction and the common builtins
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Identity,
+    Literal(Value),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Iterate(Box<Expr>),
+    /// `expr?`: errors produce no output
+    Try(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(Expr, Expr)>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+static QUERY: RwLock<Option<Query>> = RwLock::new(None);
+
+/// Install the `--query` expression applied to all output. Call once at startup.
+pub fn configure(query: Option<Query>) {
+    *QUERY.write().unwrap_or_else(|e| e.into_inner()) = query;
+}
+
+/// Whether a `--query` expression is installed
+pub fn active() -> bool {
+    QUERY.read().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Run the installed expression on a value. Without one the value passes through.
+pub fn apply(value: Value) -> Result<Vec<Value>, String> {
+    match *QUERY.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref query) => query.run(&value),
+        None => Ok(vec![value]),
+    }
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Self, WorkspaceError> {
+        let tokens = tokenize(source).map_err(|e| invalid(source, &e))?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.pipe().map_err(|e| invalid(source, &e))?;
+        if let Some(token) = parser.peek() {
+            return Err(invalid(source, &format!("unexpected {:?}", token)));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Evaluate against one input; like jq, an expression can produce any number of outputs
+    pub fn run(&self, input: &Value) -> Result<Vec<Value>, String> {
+        eval(&self.expr, input)
+    }
+}
+
+fn invalid(source: &str, reason: &str) -> WorkspaceError {
+    WorkspaceError::Config(format!("Invalid --query expression '{}': {}", source, reason))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Pipe,
+    Comma,
+    Colon,
+    Semicolon,
+    Question,
+    Op(BinOp),
+    Minus,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        i += 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '.' if next.is_some_and(|n| n.is_ascii_alphabetic() || n == '_') => {
+                let start = i;
+                while i < chars.len() && ident(chars[i]) {
+                    i += 1;
+                }
+                Token::Field(chars[start..i].iter().collect())
+            }
+            '.' => Token::Dot,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '|' => Token::Pipe,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            '?' => Token::Question,
+            '+' => Token::Op(BinOp::Add),
+            '-' => Token::Minus,
+            '*' => Token::Op(BinOp::Mul),
+            '/' => Token::Op(BinOp::Div),
+            '%' => Token::Op(BinOp::Rem),
+            '=' if next == Some('=') => {
+                i += 1;
+                Token::Op(BinOp::Eq)
+            }
+            '!' if next == Some('=') => {
+                i += 1;
+                Token::Op(BinOp::Ne)
+            }
+            '<' | '>' => {
+                let or_equal = next == Some('=');
+                if or_equal {
+                    i += 1;
+                }
+                Token::Op(match (c, or_equal) {
+                    ('<', false) => BinOp::Lt,
+                    ('<', true) => BinOp::Le,
+                    ('>', false) => BinOp::Gt,
+                    _ => BinOp::Ge,
+                })
+            }
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated string".to_string()),
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') => {
+                            let escaped = chars.get(i + 1).ok_or("unterminated string")?;
+                            s.push(match escaped {
+                                'n' => '\n',
+                                't' => '\t',
+                                'r' => '\r',
+                                other => *other,
+                            });
+                            i += 2;
+                        }
+                        Some(other) => {
+                            s.push(*other);
+                            i += 1;
+                        }
+                    }
+                }
+                Token::Str(s)
+            }
+            c if c.is_ascii_digit() => {
+                let start = i - 1;
+                while i < chars.len()
+                    && (chars[i].is_ascii_digit() || (chars[i] == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())))
+                {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                Token::Num(text.parse().map_err(|_| format!("invalid number '{}'", text))?)
+            }
+            c if ident(c) => {
+                let start = i - 1;
+                while i < chars.len() && ident(chars[i]) {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            other => return Err(format!("unexpected character '{}'", other)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref t) if *t == token => Ok(()),
+            Some(t) => Err(format!("expected {:?}, found {:?}", token, t)),
+            None => Err(format!("expected {:?} at end of expression", token)),
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, String> {
+        let left = self.comma()?;
+        if self.eat(&Token::Pipe) {
+            return Ok(Expr::Pipe(Box::new(left), Box::new(self.pipe()?)));
+        }
+        Ok(left)
+    }
+
+    fn comma(&mut self) -> Result<Expr, String> {
+        let mut left = self.or()?;
+        while self.eat(&Token::Comma) {
+            left = Expr::Comma(Box::new(left), Box::new(self.or()?));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison()?;
+        while self.keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.additive()?;
+        if let Some(Token::Op(op)) = self.peek().cloned() {
+            if matches!(op, BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge) {
+                self.pos += 1;
+                return Ok(Expr::Binary(op, Box::new(left), Box::new(self.additive()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(BinOp::Add)) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op @ (BinOp::Mul | BinOp::Div | BinOp::Rem))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Minus) {
+            return Ok(Expr::Neg(Box::new(self.postfix()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            match self.peek().cloned() {
+                Some(Token::Field(name)) => {
+                    self.pos += 1;
+                    expr = Expr::Field(Box::new(expr), name);
+                }
+                Some(Token::Dot) if matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_) | Token::LBracket)) => {
+                    self.pos += 1;
+                    if let Some(Token::Str(name)) = self.peek().cloned() {
+                        self.pos += 1;
+                        expr = Expr::Field(Box::new(expr), name);
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    expr = self.bracket_suffix(expr)?;
+                }
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    expr = Expr::Try(Box::new(expr));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// After `[`: `[]`, `[index]` or `[from:to]`
+    fn bracket_suffix(&mut self, base: Expr) -> Result<Expr, String> {
+        if self.eat(&Token::RBracket) {
+            return Ok(Expr::Iterate(Box::new(base)));
+        }
+        let from = if self.peek() == Some(&Token::Colon) { None } else { Some(Box::new(self.pipe()?)) };
+        if self.eat(&Token::Colon) {
+            let to = if self.peek() == Some(&Token::RBracket) { None } else { Some(Box::new(self.pipe()?)) };
+            self.expect(Token::RBracket)?;
+            return Ok(Expr::Slice(Box::new(base), from, to));
+        }
+        self.expect(Token::RBracket)?;
+        let index = from.ok_or("empty index")?;
+        Ok(Expr::Index(Box::new(base), index))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Dot) => match self.peek().cloned() {
+                Some(Token::Str(name)) => {
+                    self.pos += 1;
+                    Ok(Expr::Field(Box::new(Expr::Identity), name))
+                }
+                _ => Ok(Expr::Identity),
+            },
+            Some(Token::Field(name)) => Ok(Expr::Field(Box::new(Expr::Identity), name)),
+            Some(Token::Num(n)) => Ok(Expr::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::LParen) => {
+                let expr = self.pipe()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::LBracket) => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Expr::Array(None));
+                }
+                let expr = self.pipe()?;
+                self.expect(Token::RBracket)?;
+                Ok(Expr::Array(Some(Box::new(expr))))
+            }
+            Some(Token::LBrace) => self.object(),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ => {
+                    let mut args = Vec::new();
+                    if self.eat(&Token::LParen) {
+                        args.push(self.pipe()?);
+                        while self.eat(&Token::Semicolon) {
+                            args.push(self.pipe()?);
+                        }
+                        self.expect(Token::RParen)?;
+                    }
+                    check_builtin(&name, args.len())?;
+                    Ok(Expr::Call(name, args))
+                }
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    /// `{a, "b": .x, (.k): .v}`
+    fn object(&mut self) -> Result<Expr, String> {
+        let mut entries = Vec::new();
+        if self.eat(&Token::RBrace) {
+            return Ok(Expr::Object(entries));
+        }
+        loop {
+            let (key, shorthand) = match self.next() {
+                Some(Token::Ident(name)) | Some(Token::Str(name)) => (
+                    Expr::Literal(Value::String(name.clone())),
+                    Some(Expr::Field(Box::new(Expr::Identity), name)),
+                ),
+                Some(Token::LParen) => {
+                    let key = self.pipe()?;
+                    self.expect(Token::RParen)?;
+                    (key, None)
+                }
+                other => return Err(format!("invalid object key {:?}", other)),
+            };
+            let value = if self.eat(&Token::Colon) {
+                self.or()?
+            } else {
+                shorthand.ok_or("computed object keys need a value")?
+            };
+            entries.push((key, value));
+            if self.eat(&Token::RBrace) {
+                return Ok(Expr::Object(entries));
+            }
+            self.expect(Token::Comma)?;
+        }
+    }
+}
+
+/// Builtins and their arities
+const BUILTINS: &[(&str, usize)] = &[
+    ("empty", 0), ("not", 0), ("length", 0), ("keys", 0), ("values", 0), ("type", 0),
+    ("add", 0), ("any", 0), ("all", 0), ("min", 0), ("max", 0), ("sort", 0), ("unique", 0),
+    ("reverse", 0), ("first", 0), ("last", 0), ("tostring", 0), ("tonumber", 0),
+    ("ascii_downcase", 0), ("ascii_upcase", 0), ("to_entries", 0), ("from_entries", 0),
+    ("select", 1), ("map", 1), ("has", 1), ("contains", 1), ("startswith", 1), ("endswith", 1),
+    ("sort_by", 1), ("unique_by", 1), ("group_by", 1), ("min_by", 1), ("max_by", 1),
+    ("join", 1), ("split", 1), ("first", 1), ("last", 1), ("limit", 2),
+];
+
+fn check_builtin(name: &str, arity: usize) -> Result<(), String> {
+    if BUILTINS.contains(&(name, arity)) {
+        Ok(())
+    } else if BUILTINS.iter().any(|(n, _)| *n == name) {
+        Err(format!("{} does not take {} argument(s)", name, arity))
+    } else {
+        Err(format!("unknown function '{}'", name))
+    }
+}
+
+fn eval(expr: &Expr, input: &Value) -> Result<Vec<Value>, String> {
+    Ok(match expr {
+        Expr::Identity => vec![input.clone()],
+        Expr::Literal(value) => vec![value.clone()],
+        Expr::Field(base, name) => eval(base, input)?
+            .into_iter()
+            .map(|v| index(&v, &Value::String(name.clone())))
+            .collect::<Result<_, _>>()?,
+        Expr::Index(base, idx) => {
+            let mut out = Vec::new();
+            for v in eval(base, input)? {
+                for i in eval(idx, input)? {
+                    out.push(index(&v, &i)?);
+                }
+            }
+            out
+        }
+        Expr::Slice(base, from, to) => {
+            let bound = |e: &Option<Box<Expr>>| -> Result<Option<i64>, String> {
+                match e {
+                    Some(e) => eval(e, input)?.first().map(as_index).transpose(),
+                    None => Ok(None),
+                }
+            };
+            let (from, to) = (bound(from)?, bound(to)?);
+            eval(base, input)?
+                .into_iter()
+                .map(|v| slice(&v, from, to))
+                .collect::<Result<_, _>>()?
+        }
+        Expr::Iterate(base) => {
+            let mut out = Vec::new();
+            for v in eval(base, input)? {
+                out.extend(iterate(v)?);
+            }
+            out
+        }
+        Expr::Try(inner) => eval(inner, input).unwrap_or_default(),
+        Expr::Pipe(left, right) => {
+            let mut out = Vec::new();
+            for v in eval(left, input)? {
+                out.extend(eval(right, &v)?);
+            }
+            out
+        }
+        Expr::Comma(left, right) => {
+            let mut out = eval(left, input)?;
+            out.extend(eval(right, input)?);
+            out
+        }
+        Expr::Neg(inner) => eval(inner, input)?
+            .into_iter()
+            .map(|v| v.as_f64().map(|n| number(-n)).ok_or_else(|| format!("{} cannot be negated", type_name(&v))))
+            .collect::<Result<_, _>>()?,
+        Expr::Binary(op, left, right) => {
+            let mut out = Vec::new();
+            for r in eval(right, input)? {
+                for l in eval(left, input)? {
+                    out.push(binary(*op, l, &r)?);
+                }
+            }
+            out
+        }
+        Expr::And(left, right) => {
+            let mut out = Vec::new();
+            for l in eval(left, input)? {
+                if !truthy(&l) {
+                    out.push(Value::Bool(false));
+                    continue;
+                }
+                out.extend(eval(right, input)?.iter().map(|r| Value::Bool(truthy(r))));
+            }
+            out
+        }
+        Expr::Or(left, right) => {
+            let mut out = Vec::new();
+            for l in eval(left, input)? {
+                if truthy(&l) {
+                    out.push(Value::Bool(true));
+                    continue;
+                }
+                out.extend(eval(right, input)?.iter().map(|r| Value::Bool(truthy(r))));
+            }
+            out
+        }
+        Expr::Array(inner) => vec![Value::Array(match inner {
+            Some(inner) => eval(inner, input)?,
+            None => Vec::new(),
+        })],
+        Expr::Object(entries) => {
+            let mut objects = vec![Map::new()];
+            for (key, value) in entries {
+                let keys = eval(key, input)?;
+                let values = eval(value, input)?;
+                let mut next = Vec::new();
+                for object in &objects {
+                    for k in &keys {
+                        let Value::String(k) = k else {
+                            return Err(format!("object keys must be strings, not {}", type_name(k)));
+                        };
+                        for v in &values {
+                            let mut object = object.clone();
+                            object.insert(k.clone(), v.clone());
+                            next.push(object);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            objects.into_iter().map(Value::Object).collect()
+        }
+        Expr::Call(name, args) => call(name, args, input)?,
+    })
+}
+
+fn call(name: &str, args: &[Expr], input: &Value) -> Result<Vec<Value>, String> {
+    let one = |v: Value| Ok(vec![v]);
+    // Single-output argument, e.g. the needle of contains(...)
+    let arg = |i: usize| -> Result<Value, String> {
+        eval(&args[i], input)?.into_iter().next().ok_or_else(|| format!("{} argument produced no value", name))
+    };
+    let items = || -> Result<Vec<Value>, String> {
+        match input {
+            Value::Array(items) => Ok(items.clone()),
+            Value::Object(map) => Ok(map.values().cloned().collect()),
+            other => Err(format!("{} needs an array, not {}", name, type_name(other))),
+        }
+    };
+    let text = |v: &Value| -> Result<String, String> {
+        v.as_str().map(str::to_string).ok_or_else(|| format!("{} needs a string, not {}", name, type_name(v)))
+    };
+    // Sort key for sort_by / group_by / ...: all outputs of the argument
+    let keyed = || -> Result<Vec<(Value, Value)>, String> {
+        items()?.into_iter()
+            .map(|item| Ok((Value::Array(eval(&args[0], &item)?), item)))
+            .collect()
+    };
+
+    match (name, args.len()) {
+        ("empty", _) => Ok(Vec::new()),
+        ("not", _) => one(Value::Bool(!truthy(input))),
+        ("length", _) => one(match input {
+            Value::Null => number(0.0),
+            Value::Number(n) => number(n.as_f64().unwrap_or_default().abs()),
+            Value::String(s) => number(s.chars().count() as f64),
+            Value::Array(a) => number(a.len() as f64),
+            Value::Object(o) => number(o.len() as f64),
+            Value::Bool(_) => return Err("boolean has no length".to_string()),
+        }),
+        ("keys", _) => one(match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Value::Array(keys.into_iter().map(|k| Value::String(k.clone())).collect())
+            }
+            Value::Array(items) => Value::Array((0..items.len()).map(|i| number(i as f64)).collect()),
+            other => return Err(format!("{} has no keys", type_name(other))),
+        }),
+        ("values", _) => Ok(if input.is_null() { Vec::new() } else { vec![input.clone()] }),
+        ("type", _) => one(Value::String(type_name(input).to_string())),
+        ("add", _) => {
+            let mut total = Value::Null;
+            for item in items()? {
+                total = binary(BinOp::Add, total, &item)?;
+            }
+            one(total)
+        }
+        ("any", _) => one(Value::Bool(items()?.iter().any(truthy))),
+        ("all", _) => one(Value::Bool(items()?.iter().all(truthy))),
+        ("min", _) => one(items()?.into_iter().min_by(compare).unwrap_or(Value::Null)),
+        ("max", _) => one(items()?.into_iter().max_by(compare).unwrap_or(Value::Null)),
+        ("sort", _) => {
+            let mut items = items()?;
+            items.sort_by(compare);
+            one(Value::Array(items))
+        }
+        ("unique", _) => {
+            let mut items = items()?;
+            items.sort_by(compare);
+            items.dedup();
+            one(Value::Array(items))
+        }
+        ("reverse", _) => one(match input {
+            Value::String(s) => Value::String(s.chars().rev().collect()),
+            Value::Null => Value::Array(Vec::new()),
+            _ => Value::Array(items()?.into_iter().rev().collect()),
+        }),
+        ("first", 0) => one(index(input, &number(0.0))?),
+        ("last", 0) => one(index(input, &number(-1.0))?),
+        ("first", 1) => Ok(eval(&args[0], input)?.into_iter().take(1).collect()),
+        ("last", 1) => Ok(eval(&args[0], input)?.pop().into_iter().collect()),
+        ("limit", 2) => {
+            let n = as_index(&arg(0)?)?.max(0) as usize;
+            Ok(eval(&args[1], input)?.into_iter().take(n).collect())
+        }
+        ("tostring", _) => one(match input {
+            Value::String(_) => input.clone(),
+            other => Value::String(other.to_string()),
+        }),
+        ("tonumber", _) => one(match input {
+            Value::Number(_) => input.clone(),
+            Value::String(s) => number(s.trim().parse().map_err(|_| format!("cannot parse '{}' as a number", s))?),
+            other => return Err(format!("{} cannot be parsed as a number", type_name(other))),
+        }),
+        ("ascii_downcase", _) => one(Value::String(text(input)?.to_ascii_lowercase())),
+        ("ascii_upcase", _) => one(Value::String(text(input)?.to_ascii_uppercase())),
+        ("to_entries", _) => match input {
+            Value::Object(map) => one(Value::Array(map.iter()
+                .map(|(k, v)| serde_json::json!({ "key": k, "value": v }))
+                .collect())),
+            other => Err(format!("to_entries needs an object, not {}", type_name(other))),
+        },
+        ("from_entries", _) => {
+            let mut map = Map::new();
+            for entry in items()? {
+                let key = entry.get("key").or_else(|| entry.get("name")).cloned().unwrap_or(Value::Null);
+                let key = match key {
+                    Value::String(s) => s,
+                    Value::Null => return Err("from_entries needs key or name".to_string()),
+                    other => other.to_string(),
+                };
+                map.insert(key, entry.get("value").cloned().unwrap_or(Value::Null));
+            }
+            one(Value::Object(map))
+        }
+        ("select", _) => Ok(eval(&args[0], input)?
+            .iter()
+            .filter(|v| truthy(v))
+            .map(|_| input.clone())
+            .collect()),
+        ("map", _) => {
+            let mut out = Vec::new();
+            for item in items()? {
+                out.extend(eval(&args[0], &item)?);
+            }
+            one(Value::Array(out))
+        }
+        ("has", _) => one(Value::Bool(match (input, arg(0)?) {
+            (Value::Object(map), Value::String(key)) => map.contains_key(&key),
+            (Value::Array(items), key) => as_index(&key).is_ok_and(|i| i >= 0 && (i as usize) < items.len()),
+            (other, _) => return Err(format!("cannot check whether {} has a key", type_name(other))),
+        })),
+        ("contains", _) => one(Value::Bool(contains(input, &arg(0)?))),
+        ("startswith", _) => one(Value::Bool(text(input)?.starts_with(&text(&arg(0)?)?))),
+        ("endswith", _) => one(Value::Bool(text(input)?.ends_with(&text(&arg(0)?)?))),
+        ("join", _) => {
+            let separator = text(&arg(0)?)?;
+            let parts: Vec<String> = items()?.iter()
+                .map(|v| match v {
+                    Value::Null => String::new(),
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect();
+            one(Value::String(parts.join(&separator)))
+        }
+        ("split", _) => {
+            let separator = text(&arg(0)?)?;
+            one(Value::Array(text(input)?.split(separator.as_str()).map(|s| Value::String(s.to_string())).collect()))
+        }
+        ("sort_by", _) => {
+            let mut keyed = keyed()?;
+            keyed.sort_by(|a, b| compare(&a.0, &b.0));
+            one(Value::Array(keyed.into_iter().map(|(_, v)| v).collect()))
+        }
+        ("unique_by", _) | ("group_by", _) => {
+            let mut keyed = keyed()?;
+            keyed.sort_by(|a, b| compare(&a.0, &b.0));
+            let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+            for (key, item) in keyed {
+                match groups.last_mut() {
+                    Some((last, group)) if *last == key => group.push(item),
+                    _ => groups.push((key, vec![item])),
+                }
+            }
+            one(Value::Array(groups.into_iter()
+                .map(|(_, mut group)| match name {
+                    "unique_by" => group.swap_remove(0),
+                    _ => Value::Array(group),
+                })
+                .collect()))
+        }
+        ("min_by", _) => one(keyed()?.into_iter().min_by(|a, b| compare(&a.0, &b.0)).map(|(_, v)| v).unwrap_or(Value::Null)),
+        ("max_by", _) => one(keyed()?.into_iter().max_by(|a, b| compare(&a.0, &b.0)).map(|(_, v)| v).unwrap_or(Value::Null)),
+        _ => Err(format!("unknown function '{}'", name)),
+    }
+}
+
+fn index(value: &Value, key: &Value) -> Result<Value, String> {
+    match (value, key) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(map), Value::String(k)) => Ok(map.get(k).cloned().unwrap_or(Value::Null)),
+        (Value::Array(items), Value::Number(_)) => {
+            let i = as_index(key)?;
+            let i = if i < 0 { items.len() as i64 + i } else { i };
+            Ok(usize::try_from(i).ok().and_then(|i| items.get(i)).cloned().unwrap_or(Value::Null))
+        }
+        (value, key) => Err(format!("cannot index {} with {}", type_name(value), key)),
+    }
+}
+
+fn slice(value: &Value, from: Option<i64>, to: Option<i64>) -> Result<Value, String> {
+    let bounds = |len: usize| {
+        let clamp = |i: i64| if i < 0 { (len as i64 + i).max(0) as usize } else { (i as usize).min(len) };
+        let start = from.map(clamp).unwrap_or(0);
+        let end = to.map(clamp).unwrap_or(len);
+        (start, end.max(start))
+    };
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Array(items) => {
+            let (start, end) = bounds(items.len());
+            Ok(Value::Array(items[start..end].to_vec()))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let (start, end) = bounds(chars.len());
+            Ok(Value::String(chars[start..end].iter().collect()))
+        }
+        other => Err(format!("cannot slice {}", type_name(other))),
+    }
+}
+
+fn iterate(value: Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Array(items) => Ok(items),
+        Value::Object(map) => Ok(map.into_iter().map(|(_, v)| v).collect()),
+        other => Err(format!("cannot iterate over {}", type_name(&other))),
+    }
+}
+
+fn binary(op: BinOp, left: Value, right: &Value) -> Result<Value, String> {
+    let ordering = || compare(&left, right);
+    Ok(match op {
+        BinOp::Eq => Value::Bool(ordering() == Ordering::Equal),
+        BinOp::Ne => Value::Bool(ordering() != Ordering::Equal),
+        BinOp::Lt => Value::Bool(ordering() == Ordering::Less),
+        BinOp::Le => Value::Bool(ordering() != Ordering::Greater),
+        BinOp::Gt => Value::Bool(ordering() == Ordering::Greater),
+        BinOp::Ge => Value::Bool(ordering() != Ordering::Less),
+        BinOp::Add => match (left, right) {
+            (Value::Null, r) => r.clone(),
+            (l, Value::Null) => l,
+            (Value::Number(l), Value::Number(r)) => number(l.as_f64().unwrap_or_default() + r.as_f64().unwrap_or_default()),
+            (Value::String(l), Value::String(r)) => Value::String(l + r),
+            (Value::Array(mut l), Value::Array(r)) => {
+                l.extend(r.iter().cloned());
+                Value::Array(l)
+            }
+            (Value::Object(mut l), Value::Object(r)) => {
+                l.extend(r.iter().map(|(k, v)| (k.clone(), v.clone())));
+                Value::Object(l)
+            }
+            (l, r) => return Err(format!("{} and {} cannot be added", type_name(&l), type_name(r))),
+        },
+        BinOp::Sub => match (left, right) {
+            (Value::Number(l), Value::Number(r)) => number(l.as_f64().unwrap_or_default() - r.as_f64().unwrap_or_default()),
+            (Value::Array(l), Value::Array(r)) => Value::Array(l.into_iter().filter(|v| !r.contains(v)).collect()),
+            (l, r) => return Err(format!("{} and {} cannot be subtracted", type_name(&l), type_name(r))),
+        },
+        BinOp::Mul | BinOp::Div | BinOp::Rem => {
+            let (Some(l), Some(r)) = (numeric(&left), numeric(right)) else {
+                return Err(format!("{} and {} cannot be combined with {:?}", type_name(&left), type_name(right), op));
+            };
+            match op {
+                BinOp::Mul => number(l * r),
+                _ if r == 0.0 => return Err("division by zero".to_string()),
+                BinOp::Div => number(l / r),
+                _ => number((l.trunc() % r.trunc()).trunc()),
+            }
+        }
+    })
+}
+
+/// jq ordering: null < false < true < numbers < strings < arrays < objects.
+/// Google APIs send int64 fields (e.g. Drive `size`) as strings, so a
+/// numeric string compares as a number against a number.
+fn compare(left: &Value, right: &Value) -> Ordering {
+    if let (Some(l), Some(r)) = (numeric(left), numeric(right)) {
+        if left.is_number() || right.is_number() {
+            return l.partial_cmp(&r).unwrap_or(Ordering::Equal);
+        }
+    }
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    match (left, right) {
+        (Value::String(l), Value::String(r)) => l.cmp(r),
+        (Value::Array(l), Value::Array(r)) => l.iter()
+            .zip(r.iter())
+            .map(|(a, b)| compare(a, b))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or_else(|| l.len().cmp(&r.len())),
+        (Value::Object(l), Value::Object(r)) => {
+            let mut lk: Vec<&String> = l.keys().collect();
+            let mut rk: Vec<&String> = r.keys().collect();
+            lk.sort();
+            rk.sort();
+            lk.cmp(&rk).then_with(|| {
+                lk.iter()
+                    .map(|k| compare(&l[*k], &r[*k]))
+                    .find(|o| *o != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(left).cmp(&rank(right)),
+    }
+}
+
+fn contains(haystack: &Value, needle: &Value) -> bool {
+    match (haystack, needle) {
+        (Value::String(h), Value::String(n)) => h.contains(n.as_str()),
+        (Value::Array(h), Value::Array(n)) => n.iter().all(|n| h.iter().any(|h| contains(h, n))),
+        (Value::Object(h), Value::Object(n)) => n.iter().all(|(k, n)| h.get(k).is_some_and(|h| contains(h, n))),
+        (h, n) => compare(h, n) == Ordering::Equal,
+    }
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_index(value: &Value) -> Result<i64, String> {
+    value.as_f64()
+        .map(|n| n.floor() as i64)
+        .ok_or_else(|| format!("{} is not a valid index", type_name(value)))
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// Whole numbers stay integers so `1 + 1` prints `2`, not `2.0`
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9.0e15 {
+        Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(expr: &str, input: Value) -> Vec<Value> {
+        Query::parse(expr).unwrap().run(&input).unwrap()
+    }
+
+    #[test]
+    fn test_paths_and_iteration() {
+        let input = json!({"files": [{"id": "a", "owners": [{"email": "x@example.com"}]}, {"id": "b"}]});
+        assert_eq!(run(".files[].id", input.clone()), vec![json!("a"), json!("b")]);
+        assert_eq!(run(".files[0].owners[0].email", input.clone()), vec![json!("x@example.com")]);
+        assert_eq!(run(".files[-1]", input.clone()), vec![json!({"id": "b"})]);
+        assert_eq!(run(".files | length", input.clone()), vec![json!(2)]);
+        assert_eq!(run(".\"files\"[1:]", input), vec![json!([{"id": "b"}])]);
+    }
+
+    #[test]
+    fn test_select_map_and_projection() {
+        let input = json!({"files": [
+            {"id": "a", "name": "big.zip", "size": "2500000"},
+            {"id": "b", "name": "small.txt", "size": "10"},
+        ]});
+        assert_eq!(
+            run("[.files[] | select(.size > 1000000) | {id, name}]", input.clone()),
+            vec![json!([{"id": "a", "name": "big.zip"}])]
+        );
+        assert_eq!(run(".files | map(.name)", input.clone()), vec![json!(["big.zip", "small.txt"])]);
+        assert_eq!(
+            run(".files | map(select((.name | endswith(\".txt\")) and .id == \"b\")) | .[].id", input.clone()),
+            vec![json!("b")]
+        );
+        assert_eq!(
+            run(".files | sort_by(.size | tonumber) | first | {file: .name, bytes: (.size | tonumber)}", input),
+            vec![json!({"file": "small.txt", "bytes": 10})]
+        );
+    }
+
+    fn fails(expr: &str, input: Value) -> String {
+        Query::parse(expr).unwrap().run(&input).unwrap_err()
+    }
+
+    #[test]
+    fn test_path_forms() {
+        let input = json!({"a": {"b": 1}, "odd key": 2, "list": [10, 20, 30, 40], "text": "abcdef"});
+        assert_eq!(run(".", json!(3)), vec![json!(3)]);
+        assert_eq!(run(".a.b", input.clone()), vec![json!(1)]);
+        assert_eq!(run(".\"odd key\"", input.clone()), vec![json!(2)]);
+        assert_eq!(run(".a.\"b\"", input.clone()), vec![json!(1)]);
+        assert_eq!(run(".[\"a\"].b", input.clone()), vec![json!(1)]);
+        assert_eq!(run(".list[1]", input.clone()), vec![json!(20)]);
+        assert_eq!(run(".list[-1]", input.clone()), vec![json!(40)]);
+        assert_eq!(run(".list[9]", input.clone()), vec![Value::Null]);
+        assert_eq!(run(".list[1:3]", input.clone()), vec![json!([20, 30])]);
+        assert_eq!(run(".list[:2]", input.clone()), vec![json!([10, 20])]);
+        assert_eq!(run(".list[-2:]", input.clone()), vec![json!([30, 40])]);
+        assert_eq!(run(".list[3:1]", input.clone()), vec![json!([])]);
+        assert_eq!(run(".text[2:4]", input.clone()), vec![json!("cd")]);
+        assert_eq!(run(".list[.a.b]", input.clone()), vec![json!(20)]);
+        assert_eq!(run(".a[]", input.clone()), vec![json!(1)]);
+        assert_eq!(run(".missing.deeper[0]", input.clone()), vec![Value::Null]);
+        assert_eq!(run(".missing[1:]", input), vec![Value::Null]);
+    }
+
+    #[test]
+    fn test_optional_suppresses_errors() {
+        let input = json!([{"x": 1}, 2, {"x": 3}]);
+        assert_eq!(run("[.[] | .x?]", input.clone()), vec![json!([1, 3])]);
+        assert_eq!(run(".[1][]?", input.clone()), Vec::<Value>::new());
+        assert_eq!(run(".[0].x.y?", input), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_pipe_comma_and_literals() {
+        assert_eq!(run("1, 2 | . * 10", Value::Null), vec![json!(10), json!(20)]);
+        assert_eq!(run(".a, .b", json!({"a": 1, "b": 2})), vec![json!(1), json!(2)]);
+        assert_eq!(run(".[] | .id", json!([{"id": "a"}, {"id": "b"}])), vec![json!("a"), json!("b")]);
+        assert_eq!(
+            run("null, true, false, 1.5, \"tab\\there\"", Value::Null),
+            vec![Value::Null, json!(true), json!(false), json!(1.5), json!("tab\there")]
+        );
+        assert_eq!(run("(1, 2) + 10", Value::Null), vec![json!(11), json!(12)]);
+    }
+
+    #[test]
+    fn test_construction() {
+        let input = json!({"id": "f1", "name": "a.txt", "tags": ["x", "y"], "k": "dyn"});
+        assert_eq!(run("[]", Value::Null), vec![json!([])]);
+        assert_eq!(run("[.tags[], .id]", input.clone()), vec![json!(["x", "y", "f1"])]);
+        assert_eq!(run("{}", Value::Null), vec![json!({})]);
+        assert_eq!(run("{id, \"file\": .name}", input.clone()), vec![json!({"id": "f1", "file": "a.txt"})]);
+        assert_eq!(run("{(.k): .id}", input.clone()), vec![json!({"dyn": "f1"})]);
+        // One object per combination of values, like jq
+        assert_eq!(run("{id, tag: .tags[]}", input), vec![json!({"id": "f1", "tag": "x"}), json!({"id": "f1", "tag": "y"})]);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(run("1 + 2 * 3", Value::Null), vec![json!(7)]);
+        assert_eq!(run("(1 + 2) * 3", Value::Null), vec![json!(9)]);
+        assert_eq!(run("10 - 4 - 3", Value::Null), vec![json!(3)]);
+        assert_eq!(run("10 / 4", Value::Null), vec![json!(2.5)]);
+        assert_eq!(run("7 % 3", Value::Null), vec![json!(1)]);
+        assert_eq!(run("-.a", json!({"a": 2})), vec![json!(-2)]);
+        assert_eq!(run(".size * 2", json!({"size": "21"})), vec![json!(42)]);
+        assert_eq!(run("\"ab\" + \"cd\"", Value::Null), vec![json!("abcd")]);
+        assert_eq!(run("[1, 2] + [3]", Value::Null), vec![json!([1, 2, 3])]);
+        assert_eq!(run("[1, 2, 2, 3] - [2]", Value::Null), vec![json!([1, 3])]);
+        assert_eq!(run("{a: 1, b: 1} + {b: 2}", Value::Null), vec![json!({"a": 1, "b": 2})]);
+        assert_eq!(run("null + 1, 1 + null", Value::Null), vec![json!(1), json!(1)]);
+    }
+
+    #[test]
+    fn test_comparisons_and_logic() {
+        let cases = [
+            ("1 == 1", true), ("1 != 1", false), ("1 < 2", true), ("2 <= 2", true), ("3 > 2", true), ("2 >= 3", false),
+            ("\"10\" > 9", true), ("\"b\" > \"a\"", true), ("[1, 2] < [1, 3]", true), ("{a: 1} == {a: 1}", true),
+            // jq's type order: null < false < true < numbers < strings < arrays < objects
+            ("null < false", true), ("false < true", true), ("true < 0", true), ("0 < \"a\"", true),
+            ("\"a\" < []", true), ("[] < {}", true),
+            ("true and null", false), ("1 and \"x\"", true), ("false or 1", true), ("null or false", false),
+            ("1 == 1 and 2 > 1", true), ("false or 1 == 1", true),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(run(expr, Value::Null), vec![json!(expected)], "{}", expr);
+        }
+        assert_eq!(run(".[] | not", json!([null, false, 0, ""])), vec![json!(true), json!(true), json!(false), json!(false)]);
+    }
+
+    #[test]
+    fn test_builtins() {
+        let people = json!([{"n": "b", "age": 30}, {"n": "a", "age": 25}, {"n": "c", "age": 30}]);
+        let cases: Vec<(&str, Value, Vec<Value>)> = vec![
+            ("empty", json!(1), vec![]),
+            ("length", json!("héllo"), vec![json!(5)]),
+            ("length", json!({"a": 1}), vec![json!(1)]),
+            ("length", json!(-3), vec![json!(3)]),
+            ("length", Value::Null, vec![json!(0)]),
+            ("keys", json!({"b": 1, "a": 2}), vec![json!(["a", "b"])]),
+            ("keys", json!(["x", "y"]), vec![json!([0, 1])]),
+            (".[] | values", json!([1, null, 2]), vec![json!(1), json!(2)]),
+            (".[] | type", json!([null, true, 1, "s", [], {}]), ["null", "boolean", "number", "string", "array", "object"].map(|t| json!(t)).to_vec()),
+            ("add", json!([1, 2, 3]), vec![json!(6)]),
+            ("add", json!(["a", "b"]), vec![json!("ab")]),
+            ("add", json!([]), vec![Value::Null]),
+            ("any", json!([false, 1]), vec![json!(true)]),
+            ("all", json!([true, null]), vec![json!(false)]),
+            ("min, max", json!([3, 1, 2]), vec![json!(1), json!(3)]),
+            ("min", json!([]), vec![Value::Null]),
+            ("sort", json!([3, "a", null, true, 1]), vec![json!([null, true, 1, 3, "a"])]),
+            ("unique", json!([2, 1, 2]), vec![json!([1, 2])]),
+            ("reverse", json!([1, 2]), vec![json!([2, 1])]),
+            ("reverse", json!("abc"), vec![json!("cba")]),
+            ("first, last", json!([1, 2, 3]), vec![json!(1), json!(3)]),
+            ("first(.[] | select(. > 1))", json!([1, 2, 3]), vec![json!(2)]),
+            ("last(.[])", json!([1, 2, 3]), vec![json!(3)]),
+            ("[limit(2; .[])]", json!([1, 2, 3]), vec![json!([1, 2])]),
+            ("tostring", json!({"a": 1}), vec![json!("{\"a\":1}")]),
+            ("tostring", json!("s"), vec![json!("s")]),
+            ("tonumber", json!(" 42 "), vec![json!(42)]),
+            ("ascii_downcase, ascii_upcase", json!("MiXed"), vec![json!("mixed"), json!("MIXED")]),
+            ("to_entries", json!({"a": 1}), vec![json!([{"key": "a", "value": 1}])]),
+            ("from_entries", json!([{"key": "a", "value": 1}, {"name": "b", "value": 2}, {"key": 3}]), vec![json!({"a": 1, "b": 2, "3": null})]),
+            ("map(select(.age > 26) | .n)", people.clone(), vec![json!(["b", "c"])]),
+            ("has(\"a\"), has(\"z\")", json!({"a": null}), vec![json!(true), json!(false)]),
+            ("has(1), has(2)", json!([0, 1]), vec![json!(true), json!(false)]),
+            ("contains(\"ell\")", json!("hello"), vec![json!(true)]),
+            ("contains([\"b\"])", json!(["abc", "d"]), vec![json!(true)]),
+            ("contains({a: {b: 1}})", json!({"a": {"b": 1, "c": 2}}), vec![json!(true)]),
+            ("contains({a: 2})", json!({"a": 1}), vec![json!(false)]),
+            ("startswith(\"he\"), endswith(\"lo\")", json!("hello"), vec![json!(true), json!(true)]),
+            ("join(\"-\")", json!(["a", 1, null, true]), vec![json!("a-1--true")]),
+            ("split(\", \")", json!("a, b"), vec![json!(["a", "b"])]),
+            ("sort_by(.age) | map(.n)", people.clone(), vec![json!(["a", "b", "c"])]),
+            ("sort_by(.age, .n) | map(.n)", json!([{"n": "z", "age": 1}, {"n": "y", "age": 1}]), vec![json!(["y", "z"])]),
+            ("group_by(.age) | map(map(.n))", people.clone(), vec![json!([["a"], ["b", "c"]])]),
+            ("unique_by(.age) | map(.n)", people.clone(), vec![json!(["a", "b"])]),
+            ("min_by(.age).n, max_by(.n).n", people, vec![json!("a"), json!("c")]),
+        ];
+        for (expr, input, expected) in cases {
+            assert_eq!(run(expr, input), expected, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(Query::parse(".files[").is_err());
+        assert!(Query::parse("frobnicate(.x)").is_err());
+        assert!(Query::parse("select(.a; .b)").is_err());
+        assert!(Query::parse(".a | .b").unwrap().run(&json!({"a": 1})).is_err());
+        assert_eq!(Query::parse(".a.b?").unwrap().run(&json!({"a": 1})).unwrap(), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("", "unexpected end of expression"),
+            (".a 1", "unexpected Num"),
+            (".a |", "unexpected end of expression"),
+            ("\"open", "unterminated string"),
+            (".a & .b", "unexpected character '&'"),
+            ("(.a", "expected RParen"),
+            (".[]]", "unexpected RBracket"),
+            (".a[]  [", "unexpected end of expression"),
+            ("{(.k)}", "computed object keys need a value"),
+            ("{1: 2}", "invalid object key"),
+            ("{a: 1 b: 2}", "expected Comma"),
+            ("nope", "unknown function 'nope'"),
+            ("map", "map does not take 0 argument(s)"),
+            ("length(1)", "length does not take 1 argument(s)"),
+        ];
+        for (expr, reason) in cases {
+            let message = Query::parse(expr).unwrap_err().to_string();
+            assert!(message.contains("Invalid --query expression"), "{}: {}", expr, message);
+            assert!(message.contains(reason), "{}: {}", expr, message);
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let cases = [
+            (".[0]", json!({"a": 1}), "cannot index object with 0"),
+            (".a", json!([1]), "cannot index array with \"a\""),
+            (".[]", json!(1), "cannot iterate over number"),
+            (".[1:]", json!(true), "cannot slice boolean"),
+            ("1 / 0", Value::Null, "division by zero"),
+            ("1 % 0", Value::Null, "division by zero"),
+            ("1 + \"a\"", Value::Null, "number and string cannot be added"),
+            ("\"a\" - \"b\"", Value::Null, "string and string cannot be subtracted"),
+            ("[] * 2", Value::Null, "array and number cannot be combined"),
+            ("-\"a\"", Value::Null, "string cannot be negated"),
+            ("{(1): 2}", Value::Null, "object keys must be strings, not number"),
+            ("length", json!(true), "boolean has no length"),
+            ("keys", json!(1), "number has no keys"),
+            ("tonumber", json!("x"), "cannot parse 'x' as a number"),
+            ("tonumber", json!([]), "array cannot be parsed as a number"),
+            ("to_entries", json!([]), "to_entries needs an object, not array"),
+            ("from_entries", json!([{"value": 1}]), "from_entries needs key or name"),
+            ("map(.)", json!("s"), "map needs an array, not string"),
+            ("ascii_upcase", json!(1), "ascii_upcase needs a string, not number"),
+            ("has(\"a\")", json!("s"), "cannot check whether string has a key"),
+            ("join(empty)", json!([]), "join argument produced no value"),
+            (".[\"a\"]", json!([]), "cannot index array"),
+            (".x[0]", json!({"x": "s"}), "cannot index string with 0"),
+            (".a[.x]", json!({"a": [1]}), "cannot index array with null"),
+        ];
+        for (expr, input, reason) in cases {
+            let message = fails(expr, input);
+            assert!(message.contains(reason), "{}: {}", expr, message);
+        }
+    }
+}
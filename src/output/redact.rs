@@ -0,0 +1,268 @@
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+use std::sync::RwLock;
+
+use crate::error::{Result, WorkspaceError};
+
+/// Kinds of personal data `--redact` can mask
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Email,
+    Phone,
+    Url,
+}
+
+impl Kind {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "email" | "emails" => Some(Self::Email),
+            "phone" | "phones" => Some(Self::Phone),
+            "url" | "urls" => Some(Self::Url),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::Url => "url",
+        }
+    }
+}
+
+/// Replaces personal data in output strings with placeholders such as
+/// `[email:1f3a9c20]`. Placeholders come from an HMAC under a key drawn
+/// once per run, so one address always gets the same placeholder within a
+/// run but placeholders cannot be matched across runs.
+pub struct Redactor {
+    kinds: Vec<Kind>,
+    key: hmac::Key,
+}
+
+impl Redactor {
+    pub fn new(kinds: Vec<Kind>) -> Self {
+        let mut secret = [0u8; 32];
+        SystemRandom::new().fill(&mut secret).expect("system random number generator failed");
+        Self { kinds, key: hmac::Key::new(hmac::HMAC_SHA256, &secret) }
+    }
+
+    /// Parse a `--redact` list such as `emails,phones,urls`. `none` (or an
+    /// empty list) turns redaction off, e.g. to override the config default.
+    pub fn parse(spec: &str) -> Result<Option<Self>> {
+        let mut kinds = Vec::new();
+        for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            if name.eq_ignore_ascii_case("none") {
+                return Ok(None);
+            }
+            let kind = Kind::parse(name).ok_or_else(|| WorkspaceError::Config(format!(
+                "Invalid --redact kind '{}': expected emails, phones, urls or none", name
+            )))?;
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        Ok((!kinds.is_empty()).then(|| Self::new(kinds)))
+    }
+
+    /// Mask every string in a value. Keys are left alone.
+    pub fn redact(&self, value: Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.redact_text(&text)),
+            Value::Array(items) => Value::Array(items.into_iter().map(|v| self.redact(v)).collect()),
+            Value::Object(map) => Value::Object(map.into_iter().map(|(k, v)| (k, self.redact(v))).collect()),
+            other => other,
+        }
+    }
+
+    /// Mask one string. URLs go first so an address inside a link is masked with it.
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for kind in [Kind::Url, Kind::Email, Kind::Phone] {
+            if !self.kinds.contains(&kind) {
+                continue;
+            }
+            let spans = match kind {
+                Kind::Url => find_urls(&text),
+                Kind::Email => find_emails(&text),
+                Kind::Phone => find_phones(&text),
+            };
+            if spans.is_empty() {
+                continue;
+            }
+            let mut out = String::with_capacity(text.len());
+            let mut last = 0;
+            for (start, end) in spans {
+                out.push_str(&text[last..start]);
+                out.push_str(&self.placeholder(kind, &text[start..end]));
+                last = end;
+            }
+            out.push_str(&text[last..]);
+            text = out;
+        }
+        text
+    }
+
+    fn placeholder(&self, kind: Kind, found: &str) -> String {
+        // Spelling variants of one address or number share a placeholder
+        let normalized = match kind {
+            Kind::Email => found.to_lowercase(),
+            Kind::Phone => found.chars().filter(|c| c.is_ascii_digit() || *c == '+').collect(),
+            Kind::Url => found.to_string(),
+        };
+        let tag = hmac::sign(&self.key, format!("{}:{}", kind.label(), normalized).as_bytes());
+        let hex: String = tag.as_ref()[..4].iter().map(|b| format!("{:02x}", b)).collect();
+        format!("[{}:{}]", kind.label(), hex)
+    }
+}
+
+static REDACTOR: RwLock<Option<Redactor>> = RwLock::new(None);
+
+/// Install the `--redact` filter applied to all output. Call once at startup.
+pub fn configure(redactor: Option<Redactor>) {
+    *REDACTOR.write().unwrap_or_else(|e| e.into_inner()) = redactor;
+}
+
+/// Mask a value with the installed filter. Without one the value passes through.
+pub fn apply(value: Value) -> Value {
+    match *REDACTOR.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref redactor) => redactor.redact(value),
+        None => value,
+    }
+}
+
+/// Mask plain-text output (`docs get --text`, `slides get`)
+pub fn text(text: String) -> String {
+    match *REDACTOR.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref redactor) => redactor.redact_text(&text),
+        None => text,
+    }
+}
+
+fn find_urls(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let lower = text.to_ascii_lowercase();
+    let mut spans = Vec::new();
+    let mut from = 0;
+    while let Some(found) = ["http://", "https://"].iter().filter_map(|s| lower[from..].find(s)).min() {
+        let start = from + found;
+        let mut end = start;
+        while end < bytes.len() && !bytes[end].is_ascii_whitespace() && !b"\"'<>[]{}|\\^`".contains(&bytes[end]) {
+            end += 1;
+        }
+        // Sentence punctuation after a link is not part of it
+        while end > start && b".,;:!?)".contains(&bytes[end - 1]) {
+            end -= 1;
+        }
+        spans.push((start, end));
+        from = end.max(start + 1);
+    }
+    spans
+}
+
+fn find_emails(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || b"._%+-".contains(&b);
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b == b'.' || b == b'-';
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for (at, _) in text.match_indices('@') {
+        if spans.last().is_some_and(|&(_, end)| at < end) {
+            continue;
+        }
+        let floor = spans.last().map(|&(_, end)| end).unwrap_or(0);
+        let mut start = at;
+        while start > floor && is_local(bytes[start - 1]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < bytes.len() && is_domain(bytes[end]) {
+            end += 1;
+        }
+        while end > at + 1 && (bytes[end - 1] == b'.' || bytes[end - 1] == b'-') {
+            end -= 1;
+        }
+        let domain = &text[at + 1..end];
+        let tld = domain.rsplit('.').next().unwrap_or_default();
+        if start < at && domain.contains('.') && tld.len() >= 2 && tld.bytes().all(|b| b.is_ascii_alphabetic()) {
+            spans.push((start, end));
+        }
+    }
+    spans
+}
+
+/// Phone numbers: 7 to 15 digits written with a leading `+`, an area code in
+/// parentheses, or at least two separators (`555 123 4567`, `555-123-4567`).
+/// Bare digit runs are left alone since they are usually ids or sizes, and
+/// dates and IPv4 addresses are skipped.
+fn find_phones(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_part = |b: u8| b.is_ascii_digit() || b" -.()".contains(&b);
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let boundary = i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || b"+-./_:".contains(&bytes[i - 1]));
+        if !boundary || !(b.is_ascii_digit() || b == b'+' || b == b'(') {
+            i += 1;
+            continue;
+        }
+        let mut end = i + 1;
+        while end < bytes.len() && is_part(bytes[end]) {
+            end += 1;
+        }
+        while end > i && !bytes[end - 1].is_ascii_digit() {
+            end -= 1;
+        }
+        let followed = end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || b"/_:@".contains(&bytes[end]));
+        if end > i && !followed && looks_like_phone(&text[i..end]) {
+            spans.push((i, end));
+            i = end;
+        } else {
+            i = end.max(i + 1);
+        }
+    }
+    spans
+}
+
+fn looks_like_phone(candidate: &str) -> bool {
+    let digits = candidate.bytes().filter(u8::is_ascii_digit).count();
+    if !(7..=15).contains(&digits) {
+        return false;
+    }
+    let groups: Vec<&str> = candidate.split(|c: char| !c.is_ascii_digit()).filter(|g| !g.is_empty()).collect();
+    let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+    let dotted = candidate.contains('.') && !candidate.contains([' ', '-', '(']);
+    if lengths == [4, 2, 2] || (dotted && groups.len() == 4 && lengths.iter().all(|&n| n <= 3)) {
+        return false;
+    }
+    candidate.starts_with('+') || candidate.starts_with('(') || groups.len() >= 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_each_kind_consistently() {
+        let redactor = Redactor::parse("emails,phones,urls").unwrap().unwrap();
+        let text = "Ann <Ann.Lee@example.com> wrote: call +1 555-123-4567 or see https://example.com/a?u=ann@example.com.";
+        let masked = redactor.redact_text(text);
+        let email = redactor.placeholder(Kind::Email, "ann.lee@example.com");
+        let phone = redactor.placeholder(Kind::Phone, "+1 555-123-4567");
+        let url = redactor.placeholder(Kind::Url, "https://example.com/a?u=ann@example.com");
+        assert_eq!(masked, format!("Ann <{}> wrote: call {} or see {}.", email, phone, url));
+        assert_eq!(redactor.redact_text("ANN.LEE@example.com"), email);
+        assert_eq!(redactor.redact_text("+15551234567"), redactor.redact_text("+1 (555) 123-4567"));
+    }
+
+    #[test]
+    fn leaves_ids_dates_and_sizes_alone() {
+        let redactor = Redactor::parse("emails,phones").unwrap().unwrap();
+        for text in ["2025-01-15T14:00:00Z", "1048576", "18c2f0a9b3d4e5f6", "10.0.12.1", "v1.2.3", "spaces/AAAA1234567"] {
+            assert_eq!(redactor.redact_text(text), text);
+        }
+        assert!(Redactor::parse("none").unwrap().is_none());
+        assert!(Redactor::parse("emails,ssn").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use serde_json::Value;
+
+/// Render a value as a YAML block document
+pub fn render(value: &Value) -> String {
+    let mut out = String::new();
+    node(value, 0, &mut out);
+    out
+}
+
+/// Render a value as one entry of a top-level YAML sequence (for streaming)
+pub fn sequence_item(value: &Value) -> String {
+    let mut out = String::new();
+    node(&Value::Array(vec![value.clone()]), 0, &mut out);
+    out
+}
+
+fn node(value: &Value, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                out.push_str(&format!("{}{}:", pad, string(key)));
+                entry(value, indent, out);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                if is_block(item) {
+                    // Render the item one level deeper, then put the dash on its first line
+                    let mut block = String::new();
+                    node(item, indent + 2, &mut block);
+                    out.push_str(&format!("{}- {}", pad, &block[indent + 2..]));
+                } else {
+                    out.push_str(&format!("{}- {}\n", pad, scalar(item)));
+                }
+            }
+        }
+        _ => out.push_str(&format!("{}{}\n", pad, scalar(value))),
+    }
+}
+
+/// The rest of a `key:` line
+fn entry(value: &Value, indent: usize, out: &mut String) {
+    if is_block(value) {
+        out.push('\n');
+        node(value, indent + 2, out);
+    } else {
+        out.push_str(&format!(" {}\n", scalar(value)));
+    }
+}
+
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => string(s),
+        Value::Object(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+    }
+}
+
+/// Plain when unambiguous, else double-quoted (a JSON string is valid YAML)
+fn string(s: &str) -> String {
+    const RESERVED: &[&str] = &["true", "false", "null", "yes", "no", "on", "off", "y", "n", "~"];
+    let first = s.chars().next();
+    let needs_quotes = s.is_empty()
+        || s.trim() != s
+        || RESERVED.contains(&s.to_lowercase().as_str())
+        || first.is_some_and(|c| c.is_ascii_digit() || "-?:,[]{}#&*!|>'\"%@`.+".contains(c))
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.ends_with(':')
+        || s.chars().any(char::is_control);
+    if needs_quotes {
+        serde_json::to_string(s).unwrap_or_else(|_| format!("\"{}\"", s))
+    } else {
+        s.to_string()
+    }
+}
@@ -85,8 +85,1723 @@ fn test_all_services_have_help() {
         let output = workspace_cli()
             .args([service, "--help"])
             .output()
-            .expect(&format!("Failed to execute {} --help", service));
+            .unwrap_or_else(|_| panic!("Failed to execute {} --help", service));
 
         assert!(output.status.success(), "Help failed for {}", service);
     }
 }
+
+/// Serve a single canned JSON response on a local port.
+/// Returns the base URL and a handle yielding the raw request that was received.
+fn serve_once(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let (url, handle) = serve_sequence(vec![response]);
+    (url, std::thread::spawn(move || handle.join().unwrap().remove(0)))
+}
+
+/// Serve raw HTTP responses on a local port, one connection each, in order.
+/// `{base}` in a response is replaced with the server's URL (e.g. for `Location`).
+/// Returns the base URL and a handle yielding the raw requests that were received.
+fn serve_sequence(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let base = url.clone();
+
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().expect("Failed to accept");
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).expect("Failed to read");
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            // Read the rest of the body so assertions can inspect it
+            let text = String::from_utf8_lossy(&request).to_lowercase();
+            let header_end = text.find("\r\n\r\n").map(|i| i + 4).unwrap_or(request.len());
+            let content_length: usize = text.lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(0);
+            while request.len() < header_end + content_length {
+                let n = stream.read(&mut buf).expect("Failed to read");
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.replace("{base}", &base).as_bytes()).expect("Failed to write");
+            requests.push(String::from_utf8_lossy(&request).to_string());
+        }
+        requests
+    });
+
+    (url, handle)
+}
+
+/// Isolated config dir so a developer's real config.toml never leaks into tests
+fn isolated_config_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("workspace-cli-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_service_endpoint_override() {
+    let (url, server) = serve_once(r#"{"labels":[{"id":"INBOX","name":"INBOX","type":"system"}]}"#);
+    let config_dir = isolated_config_dir("endpoint");
+
+    let output = workspace_cli()
+        .args(["gmail", "labels", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_ENDPOINT_GMAIL", format!("{}/gmail/v1", url))
+        .output()
+        .expect("Failed to execute");
+
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(request.starts_with("GET /gmail/v1/users/me/labels "), "request: {}", request);
+    assert!(request.to_lowercase().contains("authorization: bearer test-token"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("INBOX"));
+}
+
+#[test]
+fn test_base_url_override() {
+    let (url, server) = serve_once(r#"{"items":[{"id":"list1","title":"My Tasks"}]}"#);
+    let config_dir = isolated_config_dir("base-url");
+
+    let output = workspace_cli()
+        .args(["tasks", "lists", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(request.starts_with("GET /tasks/v1/users/@me/lists "), "request: {}", request);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("My Tasks"));
+}
+
+#[test]
+fn test_drive_download_through_api_client() {
+    let (url, server) = serve_once("file-contents");
+    let config_dir = isolated_config_dir("download");
+    let target = config_dir.join("downloaded.txt");
+
+    let output = workspace_cli()
+        .args(["drive", "download", "abc123", "--output"])
+        .arg(&target)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_ENDPOINT_DRIVE", format!("{}/drive/v3", url))
+        .output()
+        .expect("Failed to execute");
+
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(request.starts_with("GET /drive/v3/files/abc123?alt=media&supportsAllDrives=true"), "request: {}", request);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "file-contents");
+}
+
+#[test]
+fn test_drive_upload_dry_run() {
+    let config_dir = isolated_config_dir("upload-dry-run");
+    let source = config_dir.join("report.txt");
+    std::fs::write(&source, "hello").unwrap();
+    let trace_file = config_dir.join("trace.jsonl");
+    let _ = std::fs::remove_file(&trace_file);
+
+    let output = workspace_cli()
+        .args(["drive", "upload", "--dry-run", "--parent", "folder1"])
+        .arg(format!("--trace={}", trace_file.display()))
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", "http://127.0.0.1:9")
+        .output()
+        .expect("Failed to execute");
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let preview: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(preview["dry_run"], true);
+    assert_eq!(preview["method"], "POST");
+    assert!(preview["url"].as_str().unwrap().starts_with("http://127.0.0.1:9/upload/drive/v3/files?uploadType=multipart"));
+    assert_eq!(preview["body"]["media"]["size"], 5);
+    assert_eq!(preview["body"]["metadata"]["parents"][0], "folder1");
+    // The dry run stops like a normal exit, so the trace summary is still written
+    let trace = std::fs::read_to_string(&trace_file).unwrap();
+    assert!(trace.contains(r#""trace":"summary""#), "trace: {}", trace);
+}
+
+#[test]
+fn test_fanout_commands_stop_at_first_dry_run_preview() {
+    let config_dir = isolated_config_dir("fanout-dry-run");
+    for args in [&["drive", "tree", "root"][..], &["chat", "unread"][..]] {
+        let output = workspace_cli()
+            .args(args)
+            .args(["--dry-run", "--no-cache"])
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+            .env("WORKSPACE_API_BASE_URL", "http://127.0.0.1:9")
+            .output()
+            .expect("Failed to execute");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{:?} stderr: {}", args, stderr);
+        assert!(!stderr.contains("Warning") && !stderr.contains("Dry run"), "{:?} stderr: {}", args, stderr);
+        // Only the preview is printed, not an empty result after it
+        let preview: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .unwrap_or_else(|e| panic!("{:?}: {} in {}", args, e, String::from_utf8_lossy(&output.stdout)));
+        assert_eq!(preview["dry_run"], true);
+        assert_eq!(preview["method"], "GET");
+    }
+}
+
+#[test]
+fn test_drive_upload_resume_completed_session() {
+    let (url, server) = serve_once(r#"{"id":"file1","name":"big.bin","mimeType":"application/octet-stream"}"#);
+    let config_dir = isolated_config_dir("upload-resume");
+    let source = config_dir.join("big.bin");
+    let size: u64 = 6 * 1024 * 1024;
+    std::fs::write(&source, vec![0u8; size as usize]).unwrap();
+
+    // Persist a session as an interrupted run would have left it
+    let modified = std::fs::metadata(&source).unwrap().modified().unwrap()
+        .duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+    let sessions_path = config_dir.join("workspace-cli").join("upload_sessions.json");
+    std::fs::create_dir_all(sessions_path.parent().unwrap()).unwrap();
+    let sessions = serde_json::json!({
+        std::fs::canonicalize(&source).unwrap().to_string_lossy(): {
+            "sessionUri": format!("{}/upload/session1", url),
+            "fingerprint": format!("{}:{}", size, modified),
+            "fileSize": size,
+            "mimeType": "application/octet-stream",
+            "confirmedOffset": 4194304,
+            "createdAt": chrono::Utc::now().timestamp()
+        }
+    });
+    std::fs::write(&sessions_path, sessions.to_string()).unwrap();
+
+    let output = workspace_cli()
+        .args(["drive", "upload", "--resume"])
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .output()
+        .expect("Failed to execute");
+
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(request.starts_with("PUT /upload/session1 "), "request: {}", request);
+    assert!(request.to_lowercase().contains(&format!("content-range: bytes */{}", size)));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("file1"));
+    let remaining: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&sessions_path).unwrap()).unwrap();
+    assert_eq!(remaining, serde_json::json!({}));
+}
+
+/// A file of `size` zero bytes with a stored resumable session at `url`, for `drive upload --resume`
+fn stored_upload_session(config_dir: &std::path::Path, size: u64, url: &str) -> std::path::PathBuf {
+    let source = config_dir.join("big.bin");
+    std::fs::write(&source, vec![0u8; size as usize]).unwrap();
+
+    let modified = std::fs::metadata(&source).unwrap().modified().unwrap()
+        .duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+    let sessions_path = config_dir.join("workspace-cli").join("upload_sessions.json");
+    std::fs::create_dir_all(sessions_path.parent().unwrap()).unwrap();
+    let sessions = serde_json::json!({
+        std::fs::canonicalize(&source).unwrap().to_string_lossy(): {
+            "sessionUri": format!("{}/upload/session1", url),
+            "fingerprint": format!("{}:{}", size, modified),
+            "fileSize": size,
+            "mimeType": "application/octet-stream",
+            "confirmedOffset": 0,
+            "createdAt": chrono::Utc::now().timestamp()
+        }
+    });
+    std::fs::write(&sessions_path, sessions.to_string()).unwrap();
+    source
+}
+
+/// `308 Resume Incomplete`, with the server's persisted range when it has one
+fn resume_incomplete(range_end: Option<u64>) -> String {
+    format!(
+        "HTTP/1.1 308 Resume Incomplete\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+        range_end.map(|end| format!("Range: bytes=0-{}\r\n", end)).unwrap_or_default()
+    )
+}
+
+const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+
+#[test]
+fn test_drive_upload_continues_from_server_offset_after_chunk_failure() {
+    let size: u64 = 6 * 1024 * 1024;
+    let half = size / 2;
+    let (url, server) = serve_sequence(vec![
+        resume_incomplete(None),
+        // The server keeps the first half of the chunk, then fails
+        UNAVAILABLE.to_string(),
+        resume_incomplete(Some(half - 1)),
+        json_response(r#"{"id":"file1","name":"big.bin","mimeType":"application/octet-stream"}"#),
+    ]);
+    let config_dir = isolated_config_dir("upload-chunk-retry");
+    let source = stored_upload_session(&config_dir, size, &url);
+
+    let output = workspace_cli()
+        .args(["drive", "upload", "--resume"])
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .output()
+        .expect("Failed to execute");
+
+    let requests: Vec<String> = server.join().unwrap().into_iter().map(|r| r.to_lowercase()).collect();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(requests[1].contains(&format!("content-range: bytes 0-{}/{}", size - 1, size)));
+    assert!(requests[2].contains(&format!("content-range: bytes */{}", size)), "request: {}", requests[2]);
+    // Only the half the server did not persist is sent again
+    assert!(requests[3].contains(&format!("content-range: bytes {}-{}/{}", half, size - 1, size)), "request: {}", requests[3]);
+    assert!(requests[3].contains(&format!("content-length: {}", half)));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("file1"));
+}
+
+#[test]
+fn test_drive_upload_retry_budget_is_per_chunk() {
+    let chunk: u64 = 8 * 1024 * 1024;
+    let size = chunk + 1024 * 1024;
+    // One failure per chunk, with a budget of one retry: both chunks still go through
+    let (url, server) = serve_sequence(vec![
+        resume_incomplete(None),
+        UNAVAILABLE.to_string(),
+        resume_incomplete(None),
+        resume_incomplete(Some(chunk - 1)),
+        UNAVAILABLE.to_string(),
+        resume_incomplete(Some(chunk - 1)),
+        json_response(r#"{"id":"file1","name":"big.bin","mimeType":"application/octet-stream"}"#),
+    ]);
+    let config_dir = isolated_config_dir("upload-per-chunk-budget");
+    let source = stored_upload_session(&config_dir, size, &url);
+
+    let output = workspace_cli()
+        .args(["drive", "upload", "--resume"])
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_MAX_RETRIES", "1")
+        .output()
+        .expect("Failed to execute");
+
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(requests.len(), 7);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("file1"));
+}
+
+#[test]
+fn test_drive_upload_gives_up_when_308_makes_no_progress() {
+    let size: u64 = 6 * 1024 * 1024;
+    let (url, server) = serve_sequence(vec![
+        resume_incomplete(None),
+        resume_incomplete(None),
+        resume_incomplete(None),
+    ]);
+    let config_dir = isolated_config_dir("upload-no-progress");
+    let source = stored_upload_session(&config_dir, size, &url);
+
+    let output = workspace_cli()
+        .args(["drive", "upload", "--resume"])
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_MAX_RETRIES", "1")
+        .output()
+        .expect("Failed to execute");
+
+    assert_eq!(server.join().unwrap().len(), 3);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Upload made no progress past byte 0"), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_replay_gmail_get_extracts_body() {
+    let config_dir = isolated_config_dir("replay-gmail");
+    let output = workspace_cli()
+        .args(["gmail", "get", "msg123", "--format", "json", "--replay"])
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/gmail_get"))
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .output()
+        .expect("Failed to execute");
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let message: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(message["subject"], "Quarterly numbers");
+    assert_eq!(message["body"], "Revenue is up 12% this quarter.\r\n");
+}
+
+#[test]
+fn test_replay_docs_get_markdown() {
+    let config_dir = isolated_config_dir("replay-docs");
+    let output = workspace_cli()
+        .args(["docs", "get", "doc123", "--markdown", "--replay"])
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/docs_get"))
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .output()
+        .expect("Failed to execute");
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# Project Plan\n\n# Overview\n"), "stdout: {}", stdout);
+    assert!(stdout.contains("Ship the **beta** in March."));
+}
+
+#[test]
+fn test_record_then_replay_scrubs_auth() {
+    let (url, server) = serve_once(r#"{"items":[{"id":"list1","title":"Recorded Tasks"}]}"#);
+    let config_dir = isolated_config_dir("record");
+    let cassettes = config_dir.join("cassettes");
+    let _ = std::fs::remove_dir_all(&cassettes);
+
+    let recorded = workspace_cli()
+        .args(["tasks", "lists", "--format", "json", "--record"])
+        .arg(&cassettes)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "secret-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+    assert!(recorded.status.success(), "stderr: {}", String::from_utf8_lossy(&recorded.stderr));
+
+    let files: Vec<_> = std::fs::read_dir(&cassettes).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(files.len(), 1);
+    let cassette = std::fs::read_to_string(&files[0]).unwrap();
+    assert!(!cassette.contains("secret-token"), "cassette: {}", cassette);
+
+    // Server is gone; replay must be served from the cassette alone
+    let replayed = workspace_cli()
+        .args(["tasks", "lists", "--format", "json", "--replay"])
+        .arg(&cassettes)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    assert!(replayed.status.success(), "stderr: {}", String::from_utf8_lossy(&replayed.stderr));
+    assert_eq!(recorded.stdout, replayed.stdout);
+}
+
+#[test]
+fn test_replay_chat_unread_skips_read_and_muted_spaces() {
+    let config_dir = isolated_config_dir("replay-chat-unread");
+    let output = workspace_cli()
+        .args(["chat", "unread", "--since", "all", "--format", "json", "--replay"])
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/chat_unread"))
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .output()
+        .expect("Failed to execute");
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped 1 muted spaces"));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["totalUnreadSpaces"], 1);
+    assert_eq!(result["totalUnreadMessages"], 2);
+    assert_eq!(result["spaces"][0]["spaceName"], "spaces/AAA");
+    assert_eq!(result["spaces"][0]["messages"][0]["text"], "Release notes are ready");
+}
+
+#[test]
+fn test_record_scrubs_upload_session_uri() {
+    let size: u64 = 6 * 1024 * 1024;
+    let (url, server) = serve_sequence(vec![
+        "HTTP/1.1 200 OK\r\nLocation: {base}/upload/drive/v3/files?uploadType=resumable&upload_id=secret-session\r\nX-GUploader-UploadID: secret-session\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        json_response(r#"{"id":"file1","name":"big.bin","mimeType":"application/octet-stream"}"#),
+    ]);
+    let config_dir = isolated_config_dir("record-upload");
+    let cassettes = config_dir.join("cassettes");
+    let _ = std::fs::remove_dir_all(&cassettes);
+    let source = config_dir.join("big.bin");
+    std::fs::write(&source, vec![0u8; size as usize]).unwrap();
+
+    let recorded = workspace_cli()
+        .args(["drive", "upload", "--format", "json", "--record"])
+        .arg(&cassettes)
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+    assert!(recorded.status.success(), "stderr: {}", String::from_utf8_lossy(&recorded.stderr));
+
+    let mut files: Vec<_> = std::fs::read_dir(&cassettes).unwrap().map(|e| e.unwrap().path()).collect();
+    files.sort();
+    assert_eq!(files.len(), 2);
+    for file in &files {
+        let cassette = std::fs::read_to_string(file).unwrap();
+        assert!(!cassette.contains("secret-session"), "cassette: {}", cassette);
+    }
+    let init: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&files[0]).unwrap()).unwrap();
+    assert!(init["response"]["headers"]["location"].as_str().unwrap().contains("upload_id=%5BREDACTED%5D"));
+
+    // The scrubbed session URI still lines up with the recorded chunk upload
+    let replayed = workspace_cli()
+        .args(["drive", "upload", "--format", "json", "--replay"])
+        .arg(&cassettes)
+        .arg(&source)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    assert!(replayed.status.success(), "stderr: {}", String::from_utf8_lossy(&replayed.stderr));
+    assert_eq!(recorded.stdout, replayed.stdout);
+}
+
+#[test]
+fn test_record_streams_media_without_storing_it() {
+    let (url, server) = serve_sequence(vec![
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 13\r\nConnection: close\r\n\r\nfile-contents".to_string(),
+    ]);
+    let config_dir = isolated_config_dir("record-download");
+    let cassettes = config_dir.join("cassettes");
+    let _ = std::fs::remove_dir_all(&cassettes);
+    let target = config_dir.join("downloaded.bin");
+
+    let output = workspace_cli()
+        .args(["drive", "download", "abc123", "--record"])
+        .arg(&cassettes)
+        .arg("--output")
+        .arg(&target)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "file-contents");
+
+    let files: Vec<_> = std::fs::read_dir(&cassettes).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(files.len(), 1);
+    let cassette: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&files[0]).unwrap()).unwrap();
+    assert_eq!(cassette["response"]["omitted"], true);
+    assert!(cassette["response"].get("base64").is_none(), "cassette: {}", cassette);
+    assert!(cassette["response"].get("text").is_none(), "cassette: {}", cassette);
+}
+
+#[test]
+fn test_etag_cache_serves_body_on_304() {
+    let body = r#"{"documentId":"doc1","title":"Cached Doc"}"#;
+    let (url, server) = serve_sequence(vec![
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ),
+        "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ),
+    ]);
+    let config_dir = isolated_config_dir("etag-cache");
+    let cache_dir = config_dir.join("workspace-cli").join("http");
+    let _ = std::fs::remove_dir_all(&cache_dir);
+
+    let run = |extra: &[&str], passphrase: Option<&str>| {
+        let mut command = workspace_cli();
+        command
+            .args(["docs", "get", "doc1", "--format", "json"])
+            .args(extra)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+            .env("WORKSPACE_API_BASE_URL", &url)
+            .env_remove("WORKSPACE_TOKEN_KEY_FILE");
+        match passphrase {
+            Some(p) => command.env("WORKSPACE_TOKEN_PASSPHRASE", p),
+            None => command.env_remove("WORKSPACE_TOKEN_PASSPHRASE"),
+        };
+        command.output().expect("Failed to execute")
+    };
+    let first = run(&[], None);
+    let second = run(&[], None);
+    // Another subject never sees the first one's entry
+    let other = run(&["--as", "other@example.com"], Some("cache passphrase"));
+
+    let requests = server.join().unwrap();
+    assert!(first.status.success(), "stderr: {}", String::from_utf8_lossy(&first.stderr));
+    assert!(second.status.success(), "stderr: {}", String::from_utf8_lossy(&second.stderr));
+    assert!(other.status.success(), "stderr: {}", String::from_utf8_lossy(&other.stderr));
+    assert!(!requests[0].to_lowercase().contains("if-none-match"));
+    assert!(requests[1].to_lowercase().contains("if-none-match: \"v1\""), "request: {}", requests[1]);
+    assert!(!requests[2].to_lowercase().contains("if-none-match"), "request: {}", requests[2]);
+    assert_eq!(first.stdout, second.stdout);
+    assert!(String::from_utf8_lossy(&second.stdout).contains("Cached Doc"));
+
+    // Entries are user-only, and encrypted when token encryption is on
+    let entries: Vec<String> = std::fs::read_dir(&cache_dir).unwrap()
+        .map(|e| {
+            let path = e.unwrap().path();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            }
+            std::fs::read_to_string(&path).unwrap()
+        })
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries.iter().filter(|e| e.contains("workspace-cli-encrypted-v1") && !e.contains("Cached Doc")).count(), 1);
+}
+
+/// Raw multipart/mixed batch response with one part per (content id, status, body)
+fn batch_response(parts: &[(usize, u16, &str)]) -> String {
+    let mut body = String::new();
+    for (id, status, json) in parts {
+        body.push_str(&format!(
+            "--batch_test\r\nContent-Type: application/http\r\nContent-ID: <response-{}>\r\n\r\nHTTP/1.1 {} X\r\nContent-Type: application/json\r\nRetry-After: 0\r\n\r\n{}\r\n",
+            id, status, json
+        ));
+    }
+    body.push_str("--batch_test--\r\n");
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/mixed; boundary=batch_test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+fn run_gmail_batch(name: &str, url: &str, count: usize) -> std::process::Output {
+    let config_dir = isolated_config_dir(name);
+    let input = config_dir.join("requests.json");
+    let requests: Vec<serde_json::Value> = (0..count)
+        .map(|i| serde_json::json!({ "id": format!("m{}", i), "method": "GET", "path": format!("/gmail/v1/users/me/messages/m{}", i) }))
+        .collect();
+    std::fs::write(&input, serde_json::to_string(&requests).unwrap()).unwrap();
+
+    workspace_cli()
+        .args(["batch", "gmail", "--format", "json", "--file"])
+        .arg(&input)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_ENDPOINT_GMAIL_BATCH", format!("{}/batch/gmail/v1", url))
+        .output()
+        .expect("Failed to execute")
+}
+
+#[test]
+fn test_batch_chunks_large_input_in_order() {
+    let parts: Vec<(usize, u16, &str)> = (0..100).map(|i| (i, 200, r#"{"ok":true}"#)).collect();
+    let (url, server) = serve_sequence(vec![batch_response(&parts), batch_response(&parts)]);
+
+    let output = run_gmail_batch("batch-chunks", &url, 150);
+
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(requests.len(), 2);
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["status"], "success");
+    let ids: Vec<&str> = result["results"].as_array().unwrap().iter().map(|r| r["id"].as_str().unwrap()).collect();
+    let expected: Vec<String> = (0..150).map(|i| format!("m{}", i)).collect();
+    assert_eq!(ids, expected);
+}
+
+#[test]
+fn test_batch_retries_failed_parts() {
+    let (url, server) = serve_sequence(vec![
+        batch_response(&[(0, 200, r#"{"id":"m0"}"#), (1, 503, r#"{"error":{"message":"Backend Error"}}"#)]),
+        batch_response(&[(1, 200, r#"{"id":"m1"}"#)]),
+    ]);
+
+    let output = run_gmail_batch("batch-retry", &url, 2);
+
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // Only the failed part is resent
+    assert!(requests[1].contains("Content-ID: <1>") && !requests[1].contains("Content-ID: <0>"), "request: {}", requests[1]);
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["status"], "success");
+    assert_eq!(result["results"][1]["body"]["id"], "m1");
+}
+
+#[test]
+fn test_batch_waits_for_retry_after_on_whole_batch_failure() {
+    let (url, server) = serve_sequence(vec![
+        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 2\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}".to_string(),
+        batch_response(&[(0, 200, r#"{"id":"m0"}"#)]),
+    ]);
+
+    let started = std::time::Instant::now();
+    let output = run_gmail_batch("batch-retry-after", &url, 1);
+
+    server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // The default first backoff is about half a second; Retry-After asks for two
+    assert!(started.elapsed() >= std::time::Duration::from_secs(2), "elapsed: {:?}", started.elapsed());
+}
+
+#[test]
+fn test_shared_rate_limit_state_tightens_on_retry_after() {
+    let body = r#"{"error":{"code":403,"message":"Rate Limit Exceeded"}}"#;
+    let (url, server) = serve_sequence(vec![format!(
+        "HTTP/1.1 403 Forbidden\r\nContent-Type: application/json\r\nRetry-After: 30\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )]);
+    let config_dir = isolated_config_dir("shared-rate-limit");
+    let state_path = config_dir.join("workspace-cli").join("ratelimit").join("tasks-default.json");
+    let _ = std::fs::remove_file(&state_path);
+
+    let output = workspace_cli()
+        .args(["tasks", "lists", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .env("WORKSPACE_SHARED_RATE_LIMIT", "1")
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+
+    assert!(!output.status.success());
+    let state: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&state_path).unwrap()).unwrap();
+    // Tasks refills at 0.5 tokens/sec, so a 30s Retry-After leaves ~15 tokens of debt for every process
+    assert!(state["tokens"].as_f64().unwrap() <= -14.0, "state: {}", state);
+}
+
+fn json_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[test]
+fn test_api_passthrough_pages_all_items() {
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"kind":"drive#revisionList","nextPageToken":"p2","revisions":[{"id":"r1"},{"id":"r2"}]}"#),
+        json_response(r#"{"kind":"drive#revisionList","revisions":[{"id":"r3"}]}"#),
+    ]);
+    let config_dir = isolated_config_dir("api-page-all");
+
+    let output = workspace_cli()
+        .args(["api", "GET", "/files/f1/revisions", "--service", "drive", "--query", "pageSize=2", "--page-all", "--page-delay", "0", "--format", "jsonl"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(requests[0].starts_with("GET /drive/v3/files/f1/revisions?pageSize=2"), "request: {}", requests[0]);
+    assert!(requests[1].contains("pageToken=p2"), "request: {}", requests[1]);
+    let ids: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ids, ["r1", "r2", "r3"]);
+}
+
+#[test]
+fn test_api_passthrough_post_body_from_file() {
+    let (url, server) = serve_once(r#"{"id":"filter1","action":{"addLabelIds":["STARRED"]}}"#);
+    let config_dir = isolated_config_dir("api-post");
+    let body_file = config_dir.join("filter.json");
+    std::fs::write(&body_file, r#"{"criteria":{"from":"boss@example.com"}}"#).unwrap();
+
+    let output = workspace_cli()
+        .args(["api", "POST"])
+        .arg(format!("{}/gmail/v1/users/me/settings/filters", url))
+        .arg("--body")
+        .arg(format!("@{}", body_file.display()))
+        .args(["--format", "json", "--fields", "id"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(request.starts_with("POST /gmail/v1/users/me/settings/filters "), "request: {}", request);
+    assert!(request.ends_with(r#"{"criteria":{"from":"boss@example.com"}}"#), "request: {}", request);
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(response, serde_json::json!({ "id": "filter1" }));
+}
+
+#[test]
+fn test_api_rejects_foreign_host_for_service() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let foreign = format!("http://{}/x", listener.local_addr().unwrap());
+    let config_dir = isolated_config_dir("api-foreign-host");
+
+    let output = workspace_cli()
+        .args(["api", "GET", &foreign, "--service", "drive"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .output()
+        .expect("Failed to execute");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not on the drive API host"), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // The token never left the process
+    assert_eq!(listener.accept().unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn test_bare_trace_before_subcommand_goes_to_stderr() {
+    let config_dir = isolated_config_dir("trace-bare");
+    let output = workspace_cli()
+        .args(["--trace", "schema"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .output()
+        .expect("Failed to execute");
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // `schema` ran as the subcommand instead of being taken as the trace file
+    assert!(String::from_utf8_lossy(&output.stderr).contains(r#""trace":"summary""#));
+}
+
+#[test]
+fn test_trace_writes_records_and_summary() {
+    let (url, server) = serve_once(r#"{"id":"m1","threadId":"t1","labelIds":["INBOX"]}"#);
+    let config_dir = isolated_config_dir("trace");
+    let trace_file = config_dir.join("trace.jsonl");
+    let _ = std::fs::remove_file(&trace_file);
+
+    let output = workspace_cli()
+        .args(["api", "GET", "/users/me/messages/m1", "--service", "gmail", "--query", "access_token=secret", "--format", "json"])
+        .arg(format!("--trace={}", trace_file.display()))
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+
+    server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&trace_file)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2, "trace: {:?}", lines);
+
+    let record = &lines[0];
+    assert_eq!(record["trace"], "request");
+    assert_eq!(record["service"], "gmail");
+    assert_eq!(record["method"], "GET");
+    assert_eq!(record["status"], 200);
+    assert_eq!(record["attempts"], 1);
+    assert_eq!(record["quotaUnits"], 5);
+    assert!(record["responseBytes"].as_u64().unwrap() > 0);
+    let traced_url = record["url"].as_str().unwrap();
+    assert!(!traced_url.contains("secret"), "url: {}", traced_url);
+
+    let summary = &lines[1];
+    assert_eq!(summary["trace"], "summary");
+    assert_eq!(summary["services"]["gmail"]["calls"], 1);
+    assert_eq!(summary["totalQuotaUnits"], 5);
+}
+
+#[test]
+fn test_network_config_proxy_user_agent_and_service_retries() {
+    let body = r#"{"error":{"code":503,"message":"Backend Error"}}"#;
+    let (proxy_url, proxy) = serve_sequence(vec![format!(
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )]);
+    let config_dir = isolated_config_dir("network-config");
+    std::fs::create_dir_all(config_dir.join("workspace-cli")).unwrap();
+    std::fs::write(
+        config_dir.join("workspace-cli").join("config.toml"),
+        format!(
+            "[api]\nproxy = \"{}\"\nuser_agent = \"corp-agent/1.0\"\n\n[api.services.docs]\ntimeout_seconds = 90\nmax_retries = 0\n",
+            proxy_url
+        ),
+    )
+    .unwrap();
+
+    let output = workspace_cli()
+        .args(["docs", "get", "doc1", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", "http://docs.example.invalid")
+        .output()
+        .expect("Failed to execute");
+
+    // Docs normally retries 5 times; the per-service override allows a single attempt
+    let requests = proxy.join().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Backend Error"), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(requests[0].starts_with("GET http://docs.example.invalid/v1/documents/doc1"), "request: {}", requests[0]);
+    assert!(requests[0].to_lowercase().contains("user-agent: corp-agent/1.0"), "request: {}", requests[0]);
+}
+
+#[test]
+fn test_profiles_switch_list_and_output_defaults() {
+    let config_dir = isolated_config_dir("profiles");
+    let app_dir = config_dir.join("workspace-cli");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    std::fs::write(
+        app_dir.join("config.toml"),
+        "[profiles.work]\ncredentials_path = \"/etc/work-credentials.json\"\nformat = \"json\"\n",
+    )
+    .unwrap();
+    let cli = |args: &[&str]| {
+        workspace_cli()
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env_remove("WORKSPACE_PROFILE")
+            .output()
+            .expect("Failed to execute")
+    };
+
+    assert!(!cli(&["auth", "switch", "personal"]).status.success());
+    let switched = cli(&["auth", "switch", "work"]);
+    assert!(switched.status.success(), "stderr: {}", String::from_utf8_lossy(&switched.stderr));
+
+    let list = cli(&["auth", "list", "--format", "json"]);
+    let profiles: serde_json::Value = serde_json::from_slice(&list.stdout).unwrap();
+    let active: Vec<&str> = profiles.as_array().unwrap()
+        .iter()
+        .filter(|p| p["active"] == true)
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(active, ["work"]);
+    assert!(profiles.as_array().unwrap().iter().any(|p| p["name"] == "default"));
+
+    // Without --format the active profile's format (json) applies
+    let listed: serde_json::Value = serde_json::from_slice(&cli(&["auth", "list"]).stdout).unwrap();
+    assert!(listed.is_array());
+
+    // --profile overrides the active profile for one command
+    let status = cli(&["auth", "status", "--profile", "default"]);
+    let status: serde_json::Value = serde_json::from_slice(&status.stdout).unwrap();
+    assert_eq!(status["profile"], "default");
+    assert!(status["token_cache_path"].as_str().unwrap().ends_with("token_cache.json"));
+
+    let status: serde_json::Value = serde_json::from_slice(&cli(&["auth", "status"]).stdout).unwrap();
+    assert_eq!(status["profile"], "work");
+    assert!(status["token_cache_path"].as_str().unwrap().ends_with("token_cache_work.json"));
+}
+
+#[test]
+fn test_auth_login_device_flow() {
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"device_code":"dev-123","user_code":"WXYZ-1234","verification_url":"https://www.google.com/device","expires_in":1800,"interval":1}"#),
+        json_response(r#"{"access_token":"ya29.device","refresh_token":"1//refresh","expires_in":3599,"token_type":"Bearer"}"#),
+    ]);
+    let config_dir = isolated_config_dir("device-login");
+    let credentials = config_dir.join("credentials.json");
+    std::fs::write(
+        &credentials,
+        serde_json::json!({
+            "installed": {
+                "client_id": "client-id.apps.googleusercontent.com",
+                "client_secret": "client-secret",
+                "token_uri": format!("{}/token", url),
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = workspace_cli()
+        .args(["auth", "login", "--device", "--services", "calendar", "--profile", "device-test", "--credentials"])
+        .arg(&credentials)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(requests[0].starts_with("POST /device/code"), "request: {}", requests[0]);
+    assert!(requests[0].contains("client_id=client-id.apps.googleusercontent.com"));
+    assert!(requests[1].starts_with("POST /token"), "request: {}", requests[1]);
+    assert!(requests[1].contains("dev-123"), "request: {}", requests[1]);
+
+    let prompt: serde_json::Value = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .find_map(|l| serde_json::from_str(l).ok())
+        .expect("device prompt on stderr");
+    assert_eq!(prompt["user_code"], "WXYZ-1234");
+    assert_eq!(prompt["verification_url"], "https://www.google.com/device");
+    let token_cache = std::fs::read_to_string(config_dir.join("workspace-cli").join("token_cache_device-test.json")).unwrap();
+    assert!(token_cache.contains("1//refresh"));
+}
+
+#[test]
+fn test_auth_login_device_refuses_scopes_google_does_not_grant() {
+    let config_dir = isolated_config_dir("device-login-scopes");
+    let credentials = config_dir.join("credentials.json");
+    std::fs::write(
+        &credentials,
+        r#"{"installed":{"client_id":"client-id.apps.googleusercontent.com","client_secret":"client-secret"}}"#,
+    )
+    .unwrap();
+    let login = |extra: &[&str]| {
+        workspace_cli()
+            .args(["auth", "login", "--device", "--credentials"])
+            .arg(&credentials)
+            .args(extra)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            // Nothing listens here: a request would fail with a network error instead
+            .env("WORKSPACE_API_BASE_URL", "http://127.0.0.1:9")
+            .output()
+            .expect("Failed to execute")
+    };
+
+    let bare = login(&[]);
+    assert!(!bare.status.success());
+    let stderr = String::from_utf8_lossy(&bare.stderr);
+    assert!(stderr.contains("--services"), "stderr: {}", stderr);
+
+    let gmail = login(&["--services", "gmail,calendar,docs"]);
+    assert!(!gmail.status.success());
+    let stderr = String::from_utf8_lossy(&gmail.stderr);
+    assert!(stderr.contains("device flow does not grant https://mail.google.com/, https://www.googleapis.com/auth/drive"), "stderr: {}", stderr);
+    assert!(!stderr.contains("calendar"), "stderr: {}", stderr);
+}
+
+#[test]
+fn test_auth_login_services_limits_scopes() {
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"device_code":"dev-456","user_code":"ABCD-5678","verification_url":"https://www.google.com/device","expires_in":1800,"interval":1}"#),
+        json_response(r#"{"access_token":"ya29.calendar","refresh_token":"1//calendar","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response(r#"{"scope":"https://www.googleapis.com/auth/calendar","expires_in":3599}"#),
+    ]);
+    let config_dir = isolated_config_dir("login-services");
+    let credentials = config_dir.join("credentials.json");
+    std::fs::write(
+        &credentials,
+        serde_json::json!({
+            "installed": {
+                "client_id": "client-id.apps.googleusercontent.com",
+                "client_secret": "client-secret",
+                "token_uri": format!("{}/token", url),
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let cli = |args: &[&str]| {
+        workspace_cli()
+            .args(args)
+            .args(["--profile", "scoped"])
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_API_BASE_URL", &url)
+            .output()
+            .expect("Failed to execute")
+    };
+
+    let login = cli(&["auth", "login", "--device", "--services", "calendar", "--credentials", credentials.to_str().unwrap()]);
+    let requests = server.join().unwrap();
+    assert!(login.status.success(), "stderr: {}", String::from_utf8_lossy(&login.stderr));
+    assert!(requests[0].ends_with("scope=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fcalendar"), "request: {}", requests[0]);
+    assert!(requests[2].starts_with("GET /tokeninfo?access_token=ya29.calendar"), "request: {}", requests[2]);
+
+    let status: serde_json::Value = serde_json::from_slice(&cli(&["auth", "status"]).stdout).unwrap();
+    assert_eq!(status["granted_scopes"], serde_json::json!(["https://www.googleapis.com/auth/calendar"]));
+
+    // Gmail was not granted; without a terminal there is no prompt, just a clear error
+    let gmail = cli(&["gmail", "list"]);
+    assert!(!gmail.status.success());
+    let stderr = String::from_utf8_lossy(&gmail.stderr);
+    assert!(stderr.contains("does not grant gmail access"), "stderr: {}", stderr);
+}
+
+#[test]
+fn test_encrypted_token_storage_migrates_plaintext() {
+    let config_dir = isolated_config_dir("token-encryption");
+    let app_dir = config_dir.join("workspace-cli");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    let credentials = app_dir.join("credentials.json");
+    std::fs::write(
+        &credentials,
+        r#"{"installed":{"client_id":"client-id","client_secret":"client-secret"}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        app_dir.join("config.toml"),
+        format!("[profiles.enc]\ncredentials_path = {:?}\n", credentials),
+    )
+    .unwrap();
+
+    // Plaintext files as written before encryption was enabled
+    let scope = "https://www.googleapis.com/auth/drive";
+    let token_file = app_dir.join("tokens_enc.json");
+    let cache_file = app_dir.join("token_cache_enc.json");
+    std::fs::write(
+        &token_file,
+        serde_json::json!({"access_token": "ya29.cached", "refresh_token": null, "expires_at": null, "scopes": [scope]}).to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+        &cache_file,
+        serde_json::json!([{"scopes": [scope], "token": {"access_token": "ya29.cached", "refresh_token": "1//secret-refresh", "expires_at": null, "id_token": null}}]).to_string(),
+    )
+    .unwrap();
+
+    let export = |passphrase: Option<&str>| {
+        let mut command = workspace_cli();
+        command
+            .args(["auth", "export", "--unmasked", "--profile", "enc"])
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env_remove("WORKSPACE_ACCESS_TOKEN")
+            .env_remove("WORKSPACE_TOKEN_KEY_FILE");
+        match passphrase {
+            Some(p) => command.env("WORKSPACE_TOKEN_PASSPHRASE", p),
+            None => command.env_remove("WORKSPACE_TOKEN_PASSPHRASE"),
+        };
+        command.output().expect("Failed to execute")
+    };
+
+    let first = export(Some("correct horse"));
+    assert!(first.status.success(), "stderr: {}", String::from_utf8_lossy(&first.stderr));
+    let result: serde_json::Value = serde_json::from_slice(&first.stdout).unwrap();
+    assert_eq!(result["access_token"], "ya29.cached");
+    assert_eq!(result["storage_type"], "encrypted_file");
+
+    // Both files were rewritten encrypted
+    for file in [&token_file, &cache_file] {
+        let content = std::fs::read_to_string(file).unwrap();
+        assert!(content.contains("workspace-cli-encrypted-v1"), "{}: {}", file.display(), content);
+        assert!(!content.contains("ya29.cached") && !content.contains("1//secret-refresh"));
+    }
+
+    let again = export(Some("correct horse"));
+    assert!(again.status.success(), "stderr: {}", String::from_utf8_lossy(&again.stderr));
+
+    let wrong = export(Some("wrong"));
+    assert!(!wrong.status.success());
+    assert!(String::from_utf8_lossy(&wrong.stderr).contains("wrong passphrase"));
+
+    let missing = export(None);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("WORKSPACE_TOKEN_PASSPHRASE"));
+}
+
+#[test]
+fn test_keyless_metadata_and_external_account_credentials() {
+    let labels = r#"{"labels":[{"id":"INBOX","name":"INBOX","type":"system"}]}"#;
+    let config_dir = isolated_config_dir("keyless");
+
+    // Metadata server identity, delegating to a user through signJwt
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"access_token":"ya29.metadata","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response("robot@project.iam.gserviceaccount.com"),
+        json_response(r#"{"keyId":"key-1","signedJwt":"header.payload.signature"}"#),
+        json_response(r#"{"access_token":"ya29.delegated","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response(labels),
+    ]);
+    let output = workspace_cli()
+        .args(["gmail", "labels", "--format", "json", "--as", "user@example.com"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_CREDENTIAL_SOURCE", "metadata")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .env_remove("WORKSPACE_ACCESS_TOKEN")
+        .env_remove("GOOGLE_APPLICATION_CREDENTIALS")
+        .output()
+        .expect("Failed to execute");
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(requests[0].starts_with("GET /computeMetadata/v1/instance/service-accounts/default/token?scopes=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fcloud-platform"), "request: {}", requests[0]);
+    assert!(requests[0].to_lowercase().contains("metadata-flavor: google"));
+    assert!(requests[1].starts_with("GET /computeMetadata/v1/instance/service-accounts/default/email"), "request: {}", requests[1]);
+    assert!(requests[2].starts_with("POST /v1/projects/-/serviceAccounts/robot@project.iam.gserviceaccount.com:signJwt"), "request: {}", requests[2]);
+    assert!(requests[2].to_lowercase().contains("authorization: bearer ya29.metadata"));
+    assert!(requests[2].contains(r#"\"sub\":\"user@example.com\""#), "request: {}", requests[2]);
+    assert!(requests[3].starts_with("POST /token"), "request: {}", requests[3]);
+    assert!(requests[3].contains("assertion=header.payload.signature"));
+    assert!(requests[4].to_lowercase().contains("authorization: bearer ya29.delegated"), "request: {}", requests[4]);
+
+    // Workload identity federation: file-sourced subject token exchanged at STS
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"access_token":"ya29.federated","expires_in":3599,"token_type":"Bearer","issued_token_type":"urn:ietf:params:oauth:token-type:access_token"}"#),
+        json_response(labels),
+    ]);
+    let subject_token = config_dir.join("oidc-token");
+    std::fs::write(&subject_token, "eyJ.oidc.token").unwrap();
+    let external_account = config_dir.join("external-account.json");
+    std::fs::write(
+        &external_account,
+        serde_json::json!({
+            "type": "external_account",
+            "audience": "//iam.googleapis.com/projects/1/locations/global/workloadIdentityPools/ci/providers/github",
+            "subject_token_type": "urn:ietf:params:oauth:token-type:jwt",
+            "token_url": format!("{}/v1/token", url),
+            "credential_source": { "file": subject_token },
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let output = workspace_cli()
+        .args(["gmail", "labels", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_CREDENTIAL_SOURCE", "service_account")
+        .env("GOOGLE_APPLICATION_CREDENTIALS", &external_account)
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .env_remove("WORKSPACE_ACCESS_TOKEN")
+        .output()
+        .expect("Failed to execute");
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(requests[0].starts_with("POST /v1/token"), "request: {}", requests[0]);
+    assert!(requests[0].contains("subject_token=eyJ.oidc.token"), "request: {}", requests[0]);
+    assert!(requests[1].to_lowercase().contains("authorization: bearer ya29.federated"), "request: {}", requests[1]);
+}
+
+#[test]
+fn test_auth_doctor_reports_disabled_api_and_missing_scope() {
+    let error = |code: u16, message: &str| {
+        let body = serde_json::json!({"error": {"code": code, "message": message}}).to_string();
+        format!(
+            "HTTP/1.1 {} Forbidden\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            code,
+            body.len(),
+            body
+        )
+    };
+    // Everything a default login asks for, minus one Chat scope
+    let granted = workspace_cli::auth::SCOPES.iter()
+        .filter(|s| !s.ends_with("/chat.messages"))
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let (url, server) = serve_sequence(vec![
+        json_response(&serde_json::json!({"scope": granted}).to_string()),
+        json_response(r#"{"emailAddress":"me@example.com"}"#),
+        json_response(r#"{"user":{"displayName":"Me"}}"#),
+        error(403, "Google Calendar API has not been used in project 123 before or it is disabled. Enable it by visiting https://console.developers.google.com/apis/api/calendar-json.googleapis.com/overview?project=123 then retry."),
+        error(404, "Requested entity was not found."),
+        error(404, "Requested entity was not found."),
+        error(404, "Requested entity was not found."),
+        json_response(r#"{"items":[]}"#),
+        error(403, "Request had insufficient authentication scopes."),
+        json_response(r#"{"resourceName":"people/me"}"#),
+        json_response(r#"{"groups":[]}"#),
+        json_response(r#"{"users":[]}"#),
+    ]);
+    let config_dir = isolated_config_dir("doctor");
+
+    let output = workspace_cli()
+        .args(["auth", "doctor", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    let requests = server.join().unwrap();
+    assert!(!output.status.success(), "doctor should fail when a service fails");
+    assert!(requests[0].starts_with("GET /tokeninfo"), "request: {}", requests[0]);
+    assert!(requests[1].starts_with("GET /gmail/v1/users/me/profile"), "request: {}", requests[1]);
+    assert!(requests[10].starts_with("GET /v1/groups?parent=customers%2Fmy_customer&pageSize=1"), "request: {}", requests[10]);
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|_| panic!("stdout: {}", String::from_utf8_lossy(&output.stdout)));
+    let check = |name: &str| report["services"].as_array().unwrap()
+        .iter()
+        .find(|s| s["service"] == name)
+        .unwrap()
+        .clone();
+
+    assert_eq!(check("gmail")["status"], "ok");
+    assert_eq!(check("docs")["status"], "ok", "404 on the probe document still proves access");
+
+    let calendar = check("calendar");
+    assert_eq!(calendar["status"], "error");
+    assert_eq!(calendar["error"]["error_code"], "configuration_error");
+    assert!(calendar["error"]["actionable_fix"].as_str().unwrap()
+        .contains("https://console.developers.google.com/apis/api/calendar-json.googleapis.com/overview?project=123"));
+
+    let chat = check("chat");
+    assert_eq!(chat["status"], "error");
+    assert_eq!(chat["error"]["error_code"], "permission_denied");
+    assert!(chat["missing_scopes"].as_array().unwrap().contains(&serde_json::json!("https://www.googleapis.com/auth/chat.messages")));
+    assert!(chat["error"]["actionable_fix"].as_str().unwrap().contains("auth login --services"));
+
+    assert_eq!(report["errors"], 2);
+    assert_eq!(report["ok"], 9);
+}
+
+#[test]
+fn test_auth_revoke_all_profiles_and_logout_keeps_tokens_on_failure() {
+    let config_dir = isolated_config_dir("revoke");
+    let app_dir = config_dir.join("workspace-cli");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    let write_cache = |file: &str, refresh: &str| {
+        std::fs::write(
+            app_dir.join(file),
+            serde_json::json!([{"scopes": ["https://www.googleapis.com/auth/drive"], "token": {"access_token": "ya29.x", "refresh_token": refresh, "expires_at": null, "id_token": null}}]).to_string(),
+        )
+        .unwrap();
+    };
+    write_cache("token_cache.json", "1//default-refresh");
+    write_cache("token_cache_work.json", "1//work-refresh");
+    let cli = |args: &[&str], url: &str| {
+        workspace_cli()
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_API_BASE_URL", url)
+            .env_remove("WORKSPACE_PROFILE")
+            .output()
+            .expect("Failed to execute")
+    };
+
+    // A failed revocation keeps the local tokens so logout can be retried
+    let failure = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+    let (url, server) = serve_sequence(vec![failure]);
+    let logout = cli(&["auth", "logout"], &url);
+    server.join().unwrap();
+    assert!(!logout.status.success());
+    assert!(String::from_utf8_lossy(&logout.stderr).contains("--local-only"));
+    assert!(app_dir.join("token_cache.json").exists());
+
+    let (url, server) = serve_sequence(vec![json_response("{}"), json_response("{}")]);
+    let revoke = cli(&["auth", "revoke", "--all-profiles", "--format", "json"], &url);
+    let requests = server.join().unwrap();
+    assert!(revoke.status.success(), "stderr: {}", String::from_utf8_lossy(&revoke.stderr));
+    assert!(requests[0].starts_with("POST /revoke"), "request: {}", requests[0]);
+    assert!(requests[0].ends_with("token=1%2F%2Fdefault-refresh"), "request: {}", requests[0]);
+    assert!(requests[1].ends_with("token=1%2F%2Fwork-refresh"), "request: {}", requests[1]);
+
+    let results: serde_json::Value = serde_json::from_slice(&revoke.stdout).unwrap();
+    assert_eq!(results.as_array().unwrap().len(), 2);
+    assert!(results.as_array().unwrap().iter().all(|r| r["status"] == "revoked" && r["revoked"] == 1));
+    assert!(!app_dir.join("token_cache.json").exists());
+    assert!(!app_dir.join("token_cache_work.json").exists());
+}
+
+#[test]
+fn test_as_each_runs_command_per_subject() {
+    let labels = r#"{"labels":[{"id":"INBOX","name":"INBOX","type":"system"}]}"#;
+    let body = r#"{"error":{"code":403,"message":"Mail service not enabled"}}"#;
+    let forbidden = format!(
+        "HTTP/1.1 403 Forbidden\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let delegation = |token: &str| vec![
+        json_response(r#"{"access_token":"ya29.metadata","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response("robot@project.iam.gserviceaccount.com"),
+        json_response(r#"{"keyId":"key-1","signedJwt":"header.payload.signature"}"#),
+        json_response(&format!(r#"{{"access_token":"{}","expires_in":3599,"token_type":"Bearer"}}"#, token)),
+    ];
+
+    // Base login, then per subject: delegated token minted by the parent and the child's API call
+    let mut responses = vec![
+        json_response(r#"{"access_token":"ya29.metadata","expires_in":3599,"token_type":"Bearer"}"#),
+    ];
+    responses.extend(delegation("ya29.alice"));
+    responses.push(json_response(labels));
+    responses.extend(delegation("ya29.bob"));
+    responses.push(forbidden);
+    let (url, server) = serve_sequence(responses);
+
+    let config_dir = isolated_config_dir("as-each");
+    let subjects = config_dir.join("subjects.txt");
+    std::fs::write(&subjects, "# sales team\nalice@example.com\n\nbob@example.com\nalice@example.com\n").unwrap();
+
+    let output = workspace_cli()
+        .args(["gmail", "labels", "--format", "json", "--as-each-concurrency", "1", "--as-each"])
+        .arg(&subjects)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_CREDENTIAL_SOURCE", "metadata")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .env_remove("WORKSPACE_ACCESS_TOKEN")
+        .env_remove("GOOGLE_APPLICATION_CREDENTIALS")
+        .output()
+        .expect("Failed to execute");
+    let requests = server.join().unwrap();
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(requests[3].contains(r#"\"sub\":\"alice@example.com\""#), "request: {}", requests[3]);
+    assert!(requests[5].to_lowercase().contains("authorization: bearer ya29.alice"), "request: {}", requests[5]);
+    assert!(requests[8].contains(r#"\"sub\":\"bob@example.com\""#), "request: {}", requests[8]);
+    assert!(requests[10].to_lowercase().contains("authorization: bearer ya29.bob"), "request: {}", requests[10]);
+
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON output");
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["subject"], "alice@example.com");
+    assert_eq!(records[0]["labels"][0]["id"], "INBOX");
+    assert_eq!(records[1]["subject"], "bob@example.com");
+    assert_eq!(records[1]["status"], "error");
+    assert!(records[1]["error"].to_string().contains("Mail service not enabled"), "record: {}", records[1]);
+}
+
+#[test]
+fn test_as_each_keeps_command_query_when_parent_filters() {
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"access_token":"ya29.metadata","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response(r#"{"access_token":"ya29.metadata","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response("robot@project.iam.gserviceaccount.com"),
+        json_response(r#"{"keyId":"key-1","signedJwt":"header.payload.signature"}"#),
+        json_response(r#"{"access_token":"ya29.alice","expires_in":3599,"token_type":"Bearer"}"#),
+        json_response(r#"{"resultSizeEstimate":0}"#),
+    ]);
+    let config_dir = isolated_config_dir("as-each-query");
+    let subjects = config_dir.join("subjects.txt");
+    std::fs::write(&subjects, "alice@example.com\n").unwrap();
+
+    // The first --query filters the merged output; the one after `gmail list` is Gmail's search
+    let output = workspace_cli()
+        .args(["--query", "map(.subject)", "gmail", "list", "--query", "is:unread", "--format", "json", "--as-each"])
+        .arg(&subjects)
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_CREDENTIAL_SOURCE", "metadata")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .env_remove("WORKSPACE_ACCESS_TOKEN")
+        .env_remove("GOOGLE_APPLICATION_CREDENTIALS")
+        .output()
+        .expect("Failed to execute");
+    let requests = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(requests[5].starts_with("GET /gmail/v1/users/me/messages?"), "request: {}", requests[5]);
+    assert!(requests[5].contains("q=is%3Aunread"), "request: {}", requests[5]);
+    let subjects: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON output");
+    assert_eq!(subjects, serde_json::json!(["alice@example.com"]));
+}
+
+#[test]
+fn test_query_filters_before_formatting() {
+    let files = r#"{"files":[{"id":"f1","name":"video.mp4","size":"2500000","mimeType":"video/mp4"},{"id":"f2","name":"notes.txt","size":"120","mimeType":"text/plain"}]}"#;
+    let config_dir = isolated_config_dir("query");
+    let query = ".files[] | select(.size > 1000000) | {id, name}";
+
+    // Before the command, --query is the output filter; after it, drive list's own search
+    let (url, server) = serve_once(files);
+    let output = workspace_cli()
+        .args(["--query", query, "drive", "list", "--query", "trashed = false", "--format", "csv"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(request.contains("q=trashed+%3D+false") || request.contains("q=trashed%20%3D%20false"), "request: {}", request);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "id,name\nf1,video.mp4\n");
+
+    // --jq is the same filter and may follow the command
+    let (url, server) = serve_once(files);
+    let output = workspace_cli()
+        .args(["drive", "list", "--format", "json", "--no-cache", "--jq", ".files | map(.name) | join(\", \")"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), r#""video.mp4, notes.txt""#);
+
+    // Syntax errors are reported before any request is made
+    let output = workspace_cli()
+        .args(["--query", ".files[ | select(", "drive", "list"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .output()
+        .expect("Failed to execute");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --query expression"));
+
+    for args in [
+        ["--query", ".files", "--jq", ".files", "drive", "list"],
+        ["--query", ".files", "drive", "list", "--jq", ".files"],
+    ] {
+        let output = workspace_cli()
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .output()
+            .expect("Failed to execute");
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"), "args: {:?}", args);
+    }
+}
+
+#[test]
+fn test_fields_sent_as_partial_response_mask() {
+    let config_dir = isolated_config_dir("partial-fields");
+
+    let (url, server) = serve_once(r#"{"files":[{"id":"f1","name":"a.txt","mimeType":"text/plain","size":"12"}]}"#);
+    let output = workspace_cli()
+        .args(["drive", "list", "--fields", "id,name", "--format", "jsonl"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let request_line = request.lines().next().unwrap();
+    assert!(
+        request_line.contains("fields=nextPageToken%2CincompleteSearch%2Cfiles%28id%2Cname%2CmimeType%29"),
+        "{}", request_line
+    );
+
+    // Wrapper prefixes and dotted paths map onto the API's mask syntax
+    let (url, server) = serve_once(r#"{"users":[{"primaryEmail":"ann@example.com","name":{"fullName":"Ann"},"isAdmin":false}],"nextPageToken":"p2"}"#);
+    let output = workspace_cli()
+        .args(["admin", "users-list", "--domain", "example.com", "--fields", "users.primaryEmail,name.fullName", "--format", "json"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    let request = server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let request_line = request.lines().next().unwrap();
+    assert!(
+        request_line.contains("fields=nextPageToken%2Cusers%28primaryEmail%2Cname%2FfullName%29"),
+        "{}", request_line
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("ann@example.com") && stdout.contains("Ann"), "{}", stdout);
+    assert!(!stdout.contains("isAdmin"));
+
+    // Raw document JSON is masked too, but converted Markdown needs the whole body
+    let docs_get = |extra: &[&str]| {
+        let (url, server) = serve_once(r#"{"documentId":"doc1","title":"Plan"}"#);
+        let output = workspace_cli()
+            .args(["docs", "get", "doc1", "--fields", "title", "--no-cache"])
+            .args(extra)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+            .env("WORKSPACE_API_BASE_URL", &url)
+            .output()
+            .expect("Failed to execute");
+        assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        server.join().unwrap().lines().next().unwrap().to_string()
+    };
+    let request_line = docs_get(&["--format", "json"]);
+    assert!(request_line.contains("fields=title%2CdocumentId "), "{}", request_line);
+    let request_line = docs_get(&["--markdown"]);
+    assert!(!request_line.contains("fields="), "{}", request_line);
+}
+
+#[test]
+fn test_markdown_and_yaml_formats() {
+    let files = r#"{"files":[{"id":"f1","name":"a|b.txt","mimeType":"text/plain"},{"id":"f2","name":"2025 plan: draft","mimeType":"text/plain"}]}"#;
+    let config_dir = isolated_config_dir("md-yaml");
+    let run = |args: &[&str]| {
+        let (url, server) = serve_once(files);
+        let output = workspace_cli()
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+            .env("WORKSPACE_API_BASE_URL", &url)
+            .output()
+            .expect("Failed to execute");
+        server.join().unwrap();
+        assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let table = "| name | id |\n| --- | --- |\n| a\\|b.txt | f1 |\n| 2025 plan: draft | f2 |\n";
+    assert_eq!(run(&["drive", "list", "--format", "md", "--fields", "name,id", "--no-cache"]), table);
+    // The streaming (--page-all) path renders the same table
+    assert_eq!(run(&["drive", "list", "--format", "md", "--fields", "name,id", "--page-all", "--no-cache"]), table);
+
+    let yaml = run(&["drive", "list", "--format", "yaml", "--fields", "id,name", "--no-cache"]);
+    assert_eq!(yaml, "files:\n  - id: f1\n    name: a|b.txt\n  - id: f2\n    name: \"2025 plan: draft\"\nnextPageToken: null\nincompleteSearch: null\n");
+
+    let single = run(&["drive", "list", "--format", "md", "--no-cache", "--jq", ".files[0]"]);
+    assert!(single.starts_with("| Field | Value |\n| --- | --- |\n| id | f1 |\n"), "{}", single);
+}
+
+#[test]
+fn test_csv_flattens_with_stable_headers() {
+    let config_dir = isolated_config_dir("csv-flatten");
+    let (url, server) = serve_sequence(vec![
+        json_response(r#"{"files":[{"id":"f1","name":"plan, v2","mimeType":"text/plain"}],"nextPageToken":"p2"}"#),
+        json_response(r#"{"files":[{"id":"f2","name":"notes","mimeType":"text/plain","parents":["a","b"],"owners":[{"displayName":"Ann","emailAddress":"ann@example.com"},{"displayName":"Bob"}]}]}"#),
+    ]);
+    let output = workspace_cli()
+        .args(["drive", "list", "--page-all", "--format", "csv", "--fields", "id,name,parents,owners", "--no-cache"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // Columns from the second page are in the header, and the first row lines up with them
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "id,name,parents,owners.emailAddress,owners.displayName\n\
+         f1,\"plan, v2\",,,\n\
+         f2,notes,a; b,ann@example.com; ,Ann; Bob\n"
+    );
+
+    let (url, server) = serve_once(r#"{"files":[{"id":"f1","name":"plan, v2","mimeType":"text/plain","parents":["a","b"]}]}"#);
+    let output = workspace_cli()
+        .args(["drive", "list", "--format", "tsv", "--fields", "id,name,parents", "--array-separator", "|", "--no-cache"])
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .env("XDG_CACHE_HOME", &config_dir)
+        .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+        .env("WORKSPACE_API_BASE_URL", &url)
+        .output()
+        .expect("Failed to execute");
+    server.join().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "id\tname\tparents\nf1\tplan, v2\ta|b\n");
+}
+
+#[test]
+fn test_schema_prints_output_and_input() {
+    let config_dir = isolated_config_dir("schema");
+    let schema = |args: &[&str]| {
+        workspace_cli()
+            .arg("schema")
+            .args(args)
+            .args(["--format", "json"])
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .output()
+            .expect("Failed to execute")
+    };
+
+    let output = schema(&["drive", "tree"]);
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let tree: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tree["command"], "drive tree");
+    assert!(tree["output"]["properties"]["nodes"].is_object(), "{}", tree["output"]);
+    assert_eq!(tree["input"]["properties"]["folder_id"]["x-positional"], 0);
+    assert_eq!(tree["input"]["properties"]["max-depth"]["type"], "integer");
+    assert_eq!(tree["input"]["properties"]["include-permissions"]["type"], "boolean");
+    assert_eq!(tree["input"]["required"], serde_json::json!(["folder_id"]));
+
+    let output = schema(&["gmail", "get"]);
+    let get: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(get["output"]["title"], "MinimalMessage");
+    assert_eq!(get["variants"]["--full"]["title"], "Message");
+
+    let output = schema(&["drive", "nope"]);
+    assert!(!output.status.success());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error_code"], "not_found");
+}
+
+#[test]
+fn test_max_tokens_and_truncate_budget_output() {
+    let files: Vec<String> = (0..20)
+        .map(|i| format!(r#"{{"id":"f{}","name":"file {}","mimeType":"text/plain"}}"#, i, i))
+        .collect();
+    let body: &'static str = format!(r#"{{"files":[{}],"nextPageToken":"p2"}}"#, files.join(",")).leak();
+    let config_dir = isolated_config_dir("budget");
+    let run = |args: &[&str]| {
+        let (url, server) = serve_once(body);
+        let output = workspace_cli()
+            .args(["drive", "list", "--format", "json-compact", "--fields", "id,name", "--no-cache"])
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+            .env("WORKSPACE_API_BASE_URL", &url)
+            .output()
+            .expect("Failed to execute");
+        server.join().unwrap();
+        assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    // Items are dropped from the tail and the rest can be fetched with --continue
+    let first = run(&["--max-tokens", "60"]);
+    let kept = first["files"].as_array().unwrap().len();
+    assert!(kept > 0 && kept < 20, "{}", first);
+    assert_eq!(first["truncated"], true);
+    assert_eq!(first["omitted"], 20 - kept);
+    assert_eq!(first["nextPageToken"], "p2");
+    let token = first["continuation"].as_str().unwrap();
+    let rest = run(&["--continue", token]);
+    assert_eq!(rest["files"][0]["id"], format!("f{}", kept));
+    assert_eq!(rest["files"].as_array().unwrap().len(), 20 - kept);
+
+    let cut = run(&["--truncate", "name=4", "--jq", ".files[0]"]);
+    assert_eq!(cut, serde_json::json!({"id": "f0", "name": {"text": "file", "truncated": true, "originalLength": 6}}));
+}
+
+#[test]
+fn test_redact_masks_personal_data_consistently() {
+    let body = r#"{"id":"m1","threadId":"t1","labelIds":["INBOX"],"payload":{"mimeType":"text/plain","headers":[{"name":"From","value":"Ann Lee <ann@example.com>"},{"name":"To","value":"bob@example.com"}],"body":{"size":60,"data":"Q2FsbCBBbm4gKGFubkBleGFtcGxlLmNvbSkgb24gKzEgNTU1LTEyMy00NTY3"}}}"#;
+    let config_dir = isolated_config_dir("redact");
+    std::fs::create_dir_all(config_dir.join("workspace-cli")).unwrap();
+    std::fs::write(
+        config_dir.join("workspace-cli").join("config.toml"),
+        "[output]\nformat = \"json\"\nredact = [\"emails\"]\n",
+    ).unwrap();
+    let run = |args: &[&str]| {
+        let (url, server) = serve_once(body);
+        let output = workspace_cli()
+            .args(["gmail", "get", "m1", "--no-cache"])
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("XDG_CACHE_HOME", &config_dir)
+            .env("WORKSPACE_ACCESS_TOKEN", "test-token")
+            .env("WORKSPACE_API_BASE_URL", &url)
+            .output()
+            .expect("Failed to execute");
+        server.join().unwrap();
+        assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    // The config default masks emails; the same address gets the same placeholder
+    let message = run(&[]);
+    let from = message["from"].as_str().unwrap();
+    let placeholder = from.trim_start_matches("Ann Lee <").trim_end_matches('>');
+    assert!(placeholder.starts_with("[email:"), "{}", message);
+    assert_eq!(message["body"], format!("Call Ann ({}) on +1 555-123-4567", placeholder));
+    assert_ne!(message["to"], placeholder);
+
+    let message = run(&["--redact", "emails,phones"]);
+    assert!(message["body"].as_str().unwrap().contains("on [phone:"), "{}", message);
+
+    let message = run(&["--redact", "none"]);
+    assert_eq!(message["to"], "bob@example.com");
+}